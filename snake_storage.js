@@ -0,0 +1,24 @@
+// Gives the game access to the browser's local storage. Loaded by index.html after the
+// miniquad bundle, see src/storage.rs for the Rust side.
+miniquad_add_plugin({
+    name: "snake_storage",
+    version: 1,
+    register_plugin: function (importObject) {
+        const prefix = "rusty-head-snake/";
+        const decode = (ptr, len) =>
+            new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, ptr, len));
+
+        importObject.env.snake_storage_length = function (key_ptr, key_len) {
+            const value = localStorage.getItem(prefix + decode(key_ptr, key_len));
+            return value === null ? -1 : new TextEncoder().encode(value).length;
+        };
+        importObject.env.snake_storage_read = function (key_ptr, key_len, buffer_ptr, buffer_len) {
+            const value = localStorage.getItem(prefix + decode(key_ptr, key_len)) || "";
+            const bytes = new TextEncoder().encode(value).subarray(0, buffer_len);
+            new Uint8Array(wasm_memory.buffer, buffer_ptr, buffer_len).set(bytes);
+        };
+        importObject.env.snake_storage_write = function (key_ptr, key_len, value_ptr, value_len) {
+            localStorage.setItem(prefix + decode(key_ptr, key_len), decode(value_ptr, value_len));
+        };
+    },
+});
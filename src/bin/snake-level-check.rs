@@ -0,0 +1,65 @@
+//! Checks level files and reports every problem found in them.
+//!
+//! Usage: `snake-level-check <level.json | directory>...`
+//!
+//! Directories are searched for level files the same way the game searches the levels directory.
+//! The exit code is 0 if all levels are valid, 1 if at least one level is invalid or can't be
+//! read, and 2 on wrong usage.
+
+use rusty_head_snake::level::check::check_file;
+use rusty_head_snake::level::search_for_custom_levels;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+fn level_files(arg: &str) -> Result<Vec<PathBuf>, String> {
+    let path = Path::new(arg);
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let names = search_for_custom_levels(arg).map_err(|err| err.to_string())?;
+    Ok(names.iter().map(|name| path.join(name)).collect())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("Usage: snake-level-check <level.json | directory>...");
+        return ExitCode::from(2);
+    }
+
+    let mut checked = 0;
+    let mut invalid = 0;
+    for arg in &args {
+        let files = match level_files(arg) {
+            Ok(files) => files,
+            Err(err) => {
+                println!("{arg}: {err}");
+                invalid += 1;
+                continue;
+            }
+        };
+        for file in files {
+            checked += 1;
+            match check_file(&file) {
+                Ok(diagnostics) if diagnostics.is_empty() => println!("{}: ok", file.display()),
+                Ok(diagnostics) => {
+                    invalid += 1;
+                    for diagnostic in diagnostics {
+                        println!("{}: {diagnostic}", file.display());
+                    }
+                }
+                Err(err) => {
+                    invalid += 1;
+                    println!("{}: {err}", file.display());
+                }
+            }
+        }
+    }
+
+    println!("{checked} level(s) checked, {invalid} invalid");
+    if invalid == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
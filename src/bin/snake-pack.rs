@@ -0,0 +1,58 @@
+//! Bundles a level pack directory into a single file which can be shared with other players.
+//!
+//! Usage: `snake-pack <pack directory> [output file]`
+//!
+//! The output file defaults to the name of the directory with the bundle extension, placed next
+//! to the directory. All levels of the pack are validated before they are bundled.
+
+use rusty_head_snake::level::pack::{bundle, BUNDLE_EXTENSION};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Path of the bundle next to the pack directory. The directory is resolved first, so that e.g.
+/// `.` is named after the directory it stands for.
+fn default_output(dir: &Path) -> io::Result<PathBuf> {
+    let dir = dir.canonicalize()?;
+    let name = dir
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the directory has no name"))?;
+    let mut file_name = name.to_owned();
+    file_name.push(format!(".{BUNDLE_EXTENSION}"));
+    Ok(dir.with_file_name(file_name))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (dir, output) = match args.as_slice() {
+        [dir] => match default_output(Path::new(dir)) {
+            Ok(output) => (Path::new(dir), output),
+            Err(err) => {
+                eprintln!("{dir}: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        [dir, output] => (Path::new(dir), PathBuf::from(output)),
+        _ => {
+            eprintln!("Usage: snake-pack <pack directory> [output file]");
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = bundle(dir).and_then(|json| {
+        let mut file = File::create(&output)?;
+        writeln!(file, "{json}")?;
+        Ok(())
+    });
+    match result {
+        Ok(()) => {
+            println!("{}: bundled into {}", dir.display(), output.display());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}: {err}", dir.display());
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Runs versus games over the network. The server runs the tick loop of every round, the players
+//! connect with `rusty-head-snake --connect <address>`.
+//!
+//! Usage: `snake-server [options]`
+//!
+//! A round starts once at least two players joined and ends like a local versus round. Players
+//! who join during a round take part in the next one.
+
+// Browsers can't accept connections, so the server only exists natively
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> std::process::ExitCode {
+    native::main()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use rusty_head_snake::campaign::{find_level, level_packs};
+    use rusty_head_snake::constants::VERSUS_TARGET_POINTS;
+    use rusty_head_snake::level::Level;
+    use rusty_head_snake::logging::{self, LogConfig};
+    use rusty_head_snake::net::server::{Server, ServerArgs, ServerConfig, USAGE};
+    use std::process::ExitCode;
+    use tracing::info;
+    use tracing::level_filters::LevelFilter;
+
+    /// Returns the level given by the options. The Endless Game map is played to
+    /// [`VERSUS_TARGET_POINTS`], like a local versus game.
+    fn level(options: &ServerArgs) -> Result<Level, String> {
+        let Some(name) = &options.level else {
+            return Ok(Level {
+                borders: options.borders,
+                target_points: Some(VERSUS_TARGET_POINTS),
+                ..Level::default()
+            });
+        };
+        let (packs, _) = level_packs(&options.levels);
+        let (pack_index, level_index) = find_level(&packs, name)
+            .ok_or_else(|| format!("Level {name} was not found in {}", options.levels))?;
+        Ok(packs[pack_index].levels[level_index].level.clone())
+    }
+
+    pub fn main() -> ExitCode {
+        let options = match ServerArgs::parse(std::env::args().skip(1)) {
+            Ok(options) => options,
+            Err(err) => {
+                eprintln!("{err}\n\n{USAGE}");
+                return ExitCode::from(2);
+            }
+        };
+        if options.help {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        logging::init(&LogConfig {
+            stderr_level: if options.verbose {
+                LevelFilter::DEBUG
+            } else {
+                LevelFilter::INFO
+            },
+            dir: None,
+            ..LogConfig::default()
+        });
+
+        let level = match level(&options) {
+            Ok(level) => level,
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        };
+        let config = ServerConfig {
+            max_players: options.max_players,
+            countdown: options.countdown,
+            seed: options.seed,
+            ..ServerConfig::new(level)
+        };
+        let mut server = match Server::bind(&options.address, config) {
+            Ok(server) => server,
+            Err(err) => {
+                eprintln!("Failed to listen on {}: {err}", options.address);
+                return ExitCode::FAILURE;
+            }
+        };
+        match server.local_addr() {
+            Ok(address) => info!(%address, "Listening"),
+            Err(err) => info!(?err, "Listening"),
+        }
+        server.run()
+    }
+}
@@ -0,0 +1,64 @@
+//! Lets the autopilot strategies play many seeded games of every level and reports how they did.
+//!
+//! Usage: `snake-tournament [options] [levels directory]`
+//!
+//! The base levels are always played. Levels of the given directory are loaded the same way the
+//! game loads its levels directory. The report is printed as CSV or JSON, with a line for every
+//! combination of level and strategy.
+
+use rusty_head_snake::campaign::{base_level_pack, level_packs};
+use rusty_head_snake::tournament::{to_csv, to_json, Format, Standing, TournamentArgs, USAGE};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let options = match TournamentArgs::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    if options.help {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let packs = match &options.levels {
+        Some(dir) => {
+            let (packs, invalid_levels) = level_packs(dir);
+            for invalid_level in invalid_levels {
+                eprintln!(
+                    "Level {} is skipped: {}",
+                    invalid_level.name, invalid_level.error
+                );
+            }
+            packs
+        }
+        None => vec![base_level_pack()],
+    };
+
+    let mut standings = vec![];
+    for level in packs.iter().flat_map(|pack| &pack.levels) {
+        for strategy in &options.strategies {
+            if !strategy.plays(&level.level) {
+                eprintln!("Skipping {} with {}", level.id, strategy.name());
+                continue;
+            }
+            eprintln!("Playing {} with {}", level.id, strategy.name());
+            standings.push(Standing::play(
+                &level.id,
+                &level.level,
+                *strategy,
+                options.games,
+                options.seed,
+                options.max_ticks,
+            ));
+        }
+    }
+
+    match options.format {
+        Format::Csv => print!("{}", to_csv(&standings)),
+        Format::Json => println!("{:#}", to_json(&standings)),
+    }
+    ExitCode::SUCCESS
+}
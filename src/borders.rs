@@ -0,0 +1,134 @@
+use serde_json::{json, Value};
+
+/// Behaviour of one edge of the map.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Border {
+    /// The snake leaves the map and enters it again at the opposite edge.
+    Wrap,
+    /// The snake dies when it hits the edge.
+    Solid,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Borders {
+    pub top: Border,
+    pub bottom: Border,
+    pub left: Border,
+    pub right: Border,
+}
+
+impl Border {
+    pub fn name(self) -> &'static str {
+        match self {
+            Border::Wrap => "wrap",
+            Border::Solid => "solid",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "wrap" => Some(Border::Wrap),
+            "solid" => Some(Border::Solid),
+            _ => None,
+        }
+    }
+}
+
+impl Borders {
+    pub const WRAP: Borders = Borders::all(Border::Wrap);
+    pub const SOLID: Borders = Borders::all(Border::Solid);
+
+    pub const fn all(border: Border) -> Self {
+        Borders {
+            top: border,
+            bottom: border,
+            left: border,
+            right: border,
+        }
+    }
+
+    pub fn has_solid_edge(&self) -> bool {
+        [self.top, self.bottom, self.left, self.right].contains(&Border::Solid)
+    }
+
+    /// Uses the short form for maps whose edges all behave the same.
+    pub fn to_json(self) -> Value {
+        if self == Borders::all(self.top) {
+            return json!(self.top.name());
+        }
+        json!({
+            "top": self.top.name(),
+            "bottom": self.bottom.name(),
+            "left": self.left.name(),
+            "right": self.right.name(),
+        })
+    }
+
+    /// Parses either a single border for all edges (`"solid"`) or an object with the borders of
+    /// the single edges (`{"top": "solid"}`). Missing values and edges wrap around.
+    pub fn from_json(json: &Value) -> Option<Self> {
+        match json {
+            Value::Null => Some(Borders::WRAP),
+            Value::String(name) => Border::from_name(name).map(Borders::all),
+            Value::Object(edges) => {
+                if edges
+                    .keys()
+                    .any(|key| !["top", "bottom", "left", "right"].contains(&key.as_str()))
+                {
+                    return None;
+                }
+                let edge = |key: &str| match &json[key] {
+                    Value::Null => Some(Border::Wrap),
+                    value => value.as_str().and_then(Border::from_name),
+                };
+                Some(Borders {
+                    top: edge("top")?,
+                    bottom: edge("bottom")?,
+                    left: edge("left")?,
+                    right: edge("right")?,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for Borders {
+    fn default() -> Self {
+        Borders::WRAP
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case::test_case(json!(null), Some(Borders::WRAP))]
+    #[test_case::test_case(json!("wrap"), Some(Borders::WRAP))]
+    #[test_case::test_case(json!("solid"), Some(Borders::SOLID))]
+    #[test_case::test_case(
+        json!({"top": "solid", "bottom": "solid"}),
+        Some(Borders { top: Border::Solid, bottom: Border::Solid, ..Borders::WRAP })
+    )]
+    #[test_case::test_case(json!("walls"), None)]
+    #[test_case::test_case(json!({"top": "walls"}), None)]
+    #[test_case::test_case(json!({"middle": "solid"}), None)]
+    #[test_case::test_case(json!(1), None)]
+    fn test_from_json(json: Value, expected: Option<Borders>) {
+        assert_eq!(expected, Borders::from_json(&json));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let borders = Borders {
+            left: Border::Solid,
+            ..Borders::WRAP
+        };
+        assert_eq!(Some(borders), Borders::from_json(&borders.to_json()));
+        assert_eq!(json!("solid"), Borders::SOLID.to_json());
+        assert_eq!(
+            Some(Borders::SOLID),
+            Borders::from_json(&Borders::SOLID.to_json())
+        );
+    }
+}
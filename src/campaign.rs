@@ -0,0 +1,572 @@
+pub mod source;
+
+use crate::level::pack::{search_for_packs, Pack, BUNDLE_EXTENSION};
+use crate::level::{base_levels, search_for_custom_levels, Level, LoadLevelError};
+use crate::storage::{self, StorageError};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::error;
+
+const STORAGE_KEY: &str = "progress";
+const CUSTOM_LEVELS_TITLE: &str = "Custom Levels";
+const BASE_LEVELS_TITLE: &str = "Base Levels";
+
+/// A level of the campaign together with the identifier its progress is stored under.
+pub struct CampaignLevel {
+    pub id: String,
+    pub title: String,
+    /// Shown to the player before the level starts.
+    pub hint: Option<String>,
+    pub level: Level,
+}
+
+/// An ordered collection of levels, which are unlocked one after another.
+pub struct LevelPack {
+    pub title: String,
+    pub author: Option<String>,
+    pub version: Option<String>,
+    pub levels: Vec<CampaignLevel>,
+}
+
+/// A custom level or level pack which could not be loaded.
+#[derive(Debug)]
+pub struct InvalidLevel {
+    pub name: String,
+    pub error: LoadLevelError,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct LevelProgress {
+    pub completed: bool,
+    pub best_score: i32,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LevelStatus {
+    Locked,
+    Unlocked,
+    Completed,
+}
+
+/// Completion state and best score of every level the player has played so far.
+#[derive(Debug, PartialEq, Default)]
+pub struct Progress {
+    levels: BTreeMap<String, LevelProgress>,
+}
+
+/// Returns the levels of the pack `pack_name` in the order of its manifest, as well as the levels
+/// of the pack which are not valid. A bundled pack shares its progress with the pack directory
+/// of the same name.
+fn pack_levels(pack: Pack, pack_name: &str) -> (LevelPack, Vec<InvalidLevel>) {
+    let pack_id = pack_name.trim_end_matches(&format!(".{BUNDLE_EXTENSION}"));
+
+    let mut levels = vec![];
+    let mut invalid_levels = vec![];
+    for entry in &pack.manifest.levels {
+        match pack.level(&entry.file) {
+            Ok(level) => levels.push(CampaignLevel {
+                id: format!("pack/{pack_id}/{}", entry.file),
+                title: entry
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| entry.file.trim_end_matches(".json").to_owned()),
+                hint: entry.hint.clone(),
+                level,
+            }),
+            Err(err) => {
+                error!(
+                    ?err,
+                    "Level {} of pack {} is not valid and therefore skipped", entry.file, pack_name
+                );
+                invalid_levels.push(InvalidLevel {
+                    name: format!("{pack_name}/{}", entry.file),
+                    error: err,
+                });
+            }
+        }
+    }
+    let pack = LevelPack {
+        title: pack.manifest.title,
+        author: pack.manifest.author,
+        version: pack.manifest.version,
+        levels,
+    };
+    (pack, invalid_levels)
+}
+
+/// Wraps a single custom level into a pack of its own.
+fn single_level_pack(file_name: &str, level: Level) -> LevelPack {
+    LevelPack {
+        title: CUSTOM_LEVELS_TITLE.to_owned(),
+        author: None,
+        version: None,
+        levels: vec![CampaignLevel {
+            id: format!("custom/{file_name}"),
+            title: file_name.trim_end_matches(".json").to_owned(),
+            hint: None,
+            level,
+        }],
+    }
+}
+
+/// Parses the contents of a level file or of a bundled pack named `file_name`.
+pub fn pack_from_text(
+    file_name: &str,
+    contents: &str,
+) -> Result<(LevelPack, Vec<InvalidLevel>), LoadLevelError> {
+    let json: Value = serde_json::from_str(contents)?;
+    if json.get("files").is_some() {
+        Ok(pack_levels(Pack::from_bundle(&json)?, file_name))
+    } else {
+        Ok((
+            single_level_pack(file_name, Level::from_json(&json)?),
+            vec![],
+        ))
+    }
+}
+
+/// Returns all valid custom levels found directly in `custom_level_path`, as well as the custom
+/// levels which are not valid.
+fn custom_levels(custom_level_path: &str) -> (Vec<CampaignLevel>, Vec<InvalidLevel>) {
+    let custom_level_names = match search_for_custom_levels(custom_level_path) {
+        Ok(paths) => paths,
+        Err(err) => {
+            error!(?err, "Failed to search for custom levels");
+            vec![]
+        }
+    };
+
+    let mut levels = vec![];
+    let mut invalid_levels = vec![];
+    for level_name in custom_level_names {
+        match Level::load_level(custom_level_path, &level_name) {
+            Ok(level) => levels.push(CampaignLevel {
+                id: format!("custom/{level_name}"),
+                title: level_name.trim_end_matches(".json").to_owned(),
+                hint: None,
+                level,
+            }),
+            Err(err) => {
+                error!(
+                    ?err,
+                    "Custom level {} is not valid and therefore skipped", level_name
+                );
+                invalid_levels.push(InvalidLevel {
+                    name: level_name,
+                    error: err,
+                });
+            }
+        }
+    }
+    (levels, invalid_levels)
+}
+
+/// Returns the packs found in `levels_path` in alphabetical order of their names, followed by the
+/// custom levels lying directly in `levels_path` (if there are any) and the base levels. If
+/// `levels_path` is a bundle or level file instead of a directory, only its levels precede the
+/// base levels. Levels and packs which are not valid are returned separately.
+pub fn level_packs(levels_path: &str) -> (Vec<LevelPack>, Vec<InvalidLevel>) {
+    if !Path::new(levels_path).is_dir() {
+        return level_packs_from_file(levels_path);
+    }
+    let pack_dirs = match search_for_packs(levels_path) {
+        Ok(dirs) => dirs,
+        Err(err) => {
+            error!(?err, "Failed to search for level packs");
+            vec![]
+        }
+    };
+
+    let mut packs = vec![];
+    let mut invalid_levels = vec![];
+    for pack_dir in pack_dirs {
+        match Pack::load(&Path::new(levels_path).join(&pack_dir)) {
+            Ok(pack) => {
+                let (pack, invalid_pack_levels) = pack_levels(pack, &pack_dir);
+                packs.push(pack);
+                invalid_levels.extend(invalid_pack_levels);
+            }
+            Err(err) => {
+                error!(
+                    ?err,
+                    "Level pack {} is not valid and therefore skipped", pack_dir
+                );
+                invalid_levels.push(InvalidLevel {
+                    name: pack_dir,
+                    error: err,
+                });
+            }
+        }
+    }
+
+    let (custom_levels, invalid_custom_levels) = custom_levels(levels_path);
+    invalid_levels.extend(invalid_custom_levels);
+    if !custom_levels.is_empty() {
+        packs.push(LevelPack {
+            title: CUSTOM_LEVELS_TITLE.to_owned(),
+            author: None,
+            version: None,
+            levels: custom_levels,
+        });
+    }
+
+    packs.push(base_level_pack());
+    (packs, invalid_levels)
+}
+
+/// Returns the pack of a bundle file or of a single level file followed by the base levels.
+fn level_packs_from_file(path: &str) -> (Vec<LevelPack>, Vec<InvalidLevel>) {
+    let file_name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let pack = if file_name.ends_with(&format!(".{BUNDLE_EXTENSION}")) {
+        Pack::load(Path::new(path)).map(|pack| pack_levels(pack, &file_name))
+    } else {
+        let dir = Path::new(path)
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        Level::load_level(&dir.to_string_lossy(), &file_name)
+            .map(|level| (single_level_pack(&file_name, level), vec![]))
+    };
+
+    match pack {
+        Ok((pack, invalid_levels)) => (vec![pack, base_level_pack()], invalid_levels),
+        Err(err) => {
+            error!(
+                ?err,
+                "Levels of {} are not valid and therefore skipped", path
+            );
+            let invalid_level = InvalidLevel {
+                name: file_name,
+                error: err,
+            };
+            (vec![base_level_pack()], vec![invalid_level])
+        }
+    }
+}
+
+/// Returns the pack of the base levels, which always comes last.
+pub fn base_level_pack() -> LevelPack {
+    LevelPack {
+        title: BASE_LEVELS_TITLE.to_owned(),
+        author: None,
+        version: None,
+        levels: base_levels()
+            .into_iter()
+            .enumerate()
+            .map(|(i, level)| CampaignLevel {
+                id: format!("base/{}", i + 1),
+                title: format!("Level {}", i + 1),
+                hint: None,
+                level,
+            })
+            .collect(),
+    }
+}
+
+/// Returns the indices of the pack and of the level whose id is `name`, or else of the first
+/// level titled `name`.
+pub fn find_level(packs: &[LevelPack], name: &str) -> Option<(usize, usize)> {
+    let find = |matches: &dyn Fn(&CampaignLevel) -> bool| {
+        packs.iter().enumerate().find_map(|(pack_index, pack)| {
+            let level_index = pack.levels.iter().position(matches)?;
+            Some((pack_index, level_index))
+        })
+    };
+    find(&|level| level.id == name).or_else(|| find(&|level| level.title == name))
+}
+
+impl LevelPack {
+    /// Label of the pack in the pack select screen.
+    pub fn label(&self) -> String {
+        match (&self.author, &self.version) {
+            (Some(author), Some(version)) => format!("{} {version} by {author}", self.title),
+            (Some(author), None) => format!("{} by {author}", self.title),
+            (None, Some(version)) => format!("{} {version}", self.title),
+            (None, None) => self.title.clone(),
+        }
+    }
+}
+
+impl Progress {
+    pub fn get(&self, id: &str) -> LevelProgress {
+        self.levels.get(id).copied().unwrap_or_default()
+    }
+
+    /// Stores the result of an attempt to play the level `id`.
+    pub fn record(&mut self, id: &str, score: i32, completed: bool) {
+        let progress = self.levels.entry(id.to_owned()).or_default();
+        progress.completed |= completed;
+        progress.best_score = progress.best_score.max(score);
+    }
+
+    /// A level is unlocked if it is the first one or if the level before it was completed.
+    pub fn status(&self, levels: &[CampaignLevel], index: usize) -> LevelStatus {
+        if self.get(&levels[index].id).completed {
+            LevelStatus::Completed
+        } else if index == 0 || self.get(&levels[index - 1].id).completed {
+            LevelStatus::Unlocked
+        } else {
+            LevelStatus::Locked
+        }
+    }
+
+    /// Label of the level in the level select screen.
+    pub fn label(&self, levels: &[CampaignLevel], index: usize) -> String {
+        let level = &levels[index];
+        match self.status(levels, index) {
+            LevelStatus::Locked => format!("{} - locked", level.title),
+            LevelStatus::Unlocked | LevelStatus::Completed => format!(
+                "{}  {}/{}",
+                level.title,
+                self.get(&level.id).best_score,
+                level.level.target_points.unwrap_or_default()
+            ),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let levels: serde_json::Map<String, Value> = self
+            .levels
+            .iter()
+            .map(|(id, progress)| {
+                (
+                    id.clone(),
+                    json!({
+                        "completed": progress.completed,
+                        "best_score": progress.best_score,
+                    }),
+                )
+            })
+            .collect();
+        json!({ "levels": levels })
+    }
+
+    fn from_json(json: &Value) -> Result<Self, ProgressError> {
+        let levels = json["levels"]
+            .as_object()
+            .ok_or(ProgressError::InvalidFormat)?
+            .iter()
+            .map(|(id, progress)| {
+                let completed = progress["completed"]
+                    .as_bool()
+                    .ok_or(ProgressError::InvalidFormat)?;
+                let best_score = progress["best_score"]
+                    .as_i64()
+                    .and_then(|score| i32::try_from(score).ok())
+                    .ok_or(ProgressError::InvalidFormat)?;
+                Ok((
+                    id.clone(),
+                    LevelProgress {
+                        completed,
+                        best_score,
+                    },
+                ))
+            })
+            .collect::<Result<_, ProgressError>>()?;
+        Ok(Progress { levels })
+    }
+
+    pub fn load() -> Result<Self, ProgressError> {
+        match storage::load(STORAGE_KEY)? {
+            Some(contents) => Progress::from_json(&serde_json::from_str(&contents)?),
+            None => Ok(Progress::default()),
+        }
+    }
+
+    pub fn load_or_default() -> Self {
+        Progress::load().unwrap_or_else(|err| {
+            error!(?err, "Failed to load level progress");
+            Progress::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<(), ProgressError> {
+        storage::save(STORAGE_KEY, &self.to_json().to_string())?;
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProgressError {
+    #[error(transparent)]
+    StorageError(#[from] StorageError),
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("The format of the stored level progress is not valid")]
+    InvalidFormat,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::pack::MANIFEST_FILE;
+    use std::fs::{create_dir, File};
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn campaign(number_of_levels: usize) -> Vec<CampaignLevel> {
+        (0..number_of_levels)
+            .map(|i| CampaignLevel {
+                id: format!("level/{i}"),
+                title: format!("Level {i}"),
+                hint: None,
+                level: Level {
+                    target_points: Some(10),
+                    ..Level::default()
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_level_status() {
+        let levels = campaign(3);
+        let mut progress = Progress::default();
+
+        assert_eq!(LevelStatus::Unlocked, progress.status(&levels, 0));
+        assert_eq!(LevelStatus::Locked, progress.status(&levels, 1));
+
+        progress.record("level/0", 4, false);
+        assert_eq!(LevelStatus::Locked, progress.status(&levels, 1));
+        assert_eq!("Level 0  4/10", progress.label(&levels, 0));
+        assert_eq!("Level 1 - locked", progress.label(&levels, 1));
+
+        progress.record("level/0", 10, true);
+        progress.record("level/0", 2, false);
+        assert_eq!(LevelStatus::Completed, progress.status(&levels, 0));
+        assert_eq!(LevelStatus::Unlocked, progress.status(&levels, 1));
+        assert_eq!(LevelStatus::Locked, progress.status(&levels, 2));
+        assert_eq!(
+            LevelProgress {
+                completed: true,
+                best_score: 10
+            },
+            progress.get("level/0")
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut progress = Progress::default();
+        progress.record("custom/level_1.json", 10, true);
+        progress.record("base/1", 3, false);
+
+        assert_eq!(progress, Progress::from_json(&progress.to_json()).unwrap());
+    }
+
+    const LEVEL_JSON: &str =
+        r#"{"target_points": 3, "updates_per_second": 8, "map": [["s","d","-"]]}"#;
+
+    #[test]
+    fn test_plain_directory() {
+        let dir = tempdir().unwrap();
+        let mut file = File::create(dir.path().join("level.json")).unwrap();
+        writeln!(file, "{LEVEL_JSON}").unwrap();
+        File::create(dir.path().join("invalid.json")).unwrap();
+
+        let dir_string = dir.path().to_string_lossy().into_owned();
+        let (packs, invalid_levels) = level_packs(&dir_string);
+        assert_eq!(2, packs.len());
+        assert_eq!(CUSTOM_LEVELS_TITLE, packs[0].title);
+        assert_eq!(1, packs[0].levels.len());
+        assert_eq!("custom/level.json", packs[0].levels[0].id);
+        assert_eq!("level", packs[0].levels[0].title);
+        assert_eq!(BASE_LEVELS_TITLE, packs[1].title);
+        assert_eq!(base_levels().len(), packs[1].levels.len());
+        assert_eq!("base/1", packs[1].levels[0].id);
+        assert_eq!(1, invalid_levels.len());
+        assert_eq!("invalid.json", invalid_levels[0].name);
+        assert!(matches!(
+            invalid_levels[0].error,
+            LoadLevelError::JsonError(_)
+        ));
+    }
+
+    #[test]
+    fn test_level_pack() {
+        let dir = tempdir().unwrap();
+        let pack_dir = dir.path().join("mazes");
+        create_dir(&pack_dir).unwrap();
+        for name in ["b.json", "a.json"] {
+            let mut file = File::create(pack_dir.join(name)).unwrap();
+            writeln!(file, "{LEVEL_JSON}").unwrap();
+        }
+        let mut manifest = File::create(pack_dir.join(MANIFEST_FILE)).unwrap();
+        writeln!(
+            manifest,
+            r#"{{"title": "Mazes", "author": "Ana", "levels": [
+                {{"file": "b.json", "title": "First", "hint": "Go right"}},
+                "a.json",
+                "missing.json"
+            ]}}"#
+        )
+        .unwrap();
+        let broken_dir = dir.path().join("broken");
+        create_dir(&broken_dir).unwrap();
+        File::create(broken_dir.join(MANIFEST_FILE)).unwrap();
+        File::create(dir.path().join("broken.snakepack")).unwrap();
+
+        let dir_string = dir.path().to_string_lossy().into_owned();
+        let (packs, invalid_levels) = level_packs(&dir_string);
+        assert_eq!(2, packs.len());
+        assert_eq!("Mazes by Ana", packs[0].label());
+        let titles: Vec<&str> = packs[0].levels.iter().map(|l| l.title.as_str()).collect();
+        assert_eq!(vec!["First", "a"], titles);
+        assert_eq!("pack/mazes/b.json", packs[0].levels[0].id);
+        assert_eq!(Some("Go right"), packs[0].levels[0].hint.as_deref());
+        assert_eq!(None, packs[0].levels[1].hint);
+        assert_eq!(BASE_LEVELS_TITLE, packs[1].title);
+
+        let names: Vec<&str> = invalid_levels.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(
+            vec!["broken", "broken.snakepack", "mazes/missing.json"],
+            names
+        );
+    }
+
+    #[test]
+    fn test_find_level() {
+        let (packs, _) = level_packs("does_not_exist");
+        assert_eq!(Some((0, 1)), find_level(&packs, "base/2"));
+        assert_eq!(Some((0, 2)), find_level(&packs, "Level 3"));
+        assert_eq!(None, find_level(&packs, "Level 0"));
+    }
+
+    #[test]
+    fn test_level_packs_from_file() {
+        let dir = tempdir().unwrap();
+        let level_path = dir.path().join("level.json");
+        let mut file = File::create(&level_path).unwrap();
+        writeln!(file, "{LEVEL_JSON}").unwrap();
+        let bundle_path = dir.path().join("mazes.snakepack");
+        let mut bundle = File::create(&bundle_path).unwrap();
+        writeln!(
+            bundle,
+            r#"{{"title": "Mazes", "levels": ["a.json"], "files": {{"a.json": {LEVEL_JSON}}}}}"#
+        )
+        .unwrap();
+
+        let (packs, invalid_levels) = level_packs(&level_path.to_string_lossy());
+        assert!(invalid_levels.is_empty());
+        assert_eq!(2, packs.len());
+        assert_eq!("custom/level.json", packs[0].levels[0].id);
+
+        let (packs, invalid_levels) = level_packs(&bundle_path.to_string_lossy());
+        assert!(invalid_levels.is_empty());
+        assert_eq!(2, packs.len());
+        assert_eq!("Mazes", packs[0].title);
+        assert_eq!("pack/mazes/a.json", packs[0].levels[0].id);
+
+        let (packs, invalid_levels) =
+            level_packs(&dir.path().join("missing.json").to_string_lossy());
+        assert_eq!(1, packs.len());
+        assert_eq!(BASE_LEVELS_TITLE, packs[0].title);
+        assert_eq!("missing.json", invalid_levels[0].name);
+    }
+}
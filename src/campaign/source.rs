@@ -0,0 +1,234 @@
+//! Sources the custom levels are loaded from. Natively, they are read from the file system. The
+//! web build can't access the file system and fetches the files listed in the level index
+//! [`crate::constants::LEVEL_INDEX_PATH`] from the directory hosting `index.html` instead.
+//! Additionally, the player can paste a level or drop its file onto the window.
+
+use super::{base_level_pack, level_packs, pack_from_text, InvalidLevel, LevelPack};
+#[cfg(target_arch = "wasm32")]
+use crate::constants::LEVEL_INDEX_PATH;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::constants::LEVEL_PATH;
+use crate::graphic_utils::draw_message;
+use crate::level::LoadLevelError;
+use macroquad::color::WHITE;
+use macroquad::file::load_string;
+use macroquad::input::{is_key_down, is_key_pressed, KeyCode};
+use macroquad::miniquad::window::{clipboard_get, dropped_file_bytes, dropped_file_path};
+use macroquad::window::next_frame;
+use std::path::Path;
+use tracing::{debug, error};
+
+const PASTED_LEVEL_NAME: &str = "Pasted Level";
+
+/// Name of pasted contents, which has to tell different levels apart, since the progress is
+/// stored by the ids derived from it. The FNV-1a hash of the contents keeps the name stable
+/// across sessions, so pasting the same level again continues its progress.
+fn pasted_level_name(contents: &str) -> String {
+    let hash = contents.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    format!("{PASTED_LEVEL_NAME} {hash:08x}")
+}
+
+/// Where the custom levels are loaded from. The base levels are always available in addition.
+pub enum LevelSource {
+    /// A levels directory, a bundled pack or a single level file in the file system.
+    Path(String),
+    /// An index of level and bundle files, one path per line relative to the index, which is
+    /// fetched over HTTP in the web build.
+    Index(String),
+    /// The contents of a level or bundle file, e.g. pasted or dropped by the player.
+    Text { name: String, contents: String },
+}
+
+impl Default for LevelSource {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn default() -> Self {
+        LevelSource::Path(LEVEL_PATH.to_owned())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn default() -> Self {
+        LevelSource::Index(LEVEL_INDEX_PATH.to_owned())
+    }
+}
+
+impl LevelSource {
+    /// Returns the packs of the source followed by the base levels, as well as the levels and
+    /// packs which are not valid.
+    pub async fn load(&self) -> (Vec<LevelPack>, Vec<InvalidLevel>) {
+        let (mut packs, invalid_levels) = match self {
+            LevelSource::Path(path) => level_packs(path),
+            LevelSource::Index(index_path) => fetch_index(index_path).await,
+            LevelSource::Text { name, contents } => {
+                packs_from_texts(vec![(name.clone(), Ok(contents.clone()))])
+            }
+        };
+        if !matches!(self, LevelSource::Path(_)) {
+            packs.push(base_level_pack());
+        }
+        debug!(
+            packs = packs.len(),
+            invalid = invalid_levels.len(),
+            "Loaded level packs"
+        );
+        (packs, invalid_levels)
+    }
+}
+
+/// Returns the paths listed in a level index. Empty lines and lines starting with `#` are
+/// ignored.
+fn parse_index(contents: &str) -> Vec<&str> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+/// Parses the fetched files, keeping the order in which they were listed.
+fn packs_from_texts(
+    files: Vec<(String, Result<String, LoadLevelError>)>,
+) -> (Vec<LevelPack>, Vec<InvalidLevel>) {
+    let mut packs = vec![];
+    let mut invalid_levels = vec![];
+    for (name, contents) in files {
+        match contents.and_then(|contents| pack_from_text(&name, &contents)) {
+            Ok((pack, invalid_pack_levels)) => {
+                packs.push(pack);
+                invalid_levels.extend(invalid_pack_levels);
+            }
+            Err(err) => {
+                error!(
+                    ?err,
+                    "Levels of {} are not valid and therefore skipped", name
+                );
+                invalid_levels.push(InvalidLevel { name, error: err });
+            }
+        }
+    }
+    (packs, invalid_levels)
+}
+
+async fn fetch(path: &str) -> Result<String, LoadLevelError> {
+    load_string(path)
+        .await
+        .map_err(|err| LoadLevelError::FetchError {
+            path: path.to_owned(),
+            message: err.to_string(),
+        })
+}
+
+async fn fetch_index(index_path: &str) -> (Vec<LevelPack>, Vec<InvalidLevel>) {
+    let index = match fetch(index_path).await {
+        Ok(index) => index,
+        // Hosting the web build without custom levels is fine
+        Err(err) => {
+            error!(?err, "Failed to fetch the level index");
+            return (vec![], vec![]);
+        }
+    };
+    let dir = Path::new(index_path).parent().unwrap_or(Path::new(""));
+
+    let mut files = vec![];
+    for file in parse_index(&index) {
+        let path = dir.join(file).to_string_lossy().into_owned();
+        files.push((file.to_owned(), fetch(&path).await));
+    }
+    packs_from_texts(files)
+}
+
+/// Waits until the player pastes the contents of a level or bundle file with Ctrl+V or drops such
+/// a file onto the window. Returns `None` if Escape is pressed instead.
+pub async fn wait_for_pasted_level() -> Option<LevelSource> {
+    // Files dropped before this screen was opened stay available and must not be taken again
+    let previous_drop = dropped_file_bytes(0);
+    loop {
+        draw_message(
+            "Paste a level or pack with Ctrl+V or drop its file here. Press Escape to go back.",
+            WHITE,
+        );
+        if is_key_pressed(KeyCode::Escape) {
+            return None;
+        }
+
+        let dropped_file = dropped_file_bytes(0);
+        if dropped_file.is_some() && dropped_file != previous_drop {
+            let contents = String::from_utf8_lossy(&dropped_file.unwrap_or_default()).into_owned();
+            let name = dropped_file_path(0)
+                .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| pasted_level_name(&contents));
+            return Some(LevelSource::Text { name, contents });
+        }
+
+        let control_down = [
+            KeyCode::LeftControl,
+            KeyCode::RightControl,
+            KeyCode::LeftSuper,
+        ]
+        .into_iter()
+        .any(is_key_down);
+        if control_down && is_key_pressed(KeyCode::V) {
+            // In the browser, the pasted text arrives with an event after the key press
+            next_frame().await;
+            if let Some(contents) = clipboard_get() {
+                return Some(LevelSource::Text {
+                    name: pasted_level_name(&contents),
+                    contents,
+                });
+            }
+        }
+        next_frame().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL_JSON: &str = r#"{"target_points": 3, "updates_per_second": 8, "map": ["sd-"]}"#;
+
+    #[test]
+    fn test_parse_index() {
+        let index = "# Levels of the web build\nlevel_1.json\n\n  mazes.snakepack  \n";
+        assert_eq!(vec!["level_1.json", "mazes.snakepack"], parse_index(index));
+    }
+
+    #[test]
+    fn test_pasted_level_name() {
+        let other_level = LEVEL_JSON.replace("sd-", "-sd");
+        assert_eq!(pasted_level_name(LEVEL_JSON), pasted_level_name(LEVEL_JSON));
+        assert_ne!(
+            pasted_level_name(LEVEL_JSON),
+            pasted_level_name(&other_level)
+        );
+        assert!(pasted_level_name(LEVEL_JSON).starts_with("Pasted Level "));
+    }
+
+    #[test]
+    fn test_packs_from_texts() {
+        let bundle = format!(
+            r#"{{"title": "Mazes", "levels": ["a.json"], "files": {{"a.json": {LEVEL_JSON}}}}}"#
+        );
+        let files = vec![
+            ("mazes.snakepack".to_owned(), Ok(bundle)),
+            ("level.json".to_owned(), Ok(LEVEL_JSON.to_owned())),
+            ("broken.json".to_owned(), Ok("{".to_owned())),
+            (
+                "missing.json".to_owned(),
+                Err(LoadLevelError::FetchError {
+                    path: "levels/missing.json".to_owned(),
+                    message: "404".to_owned(),
+                }),
+            ),
+        ];
+
+        let (packs, invalid_levels) = packs_from_texts(files);
+        assert_eq!(2, packs.len());
+        assert_eq!("Mazes", packs[0].title);
+        assert_eq!("pack/mazes/a.json", packs[0].levels[0].id);
+        assert_eq!("custom/level.json", packs[1].levels[0].id);
+        let names: Vec<&str> = invalid_levels.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(vec!["broken.json", "missing.json"], names);
+    }
+}
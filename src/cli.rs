@@ -0,0 +1,254 @@
+//! Command-line arguments of the native game.
+
+use crate::borders::{Border, Borders};
+use crate::controller::Strategy;
+use tracing::level_filters::LevelFilter;
+
+pub const USAGE: &str = "\
+Usage: rusty-head-snake [options] [levels]
+
+Options:
+  -l, --levels <path>            Load the levels from a directory, a bundled pack or a level file
+      --level <id or title>      Start directly into a level, e.g. \"base/2\" or \"Level 2\"
+      --endless <wrap|solid>     Start directly into the Endless Game
+      --seed <number>            Seed of the random number generator
+      --window-size <width>x<height>
+                                 Size of the window in pixels
+      --autopilot <strategy>     Let the computer steer the started game: greedy, shortest-path
+                                 or hamiltonian
+      --connect <host:port>      Play on a server started with snake-server
+      --name <name>              Name of the player on the server
+      --rounds <number>          Leave the server after this number of rounds
+      --spectate                 Watch the game on the server, or the game running on this
+                                 computer without --connect
+      --headless                 Simulate the started game without a window and input, or let
+                                 the autopilot play on the server
+  -v, --verbose                  Print debug output of the game
+      --log-level <level>        Level of the log file: error, warn, info, debug or trace
+      --log-dir <path>           Directory of the log files
+      --no-log-file              Don't write a log file
+  -h, --help                     Print this help";
+
+/// Game which is started right away instead of showing the menu.
+#[derive(Debug, PartialEq)]
+pub enum Start {
+    /// A level given by its id or title.
+    Level(String),
+    Endless(Borders),
+}
+
+#[derive(Debug, PartialEq, Default)]
+pub struct Args {
+    pub levels: Option<String>,
+    pub start: Option<Start>,
+    pub seed: Option<u64>,
+    pub window_size: Option<(i32, i32)>,
+    pub autopilot: Option<Strategy>,
+    pub connect: Option<String>,
+    pub name: Option<String>,
+    pub rounds: Option<u32>,
+    pub spectate: bool,
+    pub headless: bool,
+    pub verbose: bool,
+    pub log_level: Option<LevelFilter>,
+    pub log_dir: Option<String>,
+    pub no_log_file: bool,
+    pub help: bool,
+}
+
+fn parse_window_size(value: &str) -> Option<(i32, i32)> {
+    let (width, height) = value.split_once('x')?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+/// Takes the value following `option`.
+pub(crate) fn value(
+    args: &mut impl Iterator<Item = String>,
+    option: &'static str,
+) -> Result<String, ArgsError> {
+    args.next().ok_or(ArgsError::MissingValue(option))
+}
+
+/// Takes the value following `option` and converts it with `parse`, which returns `None` for
+/// values which are not valid.
+pub(crate) fn parsed_value<T>(
+    args: &mut impl Iterator<Item = String>,
+    option: &'static str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Result<T, ArgsError> {
+    let value = value(args, option)?;
+    parse(&value).ok_or(ArgsError::InvalidValue { option, value })
+}
+
+impl Args {
+    /// Parses the arguments without the name of the program.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let args = &mut args;
+            match arg.as_str() {
+                "-l" | "--levels" => parsed.levels = Some(value(args, "--levels")?),
+                "--level" => parsed.start = Some(Start::Level(value(args, "--level")?)),
+                "--endless" => {
+                    let border = parsed_value(args, "--endless", Border::from_name)?;
+                    parsed.start = Some(Start::Endless(Borders::all(border)));
+                }
+                "--seed" => {
+                    parsed.seed = Some(parsed_value(args, "--seed", |seed| seed.parse().ok())?);
+                }
+                "--window-size" => {
+                    parsed.window_size =
+                        Some(parsed_value(args, "--window-size", parse_window_size)?);
+                }
+                "--autopilot" => {
+                    parsed.autopilot =
+                        Some(parsed_value(args, "--autopilot", Strategy::from_name)?);
+                }
+                "--connect" => parsed.connect = Some(value(args, "--connect")?),
+                "--name" => parsed.name = Some(value(args, "--name")?),
+                "--rounds" => {
+                    parsed.rounds = Some(parsed_value(args, "--rounds", |rounds| {
+                        rounds.parse().ok()
+                    })?);
+                }
+                "--spectate" => parsed.spectate = true,
+                "--headless" => parsed.headless = true,
+                "-v" | "--verbose" => parsed.verbose = true,
+                "--log-level" => {
+                    parsed.log_level = Some(parsed_value(args, "--log-level", |level| {
+                        level.parse().ok()
+                    })?);
+                }
+                "--log-dir" => parsed.log_dir = Some(value(args, "--log-dir")?),
+                "--no-log-file" => parsed.no_log_file = true,
+                "-h" | "--help" => parsed.help = true,
+                _ if arg.starts_with('-') => return Err(ArgsError::UnknownOption(arg)),
+                _ if parsed.levels.is_none() => parsed.levels = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+
+        if parsed.connect.is_some() && parsed.start.is_some() {
+            return Err(ArgsError::ConnectWithStart);
+        }
+        if parsed.spectate && (parsed.start.is_some() || parsed.headless) {
+            return Err(ArgsError::SpectateWithGame);
+        }
+        if parsed.headless && parsed.start.is_none() && parsed.connect.is_none() {
+            return Err(ArgsError::HeadlessWithoutStart);
+        }
+        // Online games are only steered by the autopilot without a window
+        if parsed.autopilot.is_some()
+            && parsed.start.is_none()
+            && !(parsed.headless && parsed.connect.is_some())
+        {
+            return Err(ArgsError::AutopilotWithoutStart);
+        }
+        Ok(parsed)
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ArgsError {
+    #[error("Unknown option \"{0}\"")]
+    UnknownOption(String),
+
+    #[error("Unexpected argument \"{0}\"")]
+    UnexpectedArgument(String),
+
+    #[error("The option \"{0}\" requires a value")]
+    MissingValue(&'static str),
+
+    #[error("The value \"{value}\" of the option \"{option}\" is not valid")]
+    InvalidValue { option: &'static str, value: String },
+
+    #[error("The option \"--headless\" requires \"--level\", \"--endless\" or \"--connect\"")]
+    HeadlessWithoutStart,
+
+    #[error("The option \"--autopilot\" requires \"--level\", \"--endless\" or \"--headless --connect\"")]
+    AutopilotWithoutStart,
+
+    #[error("The option \"--connect\" can't be combined with \"--level\" or \"--endless\"")]
+    ConnectWithStart,
+
+    #[error("The option \"--spectate\" can't be combined with \"--level\", \"--endless\" or \"--headless\"")]
+    SpectateWithGame,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn parse(args: &str) -> Result<Args, ArgsError> {
+        Args::parse(args.split_whitespace().map(str::to_owned))
+    }
+
+    #[test]
+    fn test_parse() {
+        let expected = Args {
+            levels: Some("my_levels".to_owned()),
+            start: Some(Start::Endless(Borders::SOLID)),
+            seed: Some(42),
+            window_size: Some((800, 600)),
+            autopilot: Some(Strategy::Hamiltonian),
+            connect: None,
+            name: Some("Ada".to_owned()),
+            rounds: Some(3),
+            spectate: false,
+            headless: true,
+            verbose: true,
+            log_level: Some(LevelFilter::DEBUG),
+            log_dir: Some("my_logs".to_owned()),
+            no_log_file: true,
+            help: false,
+        };
+        assert_eq!(
+            expected,
+            parse(
+                "-l my_levels --endless solid --seed 42 --window-size 800x600 \
+                 --autopilot hamiltonian --name Ada --rounds 3 --headless -v --log-level debug --log-dir my_logs \
+                 --no-log-file"
+            )
+            .unwrap()
+        );
+        assert_eq!(Args::default(), parse("").unwrap());
+        assert_eq!(
+            Some("levels.snakepack".to_owned()),
+            parse("levels.snakepack").unwrap().levels
+        );
+        assert_eq!(
+            Some(Start::Level("base/2".to_owned())),
+            parse("--level base/2").unwrap().start
+        );
+        let bot = parse("--connect localhost:7878 --headless --autopilot greedy").unwrap();
+        assert_eq!(Some("localhost:7878".to_owned()), bot.connect);
+        assert_eq!(Some(Strategy::Greedy), bot.autopilot);
+        assert!(
+            parse("--connect localhost:7878 --spectate")
+                .unwrap()
+                .spectate
+        );
+    }
+
+    #[test_case("--fast" => ArgsError::UnknownOption("--fast".to_owned()))]
+    #[test_case("a b" => ArgsError::UnexpectedArgument("b".to_owned()))]
+    #[test_case("--seed" => ArgsError::MissingValue("--seed"))]
+    #[test_case("--seed -1" => ArgsError::InvalidValue { option: "--seed", value: "-1".to_owned() })]
+    #[test_case("--endless both" => ArgsError::InvalidValue { option: "--endless", value: "both".to_owned() })]
+    #[test_case("--window-size 0x600" => ArgsError::InvalidValue { option: "--window-size", value: "0x600".to_owned() })]
+    #[test_case("--log-level loud" => ArgsError::InvalidValue { option: "--log-level", value: "loud".to_owned() })]
+    #[test_case("--autopilot random --endless wrap" => ArgsError::InvalidValue { option: "--autopilot", value: "random".to_owned() })]
+    #[test_case("--headless" => ArgsError::HeadlessWithoutStart)]
+    #[test_case("--autopilot greedy" => ArgsError::AutopilotWithoutStart)]
+    #[test_case("--autopilot greedy --connect localhost:7878" => ArgsError::AutopilotWithoutStart)]
+    #[test_case("--connect localhost:7878 --endless wrap" => ArgsError::ConnectWithStart)]
+    #[test_case("--spectate --endless wrap" => ArgsError::SpectateWithGame)]
+    #[test_case("--spectate --connect localhost:7878 --headless" => ArgsError::SpectateWithGame)]
+    #[test_case("--rounds all" => ArgsError::InvalidValue { option: "--rounds", value: "all".to_owned() })]
+    fn test_invalid_args(args: &str) -> ArgsError {
+        parse(args).unwrap_err()
+    }
+}
@@ -5,6 +5,41 @@ use crate::touch_fields::TouchField;
 pub const SNAKE_WIDTH: f32 = 1.0;
 pub const TARGET_WIDTH: f32 = 1.0;
 pub const OBSTACLE_WIDTH: f32 = 1.0;
+pub const SOLID_BORDER_WIDTH: f32 = 0.25;
+
+pub const MAX_QUEUED_TURNS: usize = 3;
+
+pub const HEADLESS_MAX_TICKS: u32 = 100_000;
+
+/// Points which win a round of a versus game before the other snake collides.
+pub const VERSUS_TARGET_POINTS: i32 = 10;
+pub const VERSUS_ROUNDS_TO_WIN: u32 = 3;
+
+pub const NET_DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+pub const NET_MAX_NAME_LENGTH: usize = 16;
+pub const NET_MAX_ADDRESS_LENGTH: usize = 64;
+/// Seconds between enough players being in the lobby and the start of the next round.
+pub const NET_COUNTDOWN: f64 = 3.0;
+/// Seconds between two pings of a client. The server drops clients it hasn't heard from for
+/// [`NET_CLIENT_TIMEOUT`] seconds.
+pub const NET_PING_INTERVAL: f64 = 1.0;
+pub const NET_CLIENT_TIMEOUT: f64 = 5.0;
+/// Seconds a client tries to reach each address of the server before giving up, so an
+/// unreachable server doesn't freeze the game.
+pub const NET_CONNECT_TIMEOUT: f64 = 3.0;
+/// Seconds the writer thread of a connection waits for the client to accept a message before
+/// closing the connection.
+pub const NET_WRITE_TIMEOUT: f64 = 0.2;
+/// Messages the server queues for a client. A client which falls this far behind is dropped, so a
+/// single slow connection can't stall the game of everyone else.
+pub const NET_SEND_QUEUE: usize = 64;
+/// Turns of clients which lag behind the server by more ticks than this are ignored, as they
+/// were meant for a situation which has passed long ago.
+pub const NET_MAX_INPUT_LAG: u32 = 10;
+
+pub const EDITOR_MIN_SIZE: i32 = 3;
+pub const EDITOR_MAX_SIZE: i32 = 50;
+pub const EDITOR_MAX_UPDATES_PER_SECOND: i32 = 30;
 
 pub const WINDOW_WIDTH: i32 = 500;
 pub const WINDOW_HEIGHT: i32 = 500;
@@ -16,13 +51,55 @@ pub const SNAKE_HEAD_COLOR: Color = Color::new(1.0, 0.5, 0.0, 1.0);
 pub const SNAKE_TAIL_COLOR: Color = Color::new(0.8, 1.0, 0.0, 1.0);
 pub const TOUCH_BOUNDARY_INACTIVE_COLOR: Color = Color::new(0.3, 0.3, 0.4, 0.1);
 pub const TOUCH_BOUNDARY_ACTIVE_COLOR: Color = Color::new(1.0, 0.3, 0.4, 0.3);
+pub const EDITOR_CURSOR_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.8);
+pub const DEATH_CELL_COLOR: Color = Color::new(1.0, 0.2, 0.2, 1.0);
+pub const DIM_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.6);
+/// Head and tail colors of the snakes in a versus game. The first player keeps the colors of the
+/// single player game.
+pub const PLAYER_COLORS: [(Color, Color); 8] = [
+    (SNAKE_HEAD_COLOR, SNAKE_TAIL_COLOR),
+    (
+        Color::new(0.2, 0.6, 1.0, 1.0),
+        Color::new(0.6, 0.3, 1.0, 1.0),
+    ),
+    (
+        Color::new(1.0, 0.2, 0.6, 1.0),
+        Color::new(1.0, 0.6, 0.8, 1.0),
+    ),
+    (
+        Color::new(0.0, 0.9, 0.9, 1.0),
+        Color::new(0.5, 1.0, 1.0, 1.0),
+    ),
+    (
+        Color::new(1.0, 1.0, 1.0, 1.0),
+        Color::new(0.6, 0.6, 0.7, 1.0),
+    ),
+    (
+        Color::new(1.0, 0.9, 0.2, 1.0),
+        Color::new(1.0, 1.0, 0.6, 1.0),
+    ),
+    (
+        Color::new(0.6, 0.4, 0.2, 1.0),
+        Color::new(0.8, 0.6, 0.4, 1.0),
+    ),
+    (
+        Color::new(0.5, 0.5, 1.0, 1.0),
+        Color::new(0.3, 0.3, 0.8, 1.0),
+    ),
+];
 
 pub const TITLE_TEXT_SIZE: u16 = 40;
 pub const OPTION_TEXT_SIZE: u16 = 25;
 pub const POINTS_TEXT_SIZE: u16 = 25;
 pub const ERROR_TEXT_SIZE: u16 = 30;
+pub const TABLE_TEXT_SIZE: u16 = 18;
 
 pub const LEVEL_PATH: &str = "levels";
+pub const LEVEL_INDEX_PATH: &str = "levels/index.txt";
+pub const REPLAY_PATH: &str = "replays";
+pub const SAVE_PATH: &str = "save";
+pub const LOG_PATH: &str = "logs";
+pub const LOG_FILE_PREFIX: &str = "rusty-head-snake.log";
 
 pub const TOUCH_BOUNDARY_INACTIVE_THICKNESS: f32 = 2.0;
 pub const TOUCH_BOUNDARY_ACTIVE_THICKNESS: f32 = 10.0;
@@ -42,5 +119,29 @@ pub const RIGHT_TOUCH_FIELD: TouchField = TouchField {
     p1: Vec2 { x: 0.5, y: 0.35 },
     p2: Vec2 { x: 1.0, y: 1.0 },
 };
+pub const PAUSE_TOUCH_FIELD: TouchField = TouchField {
+    p1: Vec2 { x: -1.0, y: -1.0 },
+    p2: Vec2 { x: -0.8, y: -0.8 },
+};
 
 pub const FINAL_POINTS_SHOW_TIME: f32 = 2.0;
+pub const DEATH_SCREEN_TIME: f32 = 2.0;
+pub const ROUND_RESULT_TIME: f32 = 2.0;
+/// Seconds the final board of an autopilot demo stays visible before the next game starts.
+pub const DEMO_RESTART_TIME: f64 = 1.5;
+pub const REPLAY_FAST_FORWARD_FACTOR: f32 = 4.0;
+/// Seconds between two searches of a spectator for the replay stream of a newer local game.
+pub const STREAM_SEARCH_INTERVAL: f64 = 1.0;
+/// Fields per second the spectator camera moves at the zoom showing the whole map.
+pub const CAMERA_PAN_SPEED: f32 = 20.0;
+pub const CAMERA_ZOOM_STEP: f32 = 1.25;
+/// The spectator camera can't zoom in further than showing this many fields along the longer
+/// side of the map.
+pub const CAMERA_MIN_VISIBLE_FIELDS: f32 = 8.0;
+/// A frame which took longer than this (in seconds) means the game was not rendered for a while,
+/// e.g. because the window was minimized or the browser tab was hidden.
+pub const FOCUS_LOSS_FRAME_TIME: f32 = 0.5;
+/// Frames taking longer than this (below 30 FPS) are logged as FPS drops.
+pub const FPS_DROP_FRAME_TIME: f32 = 1.0 / 30.0;
+/// Minimum time in seconds between two logged FPS drops.
+pub const FPS_DROP_LOG_INTERVAL: f64 = 1.0;
@@ -0,0 +1,837 @@
+//! Computer players which steer the snake of a [`Simulation`] instead of keyboard or touch input.
+
+use crate::borders::Border;
+use crate::level::Level;
+use crate::simulation::{CollisionCause, Event, Simulation};
+use crate::snake::Direction;
+use euclid::Point2D;
+use std::collections::VecDeque;
+
+/// Steps the search for a cycle through the free fields of a map with obstacles may take before it
+/// gives up, so creating the controller stays fast on maps without such a cycle.
+const CYCLE_SEARCH_STEPS: u32 = 50_000;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+/// Steers a snake by choosing the direction to move into before every tick.
+pub trait Controller {
+    fn direction(&mut self, simulation: &Simulation) -> Direction;
+}
+
+/// Built-in steering strategies.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Strategy {
+    /// Moves toward the target on the shortest way, only avoiding the next field if it is deadly.
+    Greedy,
+    /// Follows the shortest path to the target around obstacles and the snake itself, unless the
+    /// path leads into an area too small for the snake.
+    ShortestPath,
+    /// Follows a cycle through every free field of the map, which never fails but takes long.
+    /// Maps on which no such cycle is found are played like [`Strategy::ShortestPath`], see
+    /// [`Strategy::plays`].
+    Hamiltonian,
+}
+
+pub const STRATEGIES: [Strategy; 3] = [
+    Strategy::Greedy,
+    Strategy::ShortestPath,
+    Strategy::Hamiltonian,
+];
+
+impl Strategy {
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Greedy => "greedy",
+            Strategy::ShortestPath => "shortest-path",
+            Strategy::Hamiltonian => "hamiltonian",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        STRATEGIES
+            .into_iter()
+            .find(|strategy| strategy.name() == name)
+    }
+
+    /// Label of the strategy in the menu.
+    pub fn label(self) -> &'static str {
+        match self {
+            Strategy::Greedy => "Greedy",
+            Strategy::ShortestPath => "Shortest Path",
+            Strategy::Hamiltonian => "Hamiltonian Cycle",
+        }
+    }
+
+    /// Whether the strategy plays `level` in its own way, rather than falling back to another
+    /// strategy. Only [`Strategy::Hamiltonian`] depends on the level.
+    pub fn plays(self, level: &Level) -> bool {
+        match self {
+            Strategy::Greedy | Strategy::ShortestPath => true,
+            Strategy::Hamiltonian => HamiltonianController::new(&Simulation::new(level, 0))
+                .cycle
+                .is_some(),
+        }
+    }
+
+    /// Creates a controller playing `simulation` with this strategy.
+    pub fn controller(self, simulation: &Simulation) -> Box<dyn Controller> {
+        match self {
+            Strategy::Greedy => Box::new(GreedyController),
+            Strategy::ShortestPath => Box::new(ShortestPathController),
+            Strategy::Hamiltonian => Box::new(HamiltonianController::new(simulation)),
+        }
+    }
+}
+
+/// Keeps the current direction, like a player who doesn't press any key.
+pub struct IdleController;
+
+impl Controller for IdleController {
+    fn direction(&mut self, simulation: &Simulation) -> Direction {
+        simulation.snake.current_direction
+    }
+}
+
+pub struct GreedyController;
+
+impl Controller for GreedyController {
+    fn direction(&mut self, simulation: &Simulation) -> Direction {
+        let grid = Grid::new(simulation);
+        let head = simulation.snake.position[0];
+        let target = simulation.target.position;
+        possible_directions(simulation)
+            .filter_map(|direction| {
+                let next = grid.neighbour(head, direction)?;
+                (!grid.is_blocked(next)).then(|| (grid.distance(next, target), direction))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map_or(simulation.snake.current_direction, |(_, direction)| {
+                direction
+            })
+    }
+}
+
+pub struct ShortestPathController;
+
+impl Controller for ShortestPathController {
+    fn direction(&mut self, simulation: &Simulation) -> Direction {
+        let grid = Grid::new(simulation);
+        let head = simulation.snake.position[0];
+        // Only take the path if the snake still fits into the area it leads to
+        grid.first_step_to(simulation, simulation.target.position)
+            .filter(|direction| {
+                grid.neighbour(head, *direction).is_some_and(|next| {
+                    grid.reachable_fields(next) >= simulation.snake.position.len()
+                })
+            })
+            .unwrap_or_else(|| most_space_direction(&grid, simulation))
+    }
+}
+
+/// Follows a precomputed cycle through all free fields. The snake can't collide with itself on
+/// the cycle, as the fields in front of the head are always left by the tail first.
+pub struct HamiltonianController {
+    /// Direction to move into on every field, if a cycle was found.
+    cycle: Option<Vec<Vec<Direction>>>,
+}
+
+impl HamiltonianController {
+    pub fn new(simulation: &Simulation) -> Self {
+        let cycle = hamiltonian_cycle(simulation.width, simulation.height)
+            .filter(|_| simulation.obstacles.is_empty())
+            .or_else(|| free_field_cycle(&Grid::with_blocked(simulation, &simulation.obstacles)));
+        HamiltonianController { cycle }
+    }
+}
+
+impl Controller for HamiltonianController {
+    #[allow(clippy::cast_sign_loss)]
+    fn direction(&mut self, simulation: &Simulation) -> Direction {
+        let head = simulation.snake.position[0];
+        let Some(cycle) = &self.cycle else {
+            return ShortestPathController.direction(simulation);
+        };
+        let direction = cycle[head.y as usize][head.x as usize];
+        // A snake which starts against the cycle can't reverse onto it
+        if direction == simulation.snake.current_direction.opposite() {
+            return ShortestPathController.direction(simulation);
+        }
+        direction
+    }
+}
+
+/// Returns the direction to move into on every field of a cycle through all fields, which only
+/// exists if the width or the height is even. The cycle doesn't cross the edges of the map.
+#[allow(clippy::cast_sign_loss)]
+fn hamiltonian_cycle(width: i32, height: i32) -> Option<Vec<Vec<Direction>>> {
+    if width < 2 || height < 2 {
+        return None;
+    }
+    if height % 2 == 0 {
+        let mut cycle = vec![vec![Direction::Up; width as usize]; height as usize];
+        // The first column leads back up, the other columns are covered row by row in a zigzag
+        for y in 0..height {
+            for x in 0..width {
+                cycle[y as usize][x as usize] = if x == 0 {
+                    if y == 0 {
+                        Direction::Right
+                    } else {
+                        Direction::Up
+                    }
+                } else if y % 2 == 0 {
+                    if x == width - 1 {
+                        Direction::Down
+                    } else {
+                        Direction::Right
+                    }
+                } else if x > 1 || y == height - 1 {
+                    Direction::Left
+                } else {
+                    Direction::Down
+                };
+            }
+        }
+        Some(cycle)
+    } else if width % 2 == 0 {
+        // The same cycle, mirrored along the diagonal
+        let transposed = hamiltonian_cycle(height, width)?;
+        let mirror = |direction| match direction {
+            Direction::Up => Direction::Left,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Up,
+            Direction::Right => Direction::Down,
+        };
+        Some(
+            (0..height as usize)
+                .map(|y| {
+                    (0..width as usize)
+                        .map(|x| mirror(transposed[x][y]))
+                        .collect()
+                })
+                .collect(),
+        )
+    } else {
+        None
+    }
+}
+
+/// Searches a cycle through all free fields of `grid` with backtracking, which also finds cycles
+/// using the wrapping edges. Returns the direction to move into on every free field, or `None` if
+/// there is no cycle or none was found within [`CYCLE_SEARCH_STEPS`] steps.
+#[allow(clippy::cast_sign_loss)]
+fn free_field_cycle(grid: &Grid) -> Option<Vec<Vec<Direction>>> {
+    let fields: Vec<Point2D<i32, i32>> = (0..grid.height)
+        .flat_map(|y| (0..grid.width).map(move |x| Point2D::new(x, y)))
+        .filter(|field| !grid.is_blocked(*field))
+        .collect();
+    // The fields are ordered by row and column
+    let index = |field: Point2D<i32, i32>| {
+        fields
+            .binary_search_by_key(&(field.y, field.x), |field| (field.y, field.x))
+            .ok()
+    };
+    let neighbours: Vec<Vec<(usize, Direction)>> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let mut neighbours: Vec<(usize, Direction)> = vec![];
+            for direction in DIRECTIONS {
+                let Some(j) = grid.neighbour(*field, direction).and_then(index) else {
+                    continue;
+                };
+                // Narrow maps wrap onto the field itself or reach a neighbour from both sides
+                if j != i && neighbours.iter().all(|(k, _)| *k != j) {
+                    neighbours.push((j, direction));
+                }
+            }
+            neighbours
+        })
+        .collect();
+    if fields.len() < 3
+        || neighbours.iter().any(|field| field.len() < 2)
+        || !balanced_colouring(&neighbours)
+    {
+        return None;
+    }
+
+    let path = grown_cycle(&neighbours).or_else(|| {
+        let mut search = CycleSearch {
+            neighbours: &neighbours,
+            visited: vec![false; fields.len()],
+            path: vec![0],
+            steps: 0,
+        };
+        search.visited[0] = true;
+        search.extend().then_some(search.path)
+    })?;
+    let mut cycle = vec![vec![Direction::Up; grid.width as usize]; grid.height as usize];
+    for (i, field) in path.iter().enumerate() {
+        let next = path[(i + 1) % path.len()];
+        let (_, direction) = neighbours[*field].iter().find(|(j, _)| *j == next)?;
+        cycle[fields[*field].y as usize][fields[*field].x as usize] = *direction;
+    }
+    Some(cycle)
+}
+
+/// Grows a cycle from a square of four fields. Two neighbouring fields of the cycle take a detour
+/// over two neighbouring fields next to them, until the cycle covers all fields or can't grow any
+/// further. This quickly finds cycles around obstacles, but not through corridors.
+fn grown_cycle(neighbours: &[Vec<(usize, Direction)>]) -> Option<Vec<usize>> {
+    let adjacent = |a: usize, b: usize| neighbours[a].iter().any(|(field, _)| *field == b);
+    let mut cycle = (0..neighbours.len()).find_map(|a| {
+        neighbours[a].iter().find_map(|(b, _)| {
+            neighbours[*b].iter().find_map(|(c, _)| {
+                neighbours[*c]
+                    .iter()
+                    .find(|(d, _)| *c != a && *d != *b && *d != a && adjacent(*d, a))
+                    .map(|(d, _)| vec![a, *b, *c, *d])
+            })
+        })
+    })?;
+    let mut in_cycle = vec![false; neighbours.len()];
+    for field in &cycle {
+        in_cycle[*field] = true;
+    }
+
+    let mut grown = true;
+    while grown && cycle.len() < neighbours.len() {
+        grown = false;
+        for i in 0..cycle.len() {
+            let (a, b) = (cycle[i], cycle[(i + 1) % cycle.len()]);
+            let detour = neighbours[a]
+                .iter()
+                .filter(|(c, _)| !in_cycle[*c])
+                .find_map(|(c, _)| {
+                    neighbours[b]
+                        .iter()
+                        .find(|(d, _)| !in_cycle[*d] && *d != *c && adjacent(*c, *d))
+                        .map(|(d, _)| (*c, *d))
+                });
+            if let Some((c, d)) = detour {
+                in_cycle[c] = true;
+                in_cycle[d] = true;
+                cycle.splice(i + 1..i + 1, [c, d]);
+                grown = true;
+                break;
+            }
+        }
+    }
+    (cycle.len() == neighbours.len()).then_some(cycle)
+}
+
+/// A cycle alternates between the colours of a chessboard, so it can't exist if the fields can be
+/// coloured like one, but in unequal numbers. Returns `false` in that case.
+fn balanced_colouring(neighbours: &[Vec<(usize, Direction)>]) -> bool {
+    let mut colours: Vec<Option<bool>> = vec![None; neighbours.len()];
+    let mut counts = [0, 0];
+    for start in 0..neighbours.len() {
+        if colours[start].is_some() {
+            continue;
+        }
+        colours[start] = Some(false);
+        counts[0] += 1;
+        let mut queue = VecDeque::from([start]);
+        while let Some(field) = queue.pop_front() {
+            let colour = colours[field] == Some(true);
+            for (next, _) in &neighbours[field] {
+                match colours[*next] {
+                    // Wrapping edges of odd length break the colouring
+                    Some(next_colour) if next_colour == colour => return true,
+                    Some(_) => {}
+                    None => {
+                        colours[*next] = Some(!colour);
+                        counts[usize::from(!colour)] += 1;
+                        queue.push_back(*next);
+                    }
+                }
+            }
+        }
+    }
+    counts[0] == counts[1]
+}
+
+/// Depth-first search for a path through all fields which leads back to its first field.
+struct CycleSearch<'a> {
+    neighbours: &'a [Vec<(usize, Direction)>],
+    visited: Vec<bool>,
+    path: Vec<usize>,
+    steps: u32,
+}
+
+impl CycleSearch<'_> {
+    /// Extends the path until it covers all fields and its last field is next to its first one.
+    /// Returns `false` if that isn't possible, or if the search ran out of steps.
+    fn extend(&mut self) -> bool {
+        let head = self.path[self.path.len() - 1];
+        if self.path.len() == self.neighbours.len() {
+            return self.neighbours[head]
+                .iter()
+                .any(|(field, _)| *field == self.path[0]);
+        }
+        // Fields with the fewest ways on are visited first, before they are cut off
+        let mut candidates: Vec<(usize, usize)> = self.neighbours[head]
+            .iter()
+            .filter(|(field, _)| !self.visited[*field])
+            .map(|(field, _)| (self.free_neighbours(*field), *field))
+            .collect();
+        candidates.sort_unstable();
+        for (_, next) in candidates {
+            self.steps += 1;
+            if self.steps > CYCLE_SEARCH_STEPS {
+                return false;
+            }
+            self.visited[next] = true;
+            self.path.push(next);
+            if self.leaves_no_dead_end(head) && self.rest_connected() && self.extend() {
+                return true;
+            }
+            self.path.pop();
+            self.visited[next] = false;
+        }
+        false
+    }
+
+    /// Whether the unvisited fields can all still be reached from the head.
+    fn rest_connected(&self) -> bool {
+        let unvisited = self.visited.iter().filter(|visited| !**visited).count();
+        let mut reached = vec![false; self.neighbours.len()];
+        let mut stack = vec![self.path[self.path.len() - 1]];
+        let mut count = 0;
+        while let Some(field) = stack.pop() {
+            for (next, _) in &self.neighbours[field] {
+                if !self.visited[*next] && !reached[*next] {
+                    reached[*next] = true;
+                    count += 1;
+                    stack.push(*next);
+                }
+            }
+        }
+        count == unvisited
+    }
+
+    fn free_neighbours(&self, field: usize) -> usize {
+        self.neighbours[field]
+            .iter()
+            .filter(|(next, _)| !self.visited[*next])
+            .count()
+    }
+
+    /// Whether every unvisited neighbour of `left`, which the path just left, can still be
+    /// entered and left again: through unvisited fields, the head or the first field.
+    fn leaves_no_dead_end(&self, left: usize) -> bool {
+        let head = self.path[self.path.len() - 1];
+        self.neighbours[left]
+            .iter()
+            .filter(|(field, _)| !self.visited[*field])
+            .all(|(field, _)| {
+                self.neighbours[*field]
+                    .iter()
+                    .filter(|(next, _)| {
+                        !self.visited[*next] || *next == head || *next == self.path[0]
+                    })
+                    .count()
+                    >= 2
+            })
+    }
+}
+
+/// Directions the snake can take in the next tick, i.e. all but the reverse of its direction.
+fn possible_directions(simulation: &Simulation) -> impl Iterator<Item = Direction> {
+    let reverse = simulation.snake.current_direction.opposite();
+    DIRECTIONS
+        .into_iter()
+        .filter(move |direction| *direction != reverse)
+}
+
+/// Picks the safe direction which leads to the largest free area, to survive as long as possible
+/// if the target can't be reached.
+fn most_space_direction(grid: &Grid, simulation: &Simulation) -> Direction {
+    let head = simulation.snake.position[0];
+    possible_directions(simulation)
+        .filter_map(|direction| {
+            let next = grid.neighbour(head, direction)?;
+            (!grid.is_blocked(next)).then(|| (grid.reachable_fields(next), direction))
+        })
+        .max_by_key(|(fields, _)| *fields)
+        .map_or(simulation.snake.current_direction, |(_, direction)| {
+            direction
+        })
+}
+
+/// Fields of the map and whether the snake can move onto them.
+struct Grid {
+    width: i32,
+    height: i32,
+    borders: crate::borders::Borders,
+    blocked: Vec<Vec<bool>>,
+}
+
+#[allow(clippy::cast_sign_loss)]
+impl Grid {
+    fn new(simulation: &Simulation) -> Self {
+        // The tail moves away during the next tick, unless the snake grows
+        let snake = &simulation.snake.position;
+        let body = if snake.contains(&simulation.target.position) {
+            &snake[..]
+        } else {
+            &snake[..snake.len() - 1]
+        };
+        let blocked: Vec<_> = simulation.obstacles.iter().chain(body).copied().collect();
+        Grid::with_blocked(simulation, &blocked)
+    }
+
+    /// The map of `simulation` with only the given fields blocked.
+    fn with_blocked(simulation: &Simulation, fields: &[Point2D<i32, i32>]) -> Self {
+        let mut blocked = vec![vec![false; simulation.width as usize]; simulation.height as usize];
+        for field in fields {
+            if (0..simulation.width).contains(&field.x) && (0..simulation.height).contains(&field.y)
+            {
+                blocked[field.y as usize][field.x as usize] = true;
+            }
+        }
+        Grid {
+            width: simulation.width,
+            height: simulation.height,
+            borders: simulation.borders,
+            blocked,
+        }
+    }
+
+    fn is_blocked(&self, field: Point2D<i32, i32>) -> bool {
+        self.blocked[field.y as usize][field.x as usize]
+    }
+
+    /// Returns the field next to `field`, wrapping at the edges which aren't solid.
+    fn neighbour(
+        &self,
+        field: Point2D<i32, i32>,
+        direction: Direction,
+    ) -> Option<Point2D<i32, i32>> {
+        let (dx, dy) = direction.offset();
+        let (x, y) = (field.x + dx, field.y + dy);
+        let border = match direction {
+            Direction::Up => self.borders.top,
+            Direction::Down => self.borders.bottom,
+            Direction::Left => self.borders.left,
+            Direction::Right => self.borders.right,
+        };
+        let outside = x < 0 || y < 0 || x >= self.width || y >= self.height;
+        if outside && border == Border::Solid {
+            return None;
+        }
+        Some(Point2D::new(
+            (x + self.width) % self.width,
+            (y + self.height) % self.height,
+        ))
+    }
+
+    /// Number of moves between two fields on an empty map, taking wrapping edges into account.
+    fn distance(&self, a: Point2D<i32, i32>, b: Point2D<i32, i32>) -> i32 {
+        let axis = |a: i32, b: i32, size: i32, low: Border, high: Border| {
+            let direct = (a - b).abs();
+            if low == Border::Wrap && high == Border::Wrap {
+                direct.min(size - direct)
+            } else {
+                direct
+            }
+        };
+        axis(a.x, b.x, self.width, self.borders.left, self.borders.right)
+            + axis(a.y, b.y, self.height, self.borders.top, self.borders.bottom)
+    }
+
+    /// Breadth-first search over the free fields, calling `visit` with every reached field and
+    /// the direction the search left the start into.
+    fn search(
+        &self,
+        start: Point2D<i32, i32>,
+        first_directions: impl Iterator<Item = Direction>,
+        mut visit: impl FnMut(Point2D<i32, i32>, Direction) -> bool,
+    ) {
+        let mut reached = vec![vec![false; self.width as usize]; self.height as usize];
+        reached[start.y as usize][start.x as usize] = true;
+        let mut queue = VecDeque::new();
+        for direction in first_directions {
+            if let Some(next) = self.neighbour(start, direction) {
+                if !self.is_blocked(next) && !reached[next.y as usize][next.x as usize] {
+                    reached[next.y as usize][next.x as usize] = true;
+                    queue.push_back((next, direction));
+                }
+            }
+        }
+
+        while let Some((field, first_direction)) = queue.pop_front() {
+            if visit(field, first_direction) {
+                return;
+            }
+            for direction in DIRECTIONS {
+                if let Some(next) = self.neighbour(field, direction) {
+                    if !self.is_blocked(next) && !reached[next.y as usize][next.x as usize] {
+                        reached[next.y as usize][next.x as usize] = true;
+                        queue.push_back((next, first_direction));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the first direction of the shortest path from the head of the snake to `goal`.
+    fn first_step_to(&self, simulation: &Simulation, goal: Point2D<i32, i32>) -> Option<Direction> {
+        let mut result = None;
+        self.search(
+            simulation.snake.position[0],
+            possible_directions(simulation),
+            |field, first_direction| {
+                if field == goal {
+                    result = Some(first_direction);
+                }
+                result.is_some()
+            },
+        );
+        result
+    }
+
+    /// Number of free fields which can be reached from `start`, including itself.
+    fn reachable_fields(&self, start: Point2D<i32, i32>) -> usize {
+        let mut fields = 1;
+        self.search(start, DIRECTIONS.into_iter(), |_, _| {
+            fields += 1;
+            false
+        });
+        fields
+    }
+}
+
+/// How a game played by a [`Controller`] ended.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GameResult {
+    pub points: i32,
+    pub ticks: u32,
+    pub won: bool,
+    /// Cause of the collision which ended the game, if it wasn't won or stopped at the tick limit.
+    pub collision: Option<CollisionCause>,
+}
+
+/// Plays `simulation` with `controller` without rendering, until the snake collides, the level is
+/// completed or `max_ticks` ticks have passed.
+pub fn autoplay(
+    simulation: &mut Simulation,
+    controller: &mut dyn Controller,
+    max_ticks: u32,
+) -> GameResult {
+    let mut result = GameResult {
+        points: 0,
+        ticks: 0,
+        won: false,
+        collision: None,
+    };
+    while simulation.tick < max_ticks {
+        let direction = controller.direction(simulation);
+        for event in simulation.step(&[direction]) {
+            match event {
+                Event::Collision { cause, .. } => result.collision = Some(cause),
+                Event::LevelComplete => result.won = true,
+                Event::TargetHit => {}
+            }
+        }
+        if result.won || result.collision.is_some() {
+            break;
+        }
+    }
+    result.points = simulation.points;
+    result.ticks = simulation.tick;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::borders::Borders;
+    use crate::level::{base_levels, Level};
+    use std::collections::HashSet;
+    use test_case::test_case;
+
+    fn level(width: i32, height: i32, borders: Borders) -> Level {
+        Level {
+            width,
+            height,
+            borders,
+            target_points: Some(30),
+            ..Level::default()
+        }
+    }
+
+    #[test_case(4, 4)]
+    #[test_case(6, 3)]
+    #[test_case(3, 6)]
+    #[test_case(2, 2)]
+    fn test_hamiltonian_cycle(width: i32, height: i32) {
+        let cycle = hamiltonian_cycle(width, height).unwrap();
+        let mut visited = HashSet::new();
+        let mut field: Point2D<i32, i32> = Point2D::new(0, 0);
+        for _ in 0..width * height {
+            assert!(visited.insert(field));
+            let (dx, dy) = cycle[field.y as usize][field.x as usize].offset();
+            field = Point2D::new(field.x + dx, field.y + dy);
+            assert!((0..width).contains(&field.x) && (0..height).contains(&field.y));
+        }
+        assert_eq!(Point2D::new(0, 0), field);
+    }
+
+    #[test]
+    fn test_no_hamiltonian_cycle_on_odd_maps() {
+        assert!(hamiltonian_cycle(5, 7).is_none());
+    }
+
+    #[test]
+    fn test_greedy_moves_toward_target() {
+        let mut simulation = Simulation::new(&level(10, 10, Borders::SOLID), 0);
+        simulation.snake.position = vec![Point2D::new(5, 5)];
+        simulation.snake.current_direction = Direction::Right;
+        simulation.target.position = Point2D::new(5, 2);
+        assert_eq!(Direction::Up, GreedyController.direction(&simulation));
+    }
+
+    #[test]
+    fn test_greedy_wraps_around() {
+        let mut simulation = Simulation::new(&level(10, 10, Borders::WRAP), 0);
+        simulation.snake.position = vec![Point2D::new(1, 5)];
+        simulation.snake.current_direction = Direction::Up;
+        simulation.target.position = Point2D::new(8, 5);
+        assert_eq!(Direction::Left, GreedyController.direction(&simulation));
+    }
+
+    #[test]
+    fn test_shortest_path_avoids_obstacles() {
+        let mut simulation = Simulation::new(&level(5, 5, Borders::SOLID), 0);
+        simulation.obstacles = vec![Point2D::new(2, 1), Point2D::new(2, 2), Point2D::new(2, 3)];
+        simulation.snake.position = vec![Point2D::new(1, 3)];
+        simulation.snake.current_direction = Direction::Right;
+        simulation.target.position = Point2D::new(3, 2);
+        // Greedy heads toward the target, but the way around the bottom end of the wall is shorter
+        assert_eq!(Direction::Up, GreedyController.direction(&simulation));
+        let mut directions = vec![];
+        for _ in 0..5 {
+            let direction = ShortestPathController.direction(&simulation);
+            directions.push(direction);
+            simulation.step(&[direction]);
+        }
+        assert_eq!(
+            vec![
+                Direction::Down,
+                Direction::Right,
+                Direction::Right,
+                Direction::Up,
+                Direction::Up
+            ],
+            directions
+        );
+        assert_eq!(Point2D::new(3, 2), simulation.snake.position[0]);
+    }
+
+    #[test_case(Strategy::ShortestPath)]
+    #[test_case(Strategy::Hamiltonian)]
+    fn test_strategies_complete_empty_map(strategy: Strategy) {
+        for seed in 0..5 {
+            let mut simulation = Simulation::new(&level(8, 8, Borders::WRAP), seed);
+            let mut controller = strategy.controller(&simulation);
+            let result = autoplay(&mut simulation, controller.as_mut(), 10_000);
+            assert!(
+                result.won,
+                "{strategy:?} failed with seed {seed}: {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_hamiltonian_never_collides() {
+        let level = Level {
+            target_points: Some(30),
+            ..level(6, 6, Borders::SOLID)
+        };
+        for seed in 0..5 {
+            let mut simulation = Simulation::new(&level, seed);
+            let mut controller = Strategy::Hamiltonian.controller(&simulation);
+            let result = autoplay(&mut simulation, controller.as_mut(), 100_000);
+            assert_eq!(None, result.collision, "seed {seed}: {result:?}");
+        }
+    }
+
+    #[test]
+    fn test_strategies_play_base_levels() {
+        for (i, level) in base_levels().iter().enumerate() {
+            // The last level asks for more points than the snake can get before it fills the map
+            let free_fields = level.width * level.height - level.obstacles.len() as i32;
+            for strategy in STRATEGIES {
+                let mut simulation = Simulation::new(level, 1);
+                let mut controller = strategy.controller(&simulation);
+                let result = autoplay(&mut simulation, controller.as_mut(), 10_000);
+                assert_ne!(Some(CollisionCause::Wall), result.collision);
+                if strategy != Strategy::Greedy {
+                    assert!(
+                        result.won || result.points == free_fields - 1,
+                        "{strategy:?} on level {}: {result:?}",
+                        i + 1
+                    );
+                }
+            }
+        }
+    }
+
+    /// Follows the cycle from the first free field and returns the number of visited fields.
+    #[allow(clippy::cast_sign_loss)]
+    fn cycle_length(level: &Level) -> Option<usize> {
+        let simulation = Simulation::new(level, 0);
+        let cycle = HamiltonianController::new(&simulation).cycle?;
+        let grid = Grid::with_blocked(&simulation, &simulation.obstacles);
+        let start = (0..level.height)
+            .flat_map(|y| (0..level.width).map(move |x| Point2D::new(x, y)))
+            .find(|field| !grid.is_blocked(*field))?;
+        let mut visited = HashSet::from([start]);
+        let mut field = grid.neighbour(start, cycle[start.y as usize][start.x as usize])?;
+        while field != start {
+            assert!(!grid.is_blocked(field) && visited.insert(field));
+            field = grid.neighbour(field, cycle[field.y as usize][field.x as usize])?;
+        }
+        Some(visited.len())
+    }
+
+    #[test]
+    fn test_hamiltonian_cycle_around_obstacles() {
+        let levels = base_levels();
+        for (i, level) in levels.iter().enumerate() {
+            let free_fields = (level.width * level.height) as usize - level.obstacles.len();
+            match cycle_length(level) {
+                Some(length) => assert_eq!(free_fields, length, "level {}", i + 1),
+                None => assert!(!Strategy::Hamiltonian.plays(level)),
+            }
+        }
+        // Around the block in the middle, and along the corridors which wrap from the bottom to
+        // the top
+        assert!(Strategy::Hamiltonian.plays(&levels[2]));
+        assert!(Strategy::Hamiltonian.plays(&levels[4]));
+        // The walls leave an odd number of fields
+        assert!(!Strategy::Hamiltonian.plays(&levels[1]));
+    }
+
+    #[test]
+    fn test_idle_controller_keeps_direction() {
+        let mut simulation = Simulation::new(&level(10, 10, Borders::SOLID), 0);
+        let direction = simulation.snake.current_direction;
+        let result = autoplay(&mut simulation, &mut IdleController, 100);
+        assert_eq!(Some(CollisionCause::Wall), result.collision);
+        assert_eq!(direction, simulation.snake.current_direction);
+    }
+
+    #[test]
+    fn test_strategy_names() {
+        for strategy in STRATEGIES {
+            assert_eq!(Some(strategy), Strategy::from_name(strategy.name()));
+        }
+        assert_eq!(None, Strategy::from_name("random"));
+    }
+}
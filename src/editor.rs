@@ -0,0 +1,529 @@
+use crate::borders::Borders;
+use crate::constants::{
+    BACKGROUND_COLOR, EDITOR_CURSOR_COLOR, EDITOR_MAX_SIZE, EDITOR_MAX_UPDATES_PER_SECOND,
+    EDITOR_MIN_SIZE, LEVEL_PATH, OBSTACLE_COLOR, SNAKE_HEAD_COLOR, SNAKE_TAIL_COLOR,
+    TABLE_TEXT_SIZE, TARGET_COLOR,
+};
+use crate::game::{render_borders, render_obstacles, start_game};
+use crate::graphic_utils::{
+    render_error_message, render_message, render_scaled_square, render_text,
+};
+use crate::level::check::check_level;
+use crate::level::{Level, LoadLevelError};
+use crate::menu::{self, Menu, Selection};
+use crate::snake::Direction;
+use crate::Context;
+use euclid::Point2D;
+use macroquad::input::{
+    get_last_key_pressed, is_mouse_button_down, mouse_position, KeyCode, MouseButton,
+};
+use macroquad::shapes::draw_rectangle_lines;
+use macroquad::window::{clear_background, next_frame, screen_height, screen_width};
+use std::fs::{create_dir_all, File};
+use std::io::Write;
+use std::path::Path;
+use tracing::error;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum MenuAction {
+    Resume,
+    PlayTest,
+    Save,
+    Quit,
+}
+
+const EDITOR_OPTIONS: [(MenuAction, &str); 4] = [
+    (MenuAction::Resume, "Resume"),
+    (MenuAction::PlayTest, "Play-Test"),
+    (MenuAction::Save, "Save"),
+    (MenuAction::Quit, "Quit"),
+];
+/// Id of the level being edited in the log, next to the ids of the campaign levels.
+const PLAY_TEST_ID: &str = "editor";
+const DIRECTIONS_CLOCKWISE: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+/// Level property which is changed with `+` and `-`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Property {
+    Width,
+    Height,
+    TargetPoints,
+    UpdatesPerSecond,
+    Borders,
+}
+
+const PROPERTIES: [Property; 5] = [
+    Property::Width,
+    Property::Height,
+    Property::TargetPoints,
+    Property::UpdatesPerSecond,
+    Property::Borders,
+];
+
+#[derive(PartialEq, Debug)]
+enum EditorAction {
+    OpenMenu,
+    None,
+}
+
+/// Editing state of a level. The level always has a start position and direction, and the
+/// direction marker always lies inside the map.
+pub struct Editor {
+    pub level: Level,
+    cursor: Point2D<i32, i32>,
+    property: Property,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Editor {
+            level: Level {
+                target_points: Some(10),
+                start_position: Some(Point2D::new(1, 1)),
+                start_direction: Some(Direction::Right),
+                updates_per_second: 8,
+                height: 13,
+                width: 13,
+                ..Level::default()
+            },
+            cursor: Point2D::new(0, 0),
+            property: Property::Width,
+        }
+    }
+}
+
+impl Editor {
+    fn start(&self) -> Point2D<i32, i32> {
+        self.level.start_position.unwrap_or_default()
+    }
+
+    fn direction(&self) -> Direction {
+        self.level.start_direction.unwrap_or(Direction::Right)
+    }
+
+    fn is_inside(&self, position: Point2D<i32, i32>) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && position.x < self.level.width
+            && position.y < self.level.height
+    }
+
+    fn marker_position(start: Point2D<i32, i32>, direction: Direction) -> Point2D<i32, i32> {
+        let (dx, dy) = direction.offset();
+        Point2D::new(start.x + dx, start.y + dy)
+    }
+
+    /// Position of the direction marker `"d"`.
+    fn marker(&self) -> Point2D<i32, i32> {
+        Editor::marker_position(self.start(), self.direction())
+    }
+
+    fn move_cursor(&mut self, dx: i32, dy: i32) {
+        self.cursor.x = (self.cursor.x + dx).clamp(0, self.level.width - 1);
+        self.cursor.y = (self.cursor.y + dy).clamp(0, self.level.height - 1);
+    }
+
+    /// Adds or removes an obstacle. The start position and the direction marker are kept free.
+    fn set_obstacle(&mut self, position: Point2D<i32, i32>, obstacle: bool) {
+        if !self.is_inside(position) || position == self.start() || position == self.marker() {
+            return;
+        }
+        let index = self.level.obstacles.iter().position(|o| *o == position);
+        match (index, obstacle) {
+            (None, true) => self.level.obstacles.push(position),
+            (Some(index), false) => {
+                self.level.obstacles.remove(index);
+            }
+            _ => {}
+        }
+    }
+
+    fn toggle_obstacle(&mut self) {
+        let obstacle = !self.level.obstacles.contains(&self.cursor);
+        self.set_obstacle(self.cursor, obstacle);
+    }
+
+    /// Moves the start position to `start`, keeping the direction if its marker fits into the map.
+    fn place_start(&mut self, start: Point2D<i32, i32>) {
+        let current = self.direction();
+        let Some(direction) = [current]
+            .into_iter()
+            .chain(DIRECTIONS_CLOCKWISE)
+            .find(|direction| self.is_inside(Editor::marker_position(start, *direction)))
+        else {
+            return;
+        };
+        self.level.start_position = Some(start);
+        self.level.start_direction = Some(direction);
+        let marker = self.marker();
+        self.level.obstacles.retain(|o| *o != start && *o != marker);
+    }
+
+    /// Turns the start direction clockwise to the next direction whose marker fits into the map.
+    fn rotate_direction(&mut self) {
+        let start = self.start();
+        let current = DIRECTIONS_CLOCKWISE
+            .iter()
+            .position(|d| *d == self.direction())
+            .unwrap_or_default();
+        for i in 1..=DIRECTIONS_CLOCKWISE.len() {
+            let direction = DIRECTIONS_CLOCKWISE[(current + i) % DIRECTIONS_CLOCKWISE.len()];
+            if self.is_inside(Editor::marker_position(start, direction)) {
+                self.level.start_direction = Some(direction);
+                break;
+            }
+        }
+        let marker = self.marker();
+        self.level.obstacles.retain(|o| *o != marker);
+    }
+
+    /// Changes the size of the map. Obstacles outside of the new map are removed and the start
+    /// position is moved into it.
+    fn resize(&mut self, width: i32, height: i32) {
+        self.level.width = width.clamp(EDITOR_MIN_SIZE, EDITOR_MAX_SIZE);
+        self.level.height = height.clamp(EDITOR_MIN_SIZE, EDITOR_MAX_SIZE);
+        let (width, height) = (self.level.width, self.level.height);
+        self.level.obstacles.retain(|o| o.x < width && o.y < height);
+        let start = self.start();
+        self.place_start(Point2D::new(
+            start.x.min(width - 1),
+            start.y.min(height - 1),
+        ));
+        self.move_cursor(0, 0);
+    }
+
+    fn adjust_property(&mut self, delta: i32) {
+        let (width, height) = (self.level.width, self.level.height);
+        let level = &mut self.level;
+        match self.property {
+            Property::Width => self.resize(width + delta, height),
+            Property::Height => self.resize(width, height + delta),
+            Property::TargetPoints => {
+                level.target_points = Some((level.target_points.unwrap_or(1) + delta).max(1));
+            }
+            Property::UpdatesPerSecond => {
+                level.updates_per_second =
+                    (level.updates_per_second + delta).clamp(1, EDITOR_MAX_UPDATES_PER_SECOND);
+            }
+            Property::Borders => {
+                level.borders = if level.borders.has_solid_edge() {
+                    Borders::WRAP
+                } else {
+                    Borders::SOLID
+                };
+            }
+        }
+    }
+
+    fn next_property(&mut self) {
+        let index = PROPERTIES
+            .iter()
+            .position(|p| *p == self.property)
+            .unwrap_or_default();
+        self.property = PROPERTIES[(index + 1) % PROPERTIES.len()];
+    }
+
+    fn handle_key_press(&mut self, key: Option<KeyCode>) -> EditorAction {
+        match key {
+            Some(KeyCode::Up) => self.move_cursor(0, -1),
+            Some(KeyCode::Down) => self.move_cursor(0, 1),
+            Some(KeyCode::Left) => self.move_cursor(-1, 0),
+            Some(KeyCode::Right) => self.move_cursor(1, 0),
+            Some(KeyCode::Space | KeyCode::O) => self.toggle_obstacle(),
+            Some(KeyCode::S) => self.place_start(self.cursor),
+            Some(KeyCode::R) => self.rotate_direction(),
+            Some(KeyCode::Tab) => self.next_property(),
+            Some(KeyCode::Equal | KeyCode::KpAdd) => self.adjust_property(1),
+            Some(KeyCode::Minus | KeyCode::KpSubtract) => self.adjust_property(-1),
+            Some(KeyCode::Escape) => return EditorAction::OpenMenu,
+            _ => {}
+        }
+        EditorAction::None
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn handle_mouse(&mut self, scaling: (f32, f32)) {
+        let (x, y) = mouse_position();
+        let position = Point2D::new((x / scaling.0) as i32, (y / scaling.1) as i32);
+        if is_mouse_button_down(MouseButton::Left) {
+            self.set_obstacle(position, true);
+        } else if is_mouse_button_down(MouseButton::Right) {
+            self.set_obstacle(position, false);
+        }
+    }
+
+    /// Returns a description of the problems of the level, if there are any.
+    fn problems(&self) -> Option<String> {
+        let diagnostics = match self.level.to_json() {
+            Ok(json) => check_level(&json.to_string()),
+            Err(err) => return Some(err.to_string()),
+        };
+        (!diagnostics.is_empty()).then(|| {
+            diagnostics
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(". ")
+        })
+    }
+
+    /// Saves the level as the first free `level_<n>.json` in `dir` and returns the file name.
+    pub fn save(&self, dir: &str) -> Result<String, LoadLevelError> {
+        create_dir_all(dir)?;
+        let name = (1..)
+            .map(|n| format!("level_{n}.json"))
+            .find(|name| !Path::new(dir).join(name).exists())
+            .unwrap_or_default();
+        let mut file = File::create(Path::new(dir).join(&name))?;
+        writeln!(file, "{:#}", self.level.to_json()?)?;
+        Ok(name)
+    }
+
+    fn scaling(&self) -> (f32, f32) {
+        (
+            screen_width() / self.level.width as f32,
+            screen_height() / self.level.height as f32,
+        )
+    }
+
+    fn property_label(&self, property: Property) -> String {
+        let level = &self.level;
+        match property {
+            Property::Width => format!("W {}", level.width),
+            Property::Height => format!("H {}", level.height),
+            Property::TargetPoints => format!("Points {}", level.target_points.unwrap_or(1)),
+            Property::UpdatesPerSecond => format!("Speed {}", level.updates_per_second),
+            Property::Borders if level.borders.has_solid_edge() => "Solid".to_owned(),
+            Property::Borders => "Wrap".to_owned(),
+        }
+    }
+
+    fn render(&self, cx: &Context) {
+        clear_background(BACKGROUND_COLOR);
+        let scaling = self.scaling();
+
+        render_obstacles(&self.level.obstacles, scaling);
+        render_borders(&self.level.borders, scaling);
+        render_scaled_square(SNAKE_HEAD_COLOR, self.start(), 1.0, scaling);
+        render_scaled_square(SNAKE_TAIL_COLOR, self.marker(), 0.5, scaling);
+        draw_rectangle_lines(
+            self.cursor.x as f32 * scaling.0,
+            self.cursor.y as f32 * scaling.1,
+            scaling.0,
+            scaling.1,
+            2.0,
+            EDITOR_CURSOR_COLOR,
+        );
+
+        let segment = screen_width() / PROPERTIES.len() as f32;
+        for (i, property) in PROPERTIES.iter().enumerate() {
+            let color = if *property == self.property {
+                TARGET_COLOR
+            } else {
+                OBSTACLE_COLOR
+            };
+            render_text(
+                &self.property_label(*property),
+                Point2D::new(segment * (i as f32 + 0.5), screen_height() * 0.97),
+                Some(&cx.font),
+                TABLE_TEXT_SIZE,
+                color,
+            );
+        }
+    }
+}
+
+/// Editor for custom levels:
+/// - Arrow keys move the cursor, Space or O toggles an obstacle
+/// - The left and right mouse button paint and erase obstacles
+/// - S places the start position at the cursor, R rotates the start direction
+/// - Tab selects a level property, `+` and `-` change it
+/// - Escape opens the menu to play-test or save the level
+pub async fn edit_level(cx: &Context) {
+    let mut editor = Editor::default();
+    loop {
+        if editor.handle_key_press(get_last_key_pressed()) == EditorAction::OpenMenu {
+            let background = || editor.render(cx);
+            // The menu ignores the Escape which opened it
+            let action = match menu::menu_loop(&mut editor_menu(), cx, &background).await {
+                Selection::Option(index) => EDITOR_OPTIONS[index].0,
+                Selection::Back => MenuAction::Resume,
+            };
+            match action {
+                MenuAction::PlayTest => match editor.problems() {
+                    Some(problems) => render_error_message(&problems).await,
+                    None => {
+                        start_game(cx, PLAY_TEST_ID, &editor.level).await;
+                    }
+                },
+                MenuAction::Save => save(&editor).await,
+                MenuAction::Quit => return,
+                MenuAction::Resume => {}
+            }
+        }
+        editor.handle_mouse(editor.scaling());
+        editor.render(cx);
+        next_frame().await;
+    }
+}
+
+fn editor_menu() -> Menu {
+    let options = EDITOR_OPTIONS
+        .iter()
+        .map(|(_, text)| (*text).to_owned())
+        .collect();
+    Menu::new("Editor", options)
+}
+
+async fn save(editor: &Editor) {
+    if let Some(problems) = editor.problems() {
+        render_error_message(&format!("The level is not saved: {problems}")).await;
+        return;
+    }
+    match editor.save(LEVEL_PATH) {
+        Ok(name) => render_message(&format!("Saved as {LEVEL_PATH}/{name}"), TARGET_COLOR).await,
+        Err(err) => {
+            error!(?err, "Failed to save level");
+            render_error_message(&format!("Failed to save level: {err}")).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_obstacles() {
+        let mut editor = Editor::default();
+
+        editor.move_cursor(3, 2);
+        editor.toggle_obstacle();
+        assert_eq!(vec![Point2D::new(3, 2)], editor.level.obstacles);
+        editor.toggle_obstacle();
+        assert!(editor.level.obstacles.is_empty());
+
+        editor.set_obstacle(Point2D::new(1, 1), true);
+        editor.set_obstacle(Point2D::new(2, 1), true);
+        editor.set_obstacle(Point2D::new(13, 0), true);
+        assert!(editor.level.obstacles.is_empty());
+    }
+
+    #[test]
+    fn test_place_start_and_rotate() {
+        let mut editor = Editor::default();
+        editor.set_obstacle(Point2D::new(12, 5), true);
+        editor.set_obstacle(Point2D::new(12, 4), true);
+
+        editor.place_start(Point2D::new(12, 5));
+        assert_eq!(Some(Point2D::new(12, 5)), editor.level.start_position);
+        assert_eq!(Some(Direction::Up), editor.level.start_direction);
+        assert!(editor.level.obstacles.is_empty());
+
+        editor.rotate_direction();
+        assert_eq!(Some(Direction::Down), editor.level.start_direction);
+        editor.rotate_direction();
+        assert_eq!(Some(Direction::Left), editor.level.start_direction);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut editor = Editor::default();
+        editor.set_obstacle(Point2D::new(10, 10), true);
+        editor.set_obstacle(Point2D::new(2, 2), true);
+        editor.place_start(Point2D::new(11, 11));
+        editor.move_cursor(20, 20);
+
+        editor.resize(6, 1);
+        assert_eq!(
+            (6, EDITOR_MIN_SIZE),
+            (editor.level.width, editor.level.height)
+        );
+        assert_eq!(vec![Point2D::new(2, 2)], editor.level.obstacles);
+        assert_eq!(Some(Point2D::new(5, 2)), editor.level.start_position);
+        assert_eq!(Point2D::new(5, 2), editor.cursor);
+        assert!(editor.is_inside(editor.marker()));
+    }
+
+    #[test]
+    fn test_properties() {
+        let mut editor = Editor::default();
+
+        editor.handle_key_press(Some(KeyCode::Tab));
+        editor.handle_key_press(Some(KeyCode::Tab));
+        editor.handle_key_press(Some(KeyCode::Minus));
+        assert_eq!(Some(9), editor.level.target_points);
+
+        editor.handle_key_press(Some(KeyCode::Tab));
+        for _ in 0..100 {
+            editor.handle_key_press(Some(KeyCode::Equal));
+        }
+        assert_eq!(
+            EDITOR_MAX_UPDATES_PER_SECOND,
+            editor.level.updates_per_second
+        );
+
+        editor.handle_key_press(Some(KeyCode::Tab));
+        editor.handle_key_press(Some(KeyCode::KpAdd));
+        assert_eq!(Borders::SOLID, editor.level.borders);
+        assert_eq!(
+            EditorAction::OpenMenu,
+            editor.handle_key_press(Some(KeyCode::Escape))
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut editor = Editor::default();
+        editor.set_obstacle(Point2D::new(4, 7), true);
+        editor.place_start(Point2D::new(6, 6));
+        editor.rotate_direction();
+        editor.level.borders = Borders::SOLID;
+        assert_eq!(None, editor.problems());
+
+        let dir = tempdir().unwrap();
+        let dir_string = dir.path().to_string_lossy().into_owned();
+        assert_eq!("level_1.json", editor.save(&dir_string).unwrap());
+        assert_eq!("level_2.json", editor.save(&dir_string).unwrap());
+
+        let level = Level::load_level(&dir_string, "level_1.json").unwrap();
+        assert_eq!(editor.level, level);
+    }
+
+    #[test]
+    fn test_save_from_menu() {
+        let mut editor = Editor::default();
+        assert_eq!(
+            EditorAction::OpenMenu,
+            editor.handle_key_press(Some(KeyCode::Escape))
+        );
+
+        let mut menu = editor_menu();
+        // The Escape which opened the menu is still reported in its first frame
+        assert_eq!(None, menu.handle_input(Some(KeyCode::Escape), &[], 1.0));
+        assert_eq!(None, menu.handle_input(Some(KeyCode::Down), &[], 1.0));
+        assert_eq!(None, menu.handle_input(Some(KeyCode::Down), &[], 1.0));
+        let Some(Selection::Option(index)) = menu.handle_input(Some(KeyCode::Enter), &[], 1.0)
+        else {
+            panic!("No option was selected");
+        };
+        assert_eq!(MenuAction::Save, EDITOR_OPTIONS[index].0);
+
+        let dir = tempdir().unwrap();
+        let dir_string = dir.path().to_string_lossy().into_owned();
+        assert_eq!("level_1.json", editor.save(&dir_string).unwrap());
+    }
+
+    #[test]
+    fn test_problems() {
+        let mut editor = Editor::default();
+        editor.level.target_points = Some(1000);
+        assert!(editor.problems().is_some());
+    }
+}
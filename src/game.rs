@@ -1,27 +1,56 @@
+use crate::borders::{Border, Borders};
 use crate::constants::{
-    BACKGROUND_COLOR, DOWN_TOUCH_FIELD, FINAL_POINTS_SHOW_TIME, LEFT_TOUCH_FIELD, OBSTACLE_COLOR,
-    OBSTACLE_WIDTH, OPTION_TEXT_SIZE, RIGHT_TOUCH_FIELD, SNAKE_HEAD_COLOR, UP_TOUCH_FIELD,
+    BACKGROUND_COLOR, DEATH_CELL_COLOR, DEATH_SCREEN_TIME, DEMO_RESTART_TIME, DOWN_TOUCH_FIELD,
+    FINAL_POINTS_SHOW_TIME, FOCUS_LOSS_FRAME_TIME, FPS_DROP_FRAME_TIME, FPS_DROP_LOG_INTERVAL,
+    LEFT_TOUCH_FIELD, OBSTACLE_COLOR, OBSTACLE_WIDTH, OPTION_TEXT_SIZE, PAUSE_TOUCH_FIELD,
+    PLAYER_COLORS, POINTS_TEXT_SIZE, REPLAY_FAST_FORWARD_FACTOR, REPLAY_PATH, RIGHT_TOUCH_FIELD,
+    ROUND_RESULT_TIME, SNAKE_HEAD_COLOR, SNAKE_WIDTH, SOLID_BORDER_WIDTH, TITLE_TEXT_SIZE,
+    TOUCH_BOUNDARY_INACTIVE_COLOR, UP_TOUCH_FIELD, VERSUS_ROUNDS_TO_WIN,
 };
-use crate::graphic_utils::{render_points, render_scaled_square, render_text};
+use crate::controller::Strategy;
+use crate::graphic_utils::{
+    render_dim_overlay, render_message, render_player_points, render_points, render_scaled_square,
+    render_text,
+};
+use crate::highscore::{record_high_score, ENDLESS_GAME, ENDLESS_GAME_SOLID_WALLS};
 use crate::level::Level;
-use crate::snake::{Direction, Snake};
-use crate::target::Target;
+use crate::menu::{self, Menu, Selection};
+use crate::replay::{Playback, Replay, ReplayStream};
+use crate::rng::Rng;
+use crate::settings::{edit_settings, Settings};
+use crate::simulation::{CollisionCause, Event, Simulation};
+use crate::snake::Direction;
+use crate::versus::{Match, Versus, VersusEvent};
 use crate::Context;
 use euclid::Point2D;
-use macroquad::input::{get_keys_down, get_last_key_pressed, touches_local, KeyCode, Touch};
-use macroquad::time::get_frame_time;
+use macroquad::input::{
+    get_keys_down, get_keys_pressed, get_last_key_pressed, touches_local, KeyCode, Touch,
+    TouchPhase,
+};
+use macroquad::shapes::draw_rectangle;
+use macroquad::time::{get_frame_time, get_time};
 use macroquad::window::{clear_background, next_frame, screen_height, screen_width};
 use std::collections::HashSet;
+use tracing::{error, info, warn};
 
+/// Macroquad frontend which feeds keyboard and touch input into a [`Simulation`] and renders it.
+/// All applied inputs are recorded into a [`Replay`].
 pub struct Game {
-    pub snake: Snake,
-    pub target: Target,
-    pub obstacles: Vec<Point2D<i32, i32>>,
-    pub width: i32,
-    pub height: i32,
+    pub simulation: Simulation,
+    pub replay: Replay,
+    /// Live copy of the replay for spectators, if it could be created.
+    pub stream: Option<ReplayStream>,
+    pending_turns: Vec<Direction>,
 }
 
-#[derive(PartialEq)]
+/// Macroquad frontend which plays a [`Replay`] back with pause, single step and fast-forward.
+struct ReplayViewer<'a> {
+    playback: Playback<'a>,
+    paused: bool,
+    fast_forward: bool,
+}
+
+#[derive(PartialEq, Debug)]
 pub enum GameOutcome {
     Lose,
     Win,
@@ -29,37 +58,117 @@ pub enum GameOutcome {
 }
 
 #[derive(PartialEq, Debug)]
-enum UpdateResult {
+enum KeyPressResult {
+    Pause,
+    Exit,
     None,
-    Collision,
-    TargetHit,
 }
 
+/// Result of a single run of the game loop.
 #[derive(PartialEq, Debug)]
-enum KeyPressResult {
-    Exit,
-    None,
+enum LoopResult {
+    Finished(GameOutcome),
+    /// The snake collided, which means the game is lost.
+    Collision {
+        cause: CollisionCause,
+        cell: Point2D<i32, i32>,
+    },
+    Restart,
 }
 
-impl Game {
-    fn render_game(&mut self, cx: &Context, point_counter: i32, point_target: Option<i32>) {
-        clear_background(BACKGROUND_COLOR);
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum PauseAction {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+}
 
-        let scaling = (
-            screen_width() / self.width as f32,
-            screen_height() / self.height as f32,
+const PAUSE_OPTIONS: [(PauseAction, &str); 4] = [
+    (PauseAction::Resume, "Resume"),
+    (PauseAction::Restart, "Restart Level"),
+    (PauseAction::Settings, "Settings"),
+    (PauseAction::Quit, "Quit to Menu"),
+];
+
+fn render_simulation(simulation: &Simulation, cx: &Context) {
+    clear_background(BACKGROUND_COLOR);
+
+    let scaling = (
+        screen_width() / simulation.width as f32,
+        screen_height() / simulation.height as f32,
+    );
+
+    render_obstacles(&simulation.obstacles, scaling);
+    render_borders(&simulation.borders, scaling);
+    simulation.target.render(scaling);
+    simulation.snake.render(scaling);
+    render_points(simulation.points, simulation.target_points, Some(&cx.font));
+}
+
+pub fn render_obstacles(obstacles: &[Point2D<i32, i32>], scaling: (f32, f32)) {
+    for position in obstacles {
+        render_scaled_square(OBSTACLE_COLOR, *position, OBSTACLE_WIDTH, scaling);
+    }
+}
+
+/// Draws a bar along every solid edge of the map.
+pub fn render_borders(borders: &Borders, scaling: (f32, f32)) {
+    let (width, height) = (screen_width(), screen_height());
+    let thickness = (
+        SOLID_BORDER_WIDTH * scaling.0,
+        SOLID_BORDER_WIDTH * scaling.1,
+    );
+    if borders.top == Border::Solid {
+        draw_rectangle(0.0, 0.0, width, thickness.1, OBSTACLE_COLOR);
+    }
+    if borders.bottom == Border::Solid {
+        draw_rectangle(
+            0.0,
+            height - thickness.1,
+            width,
+            thickness.1,
+            OBSTACLE_COLOR,
         );
+    }
+    if borders.left == Border::Solid {
+        draw_rectangle(0.0, 0.0, thickness.0, height, OBSTACLE_COLOR);
+    }
+    if borders.right == Border::Solid {
+        draw_rectangle(
+            width - thickness.0,
+            0.0,
+            thickness.0,
+            height,
+            OBSTACLE_COLOR,
+        );
+    }
+}
 
-        self.render_obstacles(scaling);
-        self.target.render(scaling);
-        self.snake.render(scaling);
-        render_points(point_counter, point_target, Some(&cx.font));
-        Game::render_touch_field_boundaries();
+impl Game {
+    pub fn new(level: &Level, seed: u64) -> Self {
+        Game {
+            simulation: Simulation::new(level, seed),
+            replay: Replay::new(level, seed),
+            stream: None,
+            pending_turns: vec![],
+        }
     }
 
-    fn render_obstacles(&mut self, scaling: (f32, f32)) {
-        for position in &self.obstacles {
-            render_scaled_square(OBSTACLE_COLOR, *position, OBSTACLE_WIDTH, scaling);
+    fn render_game(&self, cx: &Context, settings: &Settings) {
+        render_simulation(&self.simulation, cx);
+        if settings.show_touch_fields {
+            Game::render_touch_field_boundaries();
+            render_text(
+                "II",
+                Point2D::new(
+                    (PAUSE_TOUCH_FIELD.p1.x + PAUSE_TOUCH_FIELD.p2.x + 2.0) / 4.0 * screen_width(),
+                    (PAUSE_TOUCH_FIELD.p1.y + PAUSE_TOUCH_FIELD.p2.y + 2.0) / 4.0 * screen_height(),
+                ),
+                Some(&cx.font),
+                POINTS_TEXT_SIZE,
+                TOUCH_BOUNDARY_INACTIVE_COLOR,
+            );
         }
     }
 
@@ -68,52 +177,39 @@ impl Game {
         DOWN_TOUCH_FIELD.render_inactive_boundaries();
         LEFT_TOUCH_FIELD.render_inactive_boundaries();
         RIGHT_TOUCH_FIELD.render_inactive_boundaries();
+        PAUSE_TOUCH_FIELD.render_inactive_boundaries();
     }
 
-    fn update(&mut self) -> UpdateResult {
-        let snake_hit_target = self.snake_hit_target();
-        self.snake
-            .move_snake(snake_hit_target, self.width, self.height);
-        if self.snake.is_overlapping() || self.snake_hit_obstacle() {
-            UpdateResult::Collision
-        } else if snake_hit_target {
-            UpdateResult::TargetHit
-        } else {
-            UpdateResult::None
-        }
-    }
-
-    fn snake_hit_target(&mut self) -> bool {
-        if self.snake.position.contains(&self.target.position) {
-            self.target = Target::new(&self.obstacles, self.width, self.height);
-            return true;
-        }
-        false
-    }
-
-    fn snake_hit_obstacle(&mut self) -> bool {
-        if self.obstacles.contains(&self.snake.position[0]) {
-            return true;
+    fn tick(&mut self) -> Vec<Event> {
+        self.replay
+            .record(self.simulation.tick, &self.pending_turns);
+        if let Some(stream) = &mut self.stream {
+            if let Err(err) = stream.record(self.simulation.tick, &self.pending_turns) {
+                warn!(?err, "Failed to write replay stream");
+                self.stream = None;
+            }
         }
-        false
+        let events = self.simulation.step(&self.pending_turns);
+        self.pending_turns.clear();
+        events
     }
 
     fn handle_key_press(&mut self, key: Option<KeyCode>) -> KeyPressResult {
         if let Some(key) = key {
             match key {
-                KeyCode::Escape => return KeyPressResult::Exit,
+                KeyCode::Escape | KeyCode::P => return KeyPressResult::Pause,
 
                 KeyCode::Up | KeyCode::W => {
-                    self.snake.set_direction(Direction::Up);
+                    self.pending_turns.push(Direction::Up);
                 }
                 KeyCode::Down | KeyCode::S => {
-                    self.snake.set_direction(Direction::Down);
+                    self.pending_turns.push(Direction::Down);
                 }
                 KeyCode::Left | KeyCode::A => {
-                    self.snake.set_direction(Direction::Left);
+                    self.pending_turns.push(Direction::Left);
                 }
                 KeyCode::Right | KeyCode::D => {
-                    self.snake.set_direction(Direction::Right);
+                    self.pending_turns.push(Direction::Right);
                 }
                 _ => {}
             }
@@ -136,95 +232,275 @@ impl Game {
         }
     }
 
-    fn handle_touch(&mut self, touch: &Touch) {
+    fn handle_touch(&mut self, touch: &Touch) -> KeyPressResult {
+        if PAUSE_TOUCH_FIELD.in_touch_field(touch.position) {
+            return KeyPressResult::Pause;
+        }
         if UP_TOUCH_FIELD.in_touch_field(touch.position) {
             UP_TOUCH_FIELD.render_active_boundaries();
-            self.snake.set_direction(Direction::Up);
+            self.pending_turns.push(Direction::Up);
         }
         if DOWN_TOUCH_FIELD.in_touch_field(touch.position) {
             DOWN_TOUCH_FIELD.render_active_boundaries();
-            self.snake.set_direction(Direction::Down);
+            self.pending_turns.push(Direction::Down);
         }
         if LEFT_TOUCH_FIELD.in_touch_field(touch.position) {
             LEFT_TOUCH_FIELD.render_active_boundaries();
-            self.snake.set_direction(Direction::Left);
+            self.pending_turns.push(Direction::Left);
         }
         if RIGHT_TOUCH_FIELD.in_touch_field(touch.position) {
             RIGHT_TOUCH_FIELD.render_active_boundaries();
-            self.snake.set_direction(Direction::Right);
+            self.pending_turns.push(Direction::Right);
         }
+        KeyPressResult::None
     }
 }
 
-pub async fn start_game(cx: &Context, level: &Level) -> GameOutcome {
-    let mut game = Game {
-        snake: Snake::new(
-            level.start_position,
-            level.start_direction,
-            level.width,
-            level.height,
-        ),
-        target: Target::new(&level.obstacles, level.width, level.height),
-        obstacles: level.obstacles.clone(),
-        width: level.width,
-        height: level.height,
-    };
+/// Plays `level` until the player wins, loses or exits. Returns the outcome and the reached points.
+/// The `level_id` tells the level apart in the log.
+pub async fn start_game(cx: &Context, level_id: &str, level: &Level) -> (GameOutcome, i32) {
+    let mut settings = Settings::load_or_default();
+    let (game, seed, game_outcome) = loop {
+        let seed = cx.seed.unwrap_or_else(|| Rng::from_time().seed());
+        info!(
+            level_id,
+            seed,
+            width = level.width,
+            height = level.height,
+            target_points = level.target_points,
+            updates_per_second = level.updates_per_second,
+            borders = ?level.borders,
+            "Level started"
+        );
+        let mut game = Game::new(level, seed);
+        // The browser has no file system to stream into
+        #[cfg(not(target_arch = "wasm32"))]
+        if settings.live_stream {
+            match ReplayStream::create(REPLAY_PATH, level, seed) {
+                Ok(stream) => game.stream = Some(stream),
+                Err(err) => warn!(?err, "Failed to create replay stream"),
+            }
+        }
 
-    let (game_outcome, points) =
-        game_loop(&mut game, cx, level.target_points, level.updates_per_second).await;
+        let loop_result = game_loop(&mut game, cx, &mut settings, level.updates_per_second).await;
+        info!(
+            level_id,
+            result = ?loop_result,
+            points = game.simulation.points,
+            ticks = game.simulation.tick,
+            "Level ended"
+        );
+        if let Err(err) = game.replay.save(REPLAY_PATH) {
+            error!(?err, "Failed to save replay");
+        }
+        match loop_result {
+            LoopResult::Finished(game_outcome) => break (game, seed, game_outcome),
+            LoopResult::Collision { cause, cell } => {
+                render_death_screen(cx, &game.simulation, cause, cell, level.updates_per_second)
+                    .await;
+                break (game, seed, GameOutcome::Lose);
+            }
+            LoopResult::Restart => {}
+        }
+    };
     if level.target_points.is_none() {
-        render_final_points(points, cx).await;
+        render_final_points(game.simulation.points, seed, cx).await;
+        let mode = if level.borders.has_solid_edge() {
+            ENDLESS_GAME_SOLID_WALLS
+        } else {
+            ENDLESS_GAME
+        };
+        record_high_score(cx, mode, &game.simulation, level.updates_per_second).await;
     }
-    game_outcome
+    (game_outcome, game.simulation.points)
 }
 
 async fn game_loop(
     game: &mut Game,
     cx: &Context,
-    target_points: Option<i32>,
+    settings: &mut Settings,
     updates_per_second: i32,
-) -> (GameOutcome, i32) {
+) -> LoopResult {
     let expected_frame_time = 1.0 / updates_per_second as f32;
     let mut frame_time_accumulated = 0.0;
-    let mut point_counter = 0;
+    let mut last_fps_drop_log = f64::NEG_INFINITY;
 
-    let game_outcome = loop {
-        game.render_game(cx, point_counter, target_points);
+    loop {
+        game.render_game(cx, settings);
 
-        if game.handle_key_press(get_last_key_pressed()) == KeyPressResult::Exit {
-            break GameOutcome::Exit;
+        // Frames taking as long as a focus loss are handled by pausing below
+        let frame_time = get_frame_time();
+        if frame_time > FPS_DROP_FRAME_TIME
+            && frame_time <= FOCUS_LOSS_FRAME_TIME
+            && get_time() - last_fps_drop_log >= FPS_DROP_LOG_INTERVAL
+        {
+            warn!(
+                frame_time,
+                fps = 1.0 / frame_time,
+                tick = game.simulation.tick,
+                "FPS drop"
+            );
+            last_fps_drop_log = get_time();
+        }
+
+        let mut pause_requested = game.handle_key_press(get_last_key_pressed())
+            == KeyPressResult::Pause
+            || (settings.pause_on_focus_loss && get_frame_time() > FOCUS_LOSS_FRAME_TIME);
+        if settings.show_touch_fields {
+            Game::handle_keys_down(&get_keys_down());
         }
-        Game::handle_keys_down(&get_keys_down());
 
         for touch in touches_local() {
-            game.handle_touch(&touch);
+            pause_requested |= game.handle_touch(&touch) == KeyPressResult::Pause;
         }
 
-        if frame_time_accumulated >= expected_frame_time {
-            match game.update() {
-                UpdateResult::Collision => {
-                    break GameOutcome::Lose;
+        // The accumulated frame time is not advanced while paused, so the game continues exactly
+        // where it stopped.
+        if pause_requested {
+            match pause(cx, settings, &|| render_simulation(&game.simulation, cx)).await {
+                PauseAction::Restart => return LoopResult::Restart,
+                PauseAction::Quit => return LoopResult::Finished(GameOutcome::Exit),
+                PauseAction::Resume | PauseAction::Settings => {
+                    next_frame().await;
+                    continue;
                 }
-                UpdateResult::TargetHit => {
-                    point_counter += 1;
-                    if let Some(target) = target_points {
-                        if point_counter >= target {
-                            break GameOutcome::Win;
-                        }
+            }
+        }
+
+        if frame_time_accumulated >= expected_frame_time {
+            for event in game.tick() {
+                let simulation = &game.simulation;
+                let head = simulation.snake.position[0];
+                match event {
+                    Event::Collision { cause, cell } => {
+                        info!(
+                            tick = simulation.tick,
+                            x = cell.x,
+                            y = cell.y,
+                            ?cause,
+                            "Collision"
+                        );
+                        return LoopResult::Collision { cause, cell };
                     }
+                    Event::LevelComplete => return LoopResult::Finished(GameOutcome::Win),
+                    Event::TargetHit => info!(
+                        tick = simulation.tick,
+                        x = head.x,
+                        y = head.y,
+                        points = simulation.points,
+                        "Target hit"
+                    ),
                 }
-                UpdateResult::None => {}
             }
             frame_time_accumulated = 0.0;
         }
 
         frame_time_accumulated += get_frame_time();
         next_frame().await;
+    }
+}
+
+fn pause_menu() -> Menu {
+    let options = PAUSE_OPTIONS
+        .iter()
+        .map(|(_, text)| (*text).to_owned())
+        .collect();
+    Menu::new("Paused", options)
+}
+
+/// Shows the pause menu on top of `board`, dimmed, until the player picks anything but
+/// "Settings". Navigating back resumes the game.
+async fn pause(cx: &Context, settings: &mut Settings, board: &dyn Fn()) -> PauseAction {
+    let background = || {
+        board();
+        render_dim_overlay();
     };
-    (game_outcome, point_counter)
+
+    loop {
+        // The menu ignores the key press which requested the pause
+        let action = match menu::menu_loop(&mut pause_menu(), cx, &background).await {
+            Selection::Option(index) => PAUSE_OPTIONS[index].0,
+            Selection::Back => PauseAction::Resume,
+        };
+        match action {
+            PauseAction::Settings => edit_settings(cx, settings, &background).await,
+            action => return action,
+        }
+    }
 }
 
-async fn render_final_points(points: i32, cx: &Context) {
+/// Formats a duration given in seconds as minutes and seconds.
+fn format_time(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Shows the board with the field the snake collided at highlighted, together with the cause of
+/// the collision, the reached points, the length of the snake and the played time. Ends after
+/// [`DEATH_SCREEN_TIME`] or earlier on a key press or touch.
+async fn render_death_screen(
+    cx: &Context,
+    simulation: &Simulation,
+    cause: CollisionCause,
+    cell: Point2D<i32, i32>,
+    updates_per_second: i32,
+) {
+    let seconds = simulation.tick / updates_per_second.max(1).unsigned_abs();
+    let lines = [
+        format!("{} Points", simulation.points),
+        format!("Length {}", simulation.snake.position.len()),
+        format!("Time {}", format_time(seconds)),
+    ];
+    let mut frame_time_accumulated = 0.0;
+
+    // The turn which led to the collision must not skip the screen
+    next_frame().await;
+    loop {
+        render_simulation(simulation, cx);
+        render_dim_overlay();
+        let scaling = (
+            screen_width() / simulation.width as f32,
+            screen_height() / simulation.height as f32,
+        );
+        // Blinks twice per second
+        if frame_time_accumulated % 0.5 < 0.25 {
+            render_scaled_square(DEATH_CELL_COLOR, cell, SNAKE_WIDTH, scaling);
+        }
+
+        let center: Point2D<f32, f32> = Point2D::new(screen_width() / 2.0, screen_height() / 2.0);
+        render_text(
+            cause.description(),
+            Point2D::new(center.x, center.y - TITLE_TEXT_SIZE as f32 * 1.5),
+            Some(&cx.font),
+            TITLE_TEXT_SIZE,
+            DEATH_CELL_COLOR,
+        );
+        for (i, line) in lines.iter().enumerate() {
+            render_text(
+                line,
+                Point2D::new(
+                    center.x,
+                    center.y + i as f32 * OPTION_TEXT_SIZE as f32 * 1.5,
+                ),
+                Some(&cx.font),
+                OPTION_TEXT_SIZE,
+                SNAKE_HEAD_COLOR,
+            );
+        }
+
+        if frame_time_accumulated >= DEATH_SCREEN_TIME
+            || get_last_key_pressed().is_some()
+            || touches_local()
+                .iter()
+                .any(|touch| touch.phase == TouchPhase::Started)
+        {
+            break;
+        }
+        frame_time_accumulated += get_frame_time();
+        next_frame().await;
+    }
+}
+
+async fn render_final_points(points: i32, seed: u64, cx: &Context) {
     let mut frame_time_accumulated = 0.0;
     loop {
         clear_background(BACKGROUND_COLOR);
@@ -236,6 +512,13 @@ async fn render_final_points(points: i32, cx: &Context) {
             OPTION_TEXT_SIZE,
             SNAKE_HEAD_COLOR,
         );
+        render_text(
+            &format!("Seed {seed}"),
+            Point2D::new(center.x, center.y + OPTION_TEXT_SIZE as f32 * 1.5),
+            Some(&cx.font),
+            POINTS_TEXT_SIZE,
+            OBSTACLE_COLOR,
+        );
         if frame_time_accumulated >= FINAL_POINTS_SHOW_TIME {
             break;
         }
@@ -244,38 +527,423 @@ async fn render_final_points(points: i32, cx: &Context) {
     }
 }
 
+impl<'a> ReplayViewer<'a> {
+    fn new(replay: &'a Replay) -> Self {
+        ReplayViewer {
+            playback: Playback::new(replay),
+            paused: false,
+            fast_forward: false,
+        }
+    }
+
+    fn render(&self, cx: &Context) {
+        render_simulation(&self.playback.simulation, cx);
+
+        let state = if self.playback.is_finished() {
+            " - End"
+        } else if self.paused {
+            " - Paused"
+        } else if self.fast_forward {
+            " - Fast"
+        } else {
+            ""
+        };
+        render_text(
+            &format!(
+                "Tick {} / {}{state}",
+                self.playback.simulation.tick,
+                self.playback.ticks()
+            ),
+            Point2D::new(screen_width() / 2.0, screen_height() * 0.95),
+            Some(&cx.font),
+            POINTS_TEXT_SIZE,
+            SNAKE_HEAD_COLOR,
+        );
+    }
+
+    fn handle_key_press(&mut self, key: Option<KeyCode>) -> KeyPressResult {
+        if let Some(key) = key {
+            match key {
+                KeyCode::Escape => return KeyPressResult::Exit,
+                KeyCode::Space | KeyCode::P => self.paused = !self.paused,
+                KeyCode::F => self.fast_forward = !self.fast_forward,
+                KeyCode::Right | KeyCode::N if self.paused => {
+                    self.playback.step();
+                }
+                _ => {}
+            }
+        }
+        KeyPressResult::None
+    }
+
+    fn speed(&self) -> f32 {
+        if self.fast_forward {
+            REPLAY_FAST_FORWARD_FACTOR
+        } else {
+            1.0
+        }
+    }
+}
+
+pub async fn play_replay(cx: &Context, replay: &Replay) {
+    let mut viewer = ReplayViewer::new(replay);
+    let expected_frame_time = 1.0 / replay.level.updates_per_second as f32;
+    let mut frame_time_accumulated = 0.0;
+
+    loop {
+        viewer.render(cx);
+
+        if viewer.handle_key_press(get_last_key_pressed()) == KeyPressResult::Exit {
+            return;
+        }
+
+        if viewer.paused {
+            frame_time_accumulated = 0.0;
+        }
+        while frame_time_accumulated >= expected_frame_time {
+            viewer.playback.step();
+            frame_time_accumulated -= expected_frame_time;
+        }
+
+        frame_time_accumulated += get_frame_time() * viewer.speed();
+        next_frame().await;
+    }
+}
+
+/// Lets `strategy` play `level` until any key is pressed or the screen is touched. A new game with
+/// another seed is started whenever the snake collides or completes the level.
+pub async fn play_demo(cx: &Context, level: &Level, strategy: Strategy) {
+    let expected_frame_time = 1.0 / level.updates_per_second as f32;
+    // The selection in the menu must not end the demo right away
+    next_frame().await;
+    let mut given_seed = cx.seed;
+    loop {
+        // Only the first game uses the seed given on the command line
+        let seed = given_seed.take().unwrap_or_else(|| Rng::from_time().seed());
+        info!(seed, strategy = strategy.name(), "Demo started");
+        let mut simulation = Simulation::new(level, seed);
+        let mut controller = strategy.controller(&simulation);
+        let mut frame_time_accumulated = 0.0;
+        let mut game_over_time = None;
+
+        loop {
+            render_simulation(&simulation, cx);
+            render_text(
+                &format!("Autopilot: {} - Press any key to exit", strategy.label()),
+                Point2D::new(screen_width() / 2.0, screen_height() * 0.95),
+                Some(&cx.font),
+                POINTS_TEXT_SIZE,
+                SNAKE_HEAD_COLOR,
+            );
+
+            if get_last_key_pressed().is_some()
+                || touches_local()
+                    .iter()
+                    .any(|touch| touch.phase == TouchPhase::Started)
+            {
+                return;
+            }
+
+            // The final board stays visible for a moment before the next game starts
+            if let Some(time) = game_over_time {
+                if get_time() - time >= DEMO_RESTART_TIME {
+                    break;
+                }
+            } else if frame_time_accumulated >= expected_frame_time {
+                let direction = controller.direction(&simulation);
+                let events = simulation.step(&[direction]);
+                if events
+                    .iter()
+                    .any(|event| matches!(event, Event::Collision { .. } | Event::LevelComplete))
+                {
+                    info!(
+                        points = simulation.points,
+                        ticks = simulation.tick,
+                        "Demo ended"
+                    );
+                    game_over_time = Some(get_time());
+                }
+                frame_time_accumulated = 0.0;
+            }
+
+            frame_time_accumulated += get_frame_time();
+            next_frame().await;
+        }
+    }
+}
+
+/// Keys of the two players of a versus game on one keyboard.
+pub const VERSUS_KEYS: [[(KeyCode, Direction); 4]; 2] = [
+    [
+        (KeyCode::W, Direction::Up),
+        (KeyCode::S, Direction::Down),
+        (KeyCode::A, Direction::Left),
+        (KeyCode::D, Direction::Right),
+    ],
+    [
+        (KeyCode::Up, Direction::Up),
+        (KeyCode::Down, Direction::Down),
+        (KeyCode::Left, Direction::Left),
+        (KeyCode::Right, Direction::Right),
+    ],
+];
+
+/// Result of a single round of the versus loop.
+#[derive(PartialEq, Debug)]
+enum VersusLoopResult {
+    RoundOver(Option<usize>),
+    Restart,
+    Exit,
+}
+
+/// Returns the turns of both players for the keys pressed during a frame.
+fn versus_turns(keys: &HashSet<KeyCode>) -> Vec<(usize, Direction)> {
+    VERSUS_KEYS
+        .iter()
+        .enumerate()
+        .flat_map(|(player, player_keys)| {
+            player_keys
+                .iter()
+                .filter(|(key, _)| keys.contains(key))
+                .map(move |(_, direction)| (player, *direction))
+        })
+        .collect()
+}
+
+/// Draws the map and the snakes which are alive, each in the colors of its player.
+pub fn render_versus_board(versus: &Versus) {
+    clear_background(BACKGROUND_COLOR);
+
+    let scaling = (
+        screen_width() / versus.width as f32,
+        screen_height() / versus.height as f32,
+    );
+
+    render_obstacles(&versus.obstacles, scaling);
+    render_borders(&versus.borders, scaling);
+    versus.target.render(scaling);
+    for (i, player) in versus.players.iter().enumerate() {
+        let (head_color, tail_color) = PLAYER_COLORS[i % PLAYER_COLORS.len()];
+        if player.alive {
+            player
+                .snake
+                .render_with_colors(scaling, head_color, tail_color);
+        }
+    }
+}
+
+fn render_versus(versus: &Versus, cx: &Context) {
+    render_versus_board(versus);
+    for (i, player) in versus.players.iter().enumerate() {
+        let head_color = PLAYER_COLORS[i % PLAYER_COLORS.len()].0;
+        render_player_points(
+            i,
+            player.points,
+            versus.target_points,
+            Some(&cx.font),
+            head_color,
+        );
+    }
+}
+
+/// Plays a match of two players on one keyboard. Every round is won by the player who reaches
+/// the target points of `level` first or whose snake is the last one left. The first player to
+/// win [`VERSUS_ROUNDS_TO_WIN`] rounds wins the match.
+pub async fn play_versus(cx: &Context, level: &Level) {
+    let mut settings = Settings::load_or_default();
+    render_message(
+        "Player 1 steers with W, A, S and D, Player 2 with the arrow keys",
+        SNAKE_HEAD_COLOR,
+    )
+    .await;
+
+    let mut versus_match = Match::new(VERSUS_KEYS.len(), VERSUS_ROUNDS_TO_WIN);
+    let winner = loop {
+        if let Some(winner) = versus_match.winner() {
+            break winner;
+        }
+        // A seed given on the command line makes the whole match reproducible
+        let seed = cx.seed.map_or_else(
+            || Rng::from_time().seed(),
+            |seed| seed.wrapping_add(u64::from(versus_match.rounds)),
+        );
+        info!(seed, round = versus_match.rounds + 1, "Round started");
+        let mut versus = Versus::new(level, VERSUS_KEYS.len(), seed);
+
+        match versus_loop(&mut versus, cx, &mut settings, level.updates_per_second).await {
+            VersusLoopResult::RoundOver(winner) => {
+                versus_match.record(winner);
+                info!(?winner, wins = ?versus_match.wins, "Round ended");
+                render_round_result(cx, &versus, &versus_match, winner).await;
+            }
+            VersusLoopResult::Restart => {
+                versus_match = Match::new(VERSUS_KEYS.len(), VERSUS_ROUNDS_TO_WIN);
+            }
+            VersusLoopResult::Exit => return,
+        }
+    };
+
+    info!(winner, wins = ?versus_match.wins, "Match ended");
+    render_message(
+        &format!(
+            "Player {} wins the match {}",
+            winner + 1,
+            format_round_wins(&versus_match)
+        ),
+        PLAYER_COLORS[winner].0,
+    )
+    .await;
+}
+
+fn format_round_wins(versus_match: &Match) -> String {
+    versus_match
+        .wins
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(" : ")
+}
+
+/// Whether one of the players pressed a pause key. The pause menu opened for it ignores these
+/// keys in its first frame, when they are still reported.
+fn versus_pause_requested(keys: &HashSet<KeyCode>) -> bool {
+    keys.contains(&KeyCode::Escape) || keys.contains(&KeyCode::P)
+}
+
+async fn versus_loop(
+    versus: &mut Versus,
+    cx: &Context,
+    settings: &mut Settings,
+    updates_per_second: i32,
+) -> VersusLoopResult {
+    let expected_frame_time = 1.0 / updates_per_second as f32;
+    let mut frame_time_accumulated = 0.0;
+    let mut pending_turns = vec![];
+
+    loop {
+        render_versus(versus, cx);
+
+        let keys = get_keys_pressed();
+        let pause_requested = versus_pause_requested(&keys)
+            || (settings.pause_on_focus_loss && get_frame_time() > FOCUS_LOSS_FRAME_TIME);
+        if pause_requested {
+            match pause(cx, settings, &|| render_versus(versus, cx)).await {
+                PauseAction::Restart => return VersusLoopResult::Restart,
+                PauseAction::Quit => return VersusLoopResult::Exit,
+                PauseAction::Resume | PauseAction::Settings => {
+                    next_frame().await;
+                    continue;
+                }
+            }
+        }
+        pending_turns.extend(versus_turns(&keys));
+
+        if frame_time_accumulated >= expected_frame_time {
+            for event in versus.step(&pending_turns) {
+                match event {
+                    VersusEvent::Collision {
+                        player,
+                        cause,
+                        cell,
+                    } => info!(
+                        tick = versus.tick,
+                        player,
+                        x = cell.x,
+                        y = cell.y,
+                        ?cause,
+                        "Collision"
+                    ),
+                    VersusEvent::TargetHit { player } => info!(
+                        tick = versus.tick,
+                        player,
+                        points = versus.players[player].points,
+                        "Target hit"
+                    ),
+                    VersusEvent::RoundOver { winner } => {
+                        return VersusLoopResult::RoundOver(winner);
+                    }
+                }
+            }
+            pending_turns.clear();
+            frame_time_accumulated = 0.0;
+        }
+
+        frame_time_accumulated += get_frame_time();
+        next_frame().await;
+    }
+}
+
+/// Shows the winner of the round and the round wins of the match on top of the final board for
+/// [`ROUND_RESULT_TIME`]. Key presses don't skip it, as the players are likely still steering.
+async fn render_round_result(
+    cx: &Context,
+    versus: &Versus,
+    versus_match: &Match,
+    winner: Option<usize>,
+) {
+    let (title, color) = match winner {
+        Some(winner) => (
+            format!("Player {} wins the round", winner + 1),
+            PLAYER_COLORS[winner].0,
+        ),
+        None => ("Draw".to_owned(), SNAKE_HEAD_COLOR),
+    };
+    let round_wins = format_round_wins(versus_match);
+    let mut frame_time_accumulated = 0.0;
+
+    while frame_time_accumulated < ROUND_RESULT_TIME {
+        render_versus(versus, cx);
+        render_dim_overlay();
+        let center: Point2D<f32, f32> = Point2D::new(screen_width() / 2.0, screen_height() / 2.0);
+        render_text(
+            &title,
+            Point2D::new(center.x, center.y - TITLE_TEXT_SIZE as f32),
+            Some(&cx.font),
+            TITLE_TEXT_SIZE,
+            color,
+        );
+        render_text(
+            &round_wins,
+            Point2D::new(center.x, center.y + OPTION_TEXT_SIZE as f32),
+            Some(&cx.font),
+            OPTION_TEXT_SIZE,
+            SNAKE_HEAD_COLOR,
+        );
+        frame_time_accumulated += get_frame_time();
+        next_frame().await;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::vec;
-
-    fn init(
-        snake: Snake,
-        target: Target,
-        obstacles: Vec<Point2D<i32, i32>>,
-        width: i32,
-        height: i32,
-    ) -> Game {
-        Game {
-            snake,
-            target,
-            obstacles,
-            width,
-            height,
-        }
-    }
 
     fn default_init() -> Game {
-        let width = 10;
-        let height = 10;
+        Game::new(&Level::default(), 0)
+    }
 
-        Game {
-            snake: Snake::new(None, None, width, height),
-            target: Target::new(&[], width, height),
-            obstacles: vec![],
-            width,
-            height,
-        }
+    #[test]
+    fn test_pause_menu_ignores_pause_key() {
+        let mut game = default_init();
+        assert_eq!(
+            KeyPressResult::Pause,
+            game.handle_key_press(Some(KeyCode::Escape))
+        );
+        let mut menu = pause_menu();
+        assert_eq!(None, menu.handle_input(Some(KeyCode::Escape), &[], 1.0));
+        assert_eq!(
+            Some(Selection::Back),
+            menu.handle_input(Some(KeyCode::Escape), &[], 1.0)
+        );
+    }
+
+    #[test_case::test_case(KeyCode::Escape; "escape")]
+    #[test_case::test_case(KeyCode::P; "p")]
+    fn test_versus_pause_menu_ignores_pause_key(key: KeyCode) {
+        assert!(versus_pause_requested(&HashSet::from([key])));
+        let mut menu = pause_menu();
+        assert_eq!(None, menu.handle_input(Some(key), &[], 1.0));
+        assert_eq!(None, menu.handle_input(None, &[], 1.0));
     }
 
     #[test]
@@ -283,67 +951,102 @@ mod tests {
         let mut game = default_init();
 
         let keys_none_result = vec![
-            (KeyCode::Up, Direction::Up, Direction::Left),
-            (KeyCode::Left, Direction::Left, Direction::Down),
-            (KeyCode::Down, Direction::Down, Direction::Right),
-            (KeyCode::Right, Direction::Right, Direction::Up),
-            (KeyCode::W, Direction::Up, Direction::Left),
-            (KeyCode::A, Direction::Left, Direction::Down),
-            (KeyCode::S, Direction::Down, Direction::Right),
-            (KeyCode::D, Direction::Right, Direction::Up),
-            (KeyCode::X, Direction::Right, Direction::Up),
+            (KeyCode::Up, Some(Direction::Up)),
+            (KeyCode::Left, Some(Direction::Left)),
+            (KeyCode::Down, Some(Direction::Down)),
+            (KeyCode::Right, Some(Direction::Right)),
+            (KeyCode::W, Some(Direction::Up)),
+            (KeyCode::A, Some(Direction::Left)),
+            (KeyCode::S, Some(Direction::Down)),
+            (KeyCode::D, Some(Direction::Right)),
+            (KeyCode::X, None),
         ];
         for key in keys_none_result {
-            game.snake.current_direction = key.2;
             assert_eq!(KeyPressResult::None, game.handle_key_press(Some(key.0)));
-            assert_eq!(game.snake.direction, key.1);
+            assert_eq!(game.pending_turns.pop(), key.1);
         }
         assert_eq!(
-            KeyPressResult::Exit,
+            KeyPressResult::Pause,
             game.handle_key_press(Some(KeyCode::Escape))
         );
+        assert_eq!(
+            KeyPressResult::Pause,
+            game.handle_key_press(Some(KeyCode::P))
+        );
     }
 
     #[test]
-    fn test_snake_hit_target() {
-        let width = 5;
-        let height = 5;
-
-        let target = Target {
-            position: Point2D::new(2, 2),
+    fn test_pause_touch() {
+        let mut game = default_init();
+        let touch = Touch {
+            id: 0,
+            phase: macroquad::input::TouchPhase::Started,
+            position: macroquad::math::Vec2 { x: -0.95, y: -0.95 },
         };
-        let snake = Snake::new(
-            Some(Point2D::new(1, 2)),
-            Some(Direction::Right),
-            width,
-            height,
-        );
-        let mut game = init(snake, target, vec![], width, height);
 
-        assert_eq!(UpdateResult::None, game.update());
-        assert_eq!(UpdateResult::TargetHit, game.update());
-        assert_eq!(UpdateResult::None, game.update());
+        assert_eq!(KeyPressResult::Pause, game.handle_touch(&touch));
+        assert!(game.pending_turns.is_empty());
     }
 
     #[test]
-    fn test_snake_hit_obstacle() {
-        let width = 5;
-        let height = 5;
+    fn test_tick_consumes_pending_turns() {
+        let mut game = default_init();
+        game.simulation.snake.current_direction = Direction::Left;
 
-        let target = Target {
-            position: Point2D::new(0, 0),
-        };
-        let snake = Snake::new(
-            Some(Point2D::new(0, 2)),
-            Some(Direction::Right),
-            width,
-            height,
+        game.handle_key_press(Some(KeyCode::Up));
+        game.tick();
+        assert!(game.pending_turns.is_empty());
+        assert_eq!(Direction::Up, game.simulation.snake.current_direction);
+        assert_eq!(vec![(0, vec![Direction::Up])], game.replay.inputs);
+        assert_eq!(1, game.replay.ticks);
+    }
+
+    #[test]
+    fn test_replay_viewer_key_press() {
+        let mut game = default_init();
+        game.handle_key_press(Some(KeyCode::Left));
+        game.tick();
+        game.tick();
+
+        let mut viewer = ReplayViewer::new(&game.replay);
+        viewer.handle_key_press(Some(KeyCode::N));
+        assert_eq!(0, viewer.playback.simulation.tick);
+
+        viewer.handle_key_press(Some(KeyCode::Space));
+        viewer.handle_key_press(Some(KeyCode::N));
+        assert!(viewer.paused);
+        assert_eq!(1, viewer.playback.simulation.tick);
+
+        viewer.handle_key_press(Some(KeyCode::F));
+        assert!(viewer.speed() > 1.0);
+        assert_eq!(
+            KeyPressResult::Exit,
+            viewer.handle_key_press(Some(KeyCode::Escape))
         );
-        let obstacle = vec![Point2D::new(2, 2)];
-        let mut game = init(snake, target, obstacle, width, height);
+    }
 
-        assert_eq!(UpdateResult::None, game.update());
-        assert_eq!(UpdateResult::Collision, game.update());
-        assert_eq!(UpdateResult::None, game.update());
+    #[test_case::test_case(0 => "0:00")]
+    #[test_case::test_case(59 => "0:59")]
+    #[test_case::test_case(61 => "1:01")]
+    #[test_case::test_case(600 => "10:00")]
+    fn test_format_time(seconds: u32) -> String {
+        format_time(seconds)
+    }
+
+    #[test]
+    fn test_versus_turns() {
+        let keys = HashSet::from([KeyCode::Left, KeyCode::W, KeyCode::Escape]);
+        assert_eq!(
+            vec![(0, Direction::Up), (1, Direction::Left)],
+            versus_turns(&keys)
+        );
+        assert!(versus_turns(&HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_format_round_wins() {
+        let mut versus_match = Match::new(2, 3);
+        versus_match.record(Some(1));
+        assert_eq!("0 : 1", format_round_wins(&versus_match));
     }
 }
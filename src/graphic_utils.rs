@@ -1,26 +1,81 @@
-use crate::constants::{ERROR_TEXT_SIZE, POINTS_TEXT_SIZE, SNAKE_HEAD_COLOR};
+use crate::constants::{
+    DIM_COLOR, ERROR_TEXT_SIZE, POINTS_TEXT_SIZE, SNAKE_HEAD_COLOR, TABLE_TEXT_SIZE,
+};
 use euclid::Point2D;
 use macroquad::{
     color::{Color, BLACK, RED},
+    input::{get_last_key_pressed, touches, TouchPhase},
     shapes::draw_rectangle,
     text::{draw_text_ex, get_text_center, Font, TextParams},
     window::{clear_background, next_frame, screen_height, screen_width},
 };
 
+/// Shows `text` in red until a key is pressed or the screen is touched.
 pub async fn render_error_message(text: &str) {
-    clear_background(BLACK);
+    render_message(text, RED).await;
+}
+
+/// Shows `text` until a key is pressed or the screen is touched.
+pub async fn render_message(text: &str, color: Color) {
+    // The key press which led to the error must not close it
+    next_frame().await;
     loop {
+        draw_message(text, color);
+        if get_last_key_pressed().is_some()
+            || touches()
+                .iter()
+                .any(|touch| touch.phase == TouchPhase::Started)
+        {
+            return;
+        }
+        next_frame().await;
+    }
+}
+
+/// Draws `text` word-wrapped in the middle of an otherwise empty screen.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn draw_message(text: &str, color: Color) {
+    const LINE_HEIGHT_RATIO: f32 = 1.2;
+    const CHAR_WIDTH_RATIO: f32 = 0.5;
+
+    let font_size = f32::from(ERROR_TEXT_SIZE);
+    let lines = wrap_text(
+        text,
+        (screen_width() / (font_size * CHAR_WIDTH_RATIO)) as usize,
+    );
+    clear_background(BLACK);
+    let first_line_y =
+        (screen_height() - (lines.len().max(1) - 1) as f32 * font_size * LINE_HEIGHT_RATIO) / 2.0;
+    for (i, line) in lines.iter().enumerate() {
         render_text(
-            text,
-            Point2D::new(screen_width() / 2.0, screen_height() / 2.0),
+            line,
+            Point2D::new(
+                screen_width() / 2.0,
+                first_line_y + i as f32 * font_size * LINE_HEIGHT_RATIO,
+            ),
             None,
             ERROR_TEXT_SIZE,
-            RED,
+            color,
         );
-        next_frame().await;
     }
 }
 
+/// Splits `text` into lines of at most `max_line_length` characters at whitespace. Longer words
+/// get a line of their own.
+fn wrap_text(text: &str, max_line_length: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= max_line_length => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_owned()),
+        }
+    }
+    lines
+}
+
 pub fn render_scaled_square(
     color: Color,
     position: Point2D<i32, i32>,
@@ -36,6 +91,10 @@ pub fn render_scaled_square(
     );
 }
 
+pub fn render_dim_overlay() {
+    draw_rectangle(0.0, 0.0, screen_width(), screen_height(), DIM_COLOR);
+}
+
 pub fn render_x_centered_rect(y_position: f32, height: f32, color: Color) {
     draw_rectangle(
         screen_width() / 4.0,
@@ -79,6 +138,52 @@ pub fn render_points(point_counter: i32, point_target: Option<i32>, font: Option
     render_text(&text, position, font, POINTS_TEXT_SIZE, SNAKE_HEAD_COLOR);
 }
 
+/// Renders the points of a player of a versus game in the color of its snake. The first player's
+/// points are shown on the left, the second player's where [`render_points`] shows them.
+pub fn render_player_points(
+    player: usize,
+    point_counter: i32,
+    point_target: Option<i32>,
+    font: Option<&Font>,
+    color: Color,
+) {
+    let window_size = (screen_width(), screen_height());
+    let (text, position) = format_player_points(window_size, player, point_counter, point_target);
+    render_text(&text, position, font, POINTS_TEXT_SIZE, color);
+}
+
+/// Renders the lines left-aligned below each other in the top left corner, e.g. the names and
+/// points of all players of an online game.
+pub fn render_scoreboard(lines: &[(String, Color)], font: Option<&Font>) {
+    const MARGIN: f32 = 10.0;
+    const LINE_HEIGHT_RATIO: f32 = 1.2;
+
+    let line_height = f32::from(TABLE_TEXT_SIZE) * LINE_HEIGHT_RATIO;
+    for (i, (text, color)) in lines.iter().enumerate() {
+        let params = TextParams {
+            font,
+            font_size: TABLE_TEXT_SIZE,
+            color: *color,
+            ..TextParams::default()
+        };
+        draw_text_ex(text, MARGIN, MARGIN + (i + 1) as f32 * line_height, params);
+    }
+}
+
+fn format_player_points(
+    window_size: (f32, f32),
+    player: usize,
+    point_counter: i32,
+    point_target: Option<i32>,
+) -> (String, Point2D<f32, f32>) {
+    let (text, position) = format_points(window_size, point_counter, point_target);
+    if player.is_multiple_of(2) {
+        (text, Point2D::new(window_size.0 - position.x, position.y))
+    } else {
+        (text, position)
+    }
+}
+
 fn format_points(
     window_size: (f32, f32),
     point_counter: i32,
@@ -111,6 +216,14 @@ fn format_points(
 mod tests {
     use super::*;
 
+    #[test_case::test_case("", 10, &[])]
+    #[test_case::test_case("short", 10, &["short"])]
+    #[test_case::test_case("Row 1, column 2: unknown", 10, &["Row 1,", "column 2:", "unknown"])]
+    #[test_case::test_case("a verylongword b", 4, &["a", "verylongword", "b"])]
+    fn test_wrap_text(text: &str, max_line_length: usize, expected: &[&str]) {
+        assert_eq!(expected, wrap_text(text, max_line_length));
+    }
+
     #[test]
     fn test_format_no_target_points() {
         let window_size = (10.0, 10.0);
@@ -128,4 +241,17 @@ mod tests {
         let res = format_points(window_size, point_counter, Some(point_target));
         assert_eq!((expected_text, expected_position), res);
     }
+
+    #[test]
+    fn test_format_player_points() {
+        let window_size = (10.0, 10.0);
+        assert_eq!(
+            ("3 / 10".to_owned(), Point2D::new(2.0, 1.0)),
+            format_player_points(window_size, 0, 3, Some(10))
+        );
+        assert_eq!(
+            ("4 / 10".to_owned(), Point2D::new(8.0, 1.0)),
+            format_player_points(window_size, 1, 4, Some(10))
+        );
+    }
 }
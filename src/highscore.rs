@@ -0,0 +1,549 @@
+use crate::constants::{
+    BACKGROUND_COLOR, OBSTACLE_COLOR, OPTION_TEXT_SIZE, SNAKE_HEAD_COLOR, TABLE_TEXT_SIZE,
+    TITLE_TEXT_SIZE,
+};
+use crate::graphic_utils::render_text;
+use crate::simulation::Simulation;
+use crate::storage::{self, StorageError};
+use crate::Context;
+use euclid::Point2D;
+use macroquad::input::{
+    clear_input_queue, get_char_pressed, get_last_key_pressed, is_mouse_button_released, touches,
+    KeyCode, MouseButton, TouchPhase,
+};
+use macroquad::miniquad::date;
+use macroquad::window::{clear_background, next_frame, screen_height, screen_width};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tracing::error;
+
+pub const MAX_HIGH_SCORES: usize = 10;
+pub const ENDLESS_GAME: &str = "Endless Game";
+pub const ENDLESS_GAME_SOLID_WALLS: &str = "Endless Solid Walls";
+const HIGH_SCORE_MODES: [&str; 2] = [ENDLESS_GAME, ENDLESS_GAME_SOLID_WALLS];
+const STORAGE_KEY: &str = "highscores";
+const MAX_NAME_LENGTH: usize = 10;
+const DEFAULT_NAME: &str = "Player";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct HighScore {
+    pub name: String,
+    pub score: i32,
+    pub length: usize,
+    /// Duration of the run in seconds.
+    pub duration: f32,
+    pub date: String,
+}
+
+/// The best runs of each game mode, sorted by score.
+#[derive(Debug, PartialEq, Default)]
+pub struct HighScores {
+    tables: BTreeMap<String, Vec<HighScore>>,
+    pub last_name: String,
+}
+
+impl HighScore {
+    pub fn from_simulation(name: &str, simulation: &Simulation, updates_per_second: i32) -> Self {
+        HighScore {
+            name: name.to_owned(),
+            score: simulation.points,
+            length: simulation.snake.position.len(),
+            duration: simulation.tick as f32 / updates_per_second as f32,
+            date: format_date(date::now()),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "score": self.score,
+            "length": self.length,
+            "duration": self.duration,
+            "date": self.date,
+        })
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn from_json(json: &Value) -> Result<Self, HighScoreError> {
+        Ok(HighScore {
+            name: parse_str(&json["name"])?,
+            score: i32::try_from(
+                json["score"]
+                    .as_i64()
+                    .ok_or(HighScoreError::InvalidFormat)?,
+            )
+            .map_err(|_| HighScoreError::InvalidFormat)?,
+            length: usize::try_from(
+                json["length"]
+                    .as_u64()
+                    .ok_or(HighScoreError::InvalidFormat)?,
+            )
+            .map_err(|_| HighScoreError::InvalidFormat)?,
+            duration: json["duration"]
+                .as_f64()
+                .ok_or(HighScoreError::InvalidFormat)? as f32,
+            date: parse_str(&json["date"])?,
+        })
+    }
+}
+
+impl HighScores {
+    pub fn table(&self, mode: &str) -> &[HighScore] {
+        self.tables.get(mode).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn qualifies(&self, mode: &str, score: i32) -> bool {
+        let table = self.table(mode);
+        score > 0
+            && (table.len() < MAX_HIGH_SCORES || table.last().is_some_and(|hs| score > hs.score))
+    }
+
+    /// Inserts `high_score` and returns its rank, or `None` if it did not make it into the table.
+    /// On equal scores, the faster run is ranked higher.
+    pub fn insert(&mut self, mode: &str, high_score: HighScore) -> Option<usize> {
+        let table = self.tables.entry(mode.to_owned()).or_default();
+        let rank = table.partition_point(|hs| {
+            hs.score > high_score.score
+                || (hs.score == high_score.score && hs.duration <= high_score.duration)
+        });
+        if rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+        table.insert(rank, high_score);
+        table.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+
+    fn to_json(&self) -> Value {
+        let tables: serde_json::Map<String, Value> = self
+            .tables
+            .iter()
+            .map(|(mode, table)| {
+                (
+                    mode.clone(),
+                    Value::Array(table.iter().map(HighScore::to_json).collect()),
+                )
+            })
+            .collect();
+        json!({
+            "last_name": self.last_name,
+            "tables": tables,
+        })
+    }
+
+    fn from_json(json: &Value) -> Result<Self, HighScoreError> {
+        let tables = json["tables"]
+            .as_object()
+            .ok_or(HighScoreError::InvalidFormat)?
+            .iter()
+            .map(|(mode, table)| {
+                let table = table
+                    .as_array()
+                    .ok_or(HighScoreError::InvalidFormat)?
+                    .iter()
+                    .map(HighScore::from_json)
+                    .collect::<Result<_, _>>()?;
+                Ok((mode.clone(), table))
+            })
+            .collect::<Result<_, HighScoreError>>()?;
+        Ok(HighScores {
+            tables,
+            last_name: parse_str(&json["last_name"])?,
+        })
+    }
+
+    pub fn load() -> Result<Self, HighScoreError> {
+        match storage::load(STORAGE_KEY)? {
+            Some(contents) => HighScores::from_json(&serde_json::from_str(&contents)?),
+            None => Ok(HighScores::default()),
+        }
+    }
+
+    pub fn load_or_default() -> Self {
+        HighScores::load().unwrap_or_else(|err| {
+            error!(?err, "Failed to load high scores");
+            HighScores::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<(), HighScoreError> {
+        storage::save(STORAGE_KEY, &self.to_json().to_string())?;
+        Ok(())
+    }
+}
+
+fn parse_str(value: &Value) -> Result<String, HighScoreError> {
+    value
+        .as_str()
+        .map(str::to_owned)
+        .ok_or(HighScoreError::InvalidFormat)
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD` (UTC).
+fn format_date(unix_seconds: f64) -> String {
+    #[allow(clippy::cast_possible_truncation)]
+    let days = (unix_seconds / 86_400.0).floor() as i64;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn format_duration(seconds: f32) -> String {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let seconds = seconds as u32;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Line of text typed by the player, limited to `max_length` characters which pass `allowed`.
+struct TextInput {
+    text: String,
+    max_length: usize,
+    allowed: fn(char) -> bool,
+}
+
+impl TextInput {
+    fn handle_char(&mut self, character: char) {
+        if (self.allowed)(character) && self.text.chars().count() < self.max_length {
+            self.text.push(character);
+        }
+    }
+
+    /// Returns `true` once the text is confirmed.
+    fn handle_key_press(&mut self, key: Option<KeyCode>) -> bool {
+        match key {
+            Some(KeyCode::Backspace) => {
+                self.text.pop();
+                false
+            }
+            Some(KeyCode::Enter | KeyCode::KpEnter) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Whether the character may be part of a player name.
+pub fn is_name_char(character: char) -> bool {
+    character.is_alphanumeric() || character == ' ' || character == '-'
+}
+
+fn confirmed_name(name: &str) -> String {
+    let name = name.trim();
+    if name.is_empty() {
+        DEFAULT_NAME.to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+fn tapped() -> bool {
+    is_mouse_button_released(MouseButton::Left)
+        || touches()
+            .iter()
+            .any(|touch| touch.phase == TouchPhase::Ended)
+}
+
+/// Lets the player edit `default` until the text is confirmed with Enter or a tap. Only up to
+/// `max_length` characters which pass `allowed` can be typed.
+pub async fn enter_text(
+    cx: &Context,
+    title: &str,
+    hint: &str,
+    default: &str,
+    max_length: usize,
+    allowed: fn(char) -> bool,
+) -> String {
+    let mut input = TextInput {
+        text: default.chars().take(max_length).collect(),
+        max_length,
+        allowed,
+    };
+    // The key press or tap which opened the input is still reported in the current frame, and
+    // the steering keys typed during the game are still queued as characters
+    next_frame().await;
+    clear_input_queue();
+
+    loop {
+        while let Some(character) = get_char_pressed() {
+            input.handle_char(character);
+        }
+        if input.handle_key_press(get_last_key_pressed()) || tapped() {
+            return input.text;
+        }
+
+        clear_background(BACKGROUND_COLOR);
+        let center = Point2D::new(screen_width() / 2.0, screen_height() / 2.0);
+        render_text(
+            title,
+            Point2D::new(center.x, screen_height() * 0.25),
+            Some(&cx.font),
+            TITLE_TEXT_SIZE,
+            SNAKE_HEAD_COLOR,
+        );
+        render_text(
+            &format!("{}_", input.text),
+            center,
+            Some(&cx.font),
+            OPTION_TEXT_SIZE,
+            SNAKE_HEAD_COLOR,
+        );
+        render_text(
+            hint,
+            Point2D::new(center.x, screen_height() * 0.75),
+            Some(&cx.font),
+            TABLE_TEXT_SIZE,
+            OBSTACLE_COLOR,
+        );
+        next_frame().await;
+    }
+}
+
+async fn enter_name(cx: &Context, default_name: &str) -> String {
+    let name = enter_text(
+        cx,
+        "New High Score!",
+        "Type your name and press Enter",
+        default_name,
+        MAX_NAME_LENGTH,
+        is_name_char,
+    )
+    .await;
+    confirmed_name(&name)
+}
+
+/// Asks for the player's name if the finished run qualifies for the high score table of `mode`,
+/// stores it and shows the updated table.
+pub async fn record_high_score(
+    cx: &Context,
+    mode: &str,
+    simulation: &Simulation,
+    updates_per_second: i32,
+) {
+    let mut high_scores = HighScores::load_or_default();
+    if !high_scores.qualifies(mode, simulation.points) {
+        return;
+    }
+
+    let name = enter_name(cx, &high_scores.last_name).await;
+    let high_score = HighScore::from_simulation(&name, simulation, updates_per_second);
+    high_scores.last_name = name;
+    let rank = high_scores.insert(mode, high_score);
+    if let Err(err) = high_scores.save() {
+        error!(?err, "Failed to save high scores");
+    }
+    show_high_scores(cx, &high_scores, rank.map(|rank| (mode, rank))).await;
+}
+
+/// Shows the high score tables, switchable with left and right. The entry at `highlight` is
+/// rendered in a different color.
+pub async fn show_high_scores(
+    cx: &Context,
+    high_scores: &HighScores,
+    highlight: Option<(&str, usize)>,
+) {
+    let mut mode_index = highlight
+        .and_then(|(mode, _)| HIGH_SCORE_MODES.iter().position(|m| *m == mode))
+        .unwrap_or(0);
+
+    // The key press or click which opened the table must not close it
+    next_frame().await;
+    loop {
+        match get_last_key_pressed() {
+            Some(KeyCode::Left | KeyCode::A) => {
+                mode_index = (mode_index + HIGH_SCORE_MODES.len() - 1) % HIGH_SCORE_MODES.len();
+            }
+            Some(KeyCode::Right | KeyCode::D) => {
+                mode_index = (mode_index + 1) % HIGH_SCORE_MODES.len();
+            }
+            Some(KeyCode::Escape | KeyCode::Enter | KeyCode::Space) => return,
+            _ => {}
+        }
+        if tapped() {
+            return;
+        }
+
+        let mode = HIGH_SCORE_MODES[mode_index];
+        let highlighted_rank = highlight
+            .filter(|(highlighted_mode, _)| *highlighted_mode == mode)
+            .map(|(_, rank)| rank);
+        render_high_score_table(cx, mode, high_scores.table(mode), highlighted_rank);
+        next_frame().await;
+    }
+}
+
+fn render_high_score_table(
+    cx: &Context,
+    mode: &str,
+    table: &[HighScore],
+    highlighted_rank: Option<usize>,
+) {
+    const COLUMNS: [f32; 6] = [0.06, 0.24, 0.46, 0.58, 0.71, 0.87];
+
+    clear_background(BACKGROUND_COLOR);
+    let row_height = screen_height() / (MAX_HIGH_SCORES + 3) as f32;
+    render_text(
+        mode,
+        Point2D::new(screen_width() / 2.0, row_height),
+        Some(&cx.font),
+        TITLE_TEXT_SIZE,
+        SNAKE_HEAD_COLOR,
+    );
+
+    let header = ["#", "Name", "Score", "Length", "Time", "Date"].map(str::to_owned);
+    let rows = table.iter().enumerate().map(|(rank, hs)| {
+        [
+            (rank + 1).to_string(),
+            hs.name.clone(),
+            hs.score.to_string(),
+            hs.length.to_string(),
+            format_duration(hs.duration),
+            hs.date.clone(),
+        ]
+    });
+
+    for (row, cells) in std::iter::once(header).chain(rows).enumerate() {
+        let color = if row == 0 || highlighted_rank == Some(row - 1) {
+            SNAKE_HEAD_COLOR
+        } else {
+            OBSTACLE_COLOR
+        };
+        for (cell, column) in cells.iter().zip(COLUMNS) {
+            render_text(
+                cell,
+                Point2D::new(screen_width() * column, row_height * (row as f32 + 2.0)),
+                Some(&cx.font),
+                TABLE_TEXT_SIZE,
+                color,
+            );
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum HighScoreError {
+    #[error(transparent)]
+    StorageError(#[from] StorageError),
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("The format of the stored high scores is not valid")]
+    InvalidFormat,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn high_score(name: &str, score: i32, duration: f32) -> HighScore {
+        HighScore {
+            name: name.to_owned(),
+            score,
+            length: usize::try_from(score).unwrap() + 1,
+            duration,
+            date: "2025-01-01".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_insert_sorted() {
+        let mut high_scores = HighScores::default();
+
+        assert_eq!(
+            Some(0),
+            high_scores.insert(ENDLESS_GAME, high_score("a", 5, 10.0))
+        );
+        assert_eq!(
+            Some(0),
+            high_scores.insert(ENDLESS_GAME, high_score("b", 8, 10.0))
+        );
+        assert_eq!(
+            Some(2),
+            high_scores.insert(ENDLESS_GAME, high_score("c", 5, 12.0))
+        );
+        assert_eq!(
+            Some(1),
+            high_scores.insert(ENDLESS_GAME, high_score("d", 5, 9.0))
+        );
+
+        let names: Vec<_> = high_scores
+            .table(ENDLESS_GAME)
+            .iter()
+            .map(|hs| hs.name.as_str())
+            .collect();
+        assert_eq!(vec!["b", "d", "a", "c"], names);
+        assert!(high_scores.table("other mode").is_empty());
+    }
+
+    #[test]
+    fn test_table_is_limited() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=i32::try_from(MAX_HIGH_SCORES).unwrap() {
+            assert!(high_scores.qualifies(ENDLESS_GAME, score));
+            high_scores.insert(ENDLESS_GAME, high_score("a", score, 1.0));
+        }
+
+        assert!(!high_scores.qualifies(ENDLESS_GAME, 0));
+        assert!(!high_scores.qualifies(ENDLESS_GAME, 1));
+        assert!(high_scores.qualifies(ENDLESS_GAME, 2));
+        assert_eq!(
+            None,
+            high_scores.insert(ENDLESS_GAME, high_score("b", 1, 2.0))
+        );
+        assert_eq!(
+            Some(0),
+            high_scores.insert(ENDLESS_GAME, high_score("c", 99, 1.0))
+        );
+        assert_eq!(MAX_HIGH_SCORES, high_scores.table(ENDLESS_GAME).len());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(ENDLESS_GAME, high_score("a", 3, 1.5));
+        high_scores.insert(ENDLESS_GAME, high_score("b", 7, 20.0));
+        high_scores.last_name = "b".to_owned();
+
+        assert_eq!(
+            high_scores,
+            HighScores::from_json(&high_scores.to_json()).unwrap()
+        );
+    }
+
+    #[test_case::test_case(0.0, "1970-01-01")]
+    #[test_case::test_case(951_782_400.0, "2000-02-29")]
+    #[test_case::test_case(1_700_000_000.5, "2023-11-14")]
+    fn test_format_date(unix_seconds: f64, expected: &str) {
+        assert_eq!(expected, format_date(unix_seconds));
+    }
+
+    #[test]
+    fn test_name_input() {
+        let mut input = TextInput {
+            text: String::new(),
+            max_length: MAX_NAME_LENGTH,
+            allowed: is_name_char,
+        };
+        for character in "Snake!_King of the board".chars() {
+            input.handle_char(character);
+        }
+        assert_eq!("SnakeKing ", input.text);
+        assert!(!input.handle_key_press(Some(KeyCode::Backspace)));
+        assert_eq!("SnakeKing", input.text);
+        assert!(input.handle_key_press(Some(KeyCode::Enter)));
+
+        assert_eq!(DEFAULT_NAME, confirmed_name("  "));
+    }
+}
@@ -1,12 +1,20 @@
 mod base_levels;
+pub mod check;
+pub mod pack;
 
+use crate::borders::Borders;
 use crate::snake::Direction;
 pub use base_levels::base_levels;
 use euclid::Point2D;
+use serde_json::{json, Value};
 use std::fs::{read_dir, File};
 use std::io::Read;
 
-#[derive(Debug, PartialEq)]
+/// Smallest valid values of the integer properties of a level file.
+const MIN_TARGET_POINTS: i32 = 0;
+const MIN_UPDATES_PER_SECOND: i32 = 1;
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Level {
     pub target_points: Option<i32>,
     pub start_position: Option<Point2D<i32, i32>>,
@@ -15,6 +23,7 @@ pub struct Level {
     pub updates_per_second: i32,
     pub height: i32,
     pub width: i32,
+    pub borders: Borders,
 }
 
 struct Map {
@@ -25,8 +34,8 @@ struct Map {
     width: i32,
 }
 
-impl Level {
-    pub fn default() -> Self {
+impl Default for Level {
+    fn default() -> Self {
         Level {
             target_points: None,
             start_position: None,
@@ -35,9 +44,12 @@ impl Level {
             updates_per_second: 10,
             height: 20,
             width: 20,
+            borders: Borders::WRAP,
         }
     }
+}
 
+impl Level {
     pub fn load_level(dir: &str, name: &str) -> Result<Self, LoadLevelError> {
         let path = format!("{dir}/{name}");
 
@@ -45,12 +57,17 @@ impl Level {
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
 
-        let json: serde_json::Value =
-            serde_json::from_str(&contents).map_err(|_| LoadLevelError::InvalidFormat)?;
+        Level::from_json(&serde_json::from_str(&contents)?)
+    }
 
-        let target_points = parse_property(&json["target_points"])?;
-        let updates_per_second = parse_property(&json["updates_per_second"])?;
+    /// Parses a level given in the format of the level files.
+    pub fn from_json(json: &Value) -> Result<Self, LoadLevelError> {
+        let target_points = parse_property(json, "target_points", MIN_TARGET_POINTS)?;
+        let updates_per_second =
+            parse_property(json, "updates_per_second", MIN_UPDATES_PER_SECOND)?;
         let map = parse_map(&json["map"])?;
+        let borders =
+            Borders::from_json(&json["borders"]).ok_or(LoadLevelError::InvalidField("borders"))?;
 
         Ok(Level {
             target_points: Some(target_points),
@@ -60,22 +77,83 @@ impl Level {
             updates_per_second,
             height: map.height,
             width: map.width,
+            borders,
         })
     }
+
+    /// Converts the level into the format of the level files. Fails for levels which have no
+    /// target points, start position or start direction, like the Endless Game.
+    #[allow(clippy::cast_sign_loss)]
+    pub fn to_json(&self) -> Result<Value, LoadLevelError> {
+        let target_points = self
+            .target_points
+            .ok_or(LoadLevelError::MissingField("target_points"))?;
+        let start_position = self.start_position.ok_or(LoadLevelError::MissingStart)?;
+        let direction = self
+            .start_direction
+            .ok_or(LoadLevelError::MissingDirection)?;
+        let (dx, dy) = direction.offset();
+        let direction_marker = Point2D::new(start_position.x + dx, start_position.y + dy);
+
+        let is_inside =
+            |p: &Point2D<i32, i32>| p.x >= 0 && p.y >= 0 && p.x < self.width && p.y < self.height;
+        let fields = [start_position, direction_marker];
+        if !fields.iter().chain(&self.obstacles).all(is_inside) {
+            return Err(LoadLevelError::InvalidFormat);
+        }
+        // A field holds a single symbol, so an obstacle there would be lost
+        if let Some(obstacle) = self.obstacles.iter().find(|o| fields.contains(o)) {
+            return Err(LoadLevelError::ObstacleOnMarker(*obstacle));
+        }
+
+        let mut map = vec![vec!['-'; self.width as usize]; self.height as usize];
+        for obstacle in &self.obstacles {
+            map[obstacle.y as usize][obstacle.x as usize] = 'o';
+        }
+        map[start_position.y as usize][start_position.x as usize] = 's';
+        map[direction_marker.y as usize][direction_marker.x as usize] = 'd';
+        let map: Vec<String> = map.into_iter().map(String::from_iter).collect();
+
+        let mut json = json!({
+            "target_points": target_points,
+            "updates_per_second": self.updates_per_second,
+            "map": map,
+        });
+        if self.borders != Borders::WRAP {
+            json["borders"] = self.borders.to_json();
+        }
+        Ok(json)
+    }
 }
 
-fn parse_property(value: &serde_json::Value) -> Result<i32, LoadLevelError> {
-    i32::try_from(value.as_i64().ok_or(LoadLevelError::InvalidFormat)?)
-        .map_err(|_| LoadLevelError::TooLargeValue)
+/// Returns the integer property `name`, which has to be at least `minimum`.
+fn parse_property(json: &Value, name: &'static str, minimum: i32) -> Result<i32, LoadLevelError> {
+    let value = &json[name];
+    if value.is_null() {
+        return Err(LoadLevelError::MissingField(name));
+    }
+    let value = i32::try_from(value.as_i64().ok_or(LoadLevelError::InvalidField(name))?)
+        .map_err(|_| LoadLevelError::TooLargeValue)?;
+    if value < minimum {
+        return Err(LoadLevelError::TooSmallValue { name, minimum });
+    }
+    Ok(value)
 }
 
-fn get_dimensions(values: &Vec<Vec<&str>>) -> Result<(i32, i32), LoadLevelError> {
+fn get_dimensions(values: &[Vec<&str>]) -> Result<(i32, i32), LoadLevelError> {
     let height = values.len();
-    let width = values[0].len();
+    let width = values.first().map_or(0, Vec::len);
+    if width == 0 {
+        return Err(LoadLevelError::InvalidFormat);
+    }
 
-    for row in values {
-        if row.len() != width {
-            return Err(LoadLevelError::InvalidFormat);
+    for (row, symbols) in values.iter().enumerate() {
+        if symbols.len() != width {
+            return Err(LoadLevelError::RaggedRow {
+                row,
+                expected: width,
+                found: symbols.len(),
+            });
         }
     }
     Ok((
@@ -84,26 +162,48 @@ fn get_dimensions(values: &Vec<Vec<&str>>) -> Result<(i32, i32), LoadLevelError>
     ))
 }
 
-fn extract_values(value_array: &[serde_json::Value]) -> Result<Vec<Vec<&str>>, LoadLevelError> {
-    value_array
-        .iter()
-        .map(|row| {
-            row.as_array()
-                .ok_or(LoadLevelError::InvalidFormat)?
+/// Splits a string into its symbols.
+fn split_symbols(row: &str) -> Vec<&str> {
+    row.char_indices()
+        .map(|(i, symbol)| &row[i..i + symbol.len_utf8()])
+        .collect()
+}
+
+/// Splits the map into rows of symbols. The map is either an array of rows or a single string
+/// with one row per line. A row is either an array of symbols or a string of symbols.
+/// Empty lines before the first and after the last row of a string are ignored.
+pub fn map_rows(map: &Value) -> Option<Vec<Vec<&str>>> {
+    if let Some(map) = map.as_str() {
+        let lines: Vec<_> = map.lines().collect();
+        let first = lines.iter().position(|line| !line.is_empty())?;
+        let last = lines.iter().rposition(|line| !line.is_empty())?;
+        return Some(
+            lines[first..=last]
                 .iter()
-                .map(|val| val.as_str().ok_or(LoadLevelError::InvalidFormat))
-                .collect::<Result<Vec<&str>, LoadLevelError>>()
+                .map(|line| split_symbols(line))
+                .collect(),
+        );
+    }
+    map.as_array()?
+        .iter()
+        .map(|row| match row {
+            Value::String(row) => Some(split_symbols(row)),
+            Value::Array(symbols) => symbols.iter().map(Value::as_str).collect(),
+            _ => None,
         })
         .collect()
 }
 
 fn extract_single_occurrence_element(
-    element: &[Point2D<i32, i32>],
+    element: Vec<Point2D<i32, i32>>,
+    missing: LoadLevelError,
+    multiple: fn(Vec<Point2D<i32, i32>>) -> LoadLevelError,
 ) -> Result<Point2D<i32, i32>, LoadLevelError> {
-    if element.len() != 1 {
-        return Err(LoadLevelError::InvalidFormat);
+    match element.as_slice() {
+        [] => Err(missing),
+        [position] => Ok(*position),
+        _ => Err(multiple(element)),
     }
-    Ok(element[0])
 }
 
 fn convert_direction(
@@ -119,14 +219,16 @@ fn convert_direction(
             (0, -1) => Direction::Up,
             (1, 0) => Direction::Right,
             (-1, 0) => Direction::Left,
-            _ => return Err(LoadLevelError::InvalidFormat),
+            _ => return Err(LoadLevelError::DirectionNotAdjacent),
         },
     )
 }
 
-fn parse_map(values_raw: &serde_json::Value) -> Result<Map, LoadLevelError> {
-    let value_array = values_raw.as_array().ok_or(LoadLevelError::InvalidFormat)?;
-    let values = extract_values(value_array)?;
+fn parse_map(values_raw: &Value) -> Result<Map, LoadLevelError> {
+    if values_raw.is_null() {
+        return Err(LoadLevelError::MissingField("map"));
+    }
+    let values = map_rows(values_raw).ok_or(LoadLevelError::InvalidFormat)?;
     let (width, height) = get_dimensions(&values)?;
 
     let mut obstacles: Vec<Point2D<i32, i32>> = vec![];
@@ -140,14 +242,28 @@ fn parse_map(values_raw: &serde_json::Value) -> Result<Map, LoadLevelError> {
                 "o" => obstacles.push(Point2D::new(x, y)),
                 "s" => start_positions.push(Point2D::new(x, y)),
                 "d" => directions.push(Point2D::new(x, y)),
-                "-" => {}
-                _ => return Err(LoadLevelError::InvalidFormat),
+                "-" | " " => {}
+                symbol => {
+                    return Err(LoadLevelError::UnknownSymbol {
+                        row: y as usize,
+                        col: x as usize,
+                        symbol: symbol.to_owned(),
+                    })
+                }
             }
         }
     }
 
-    let start_position = extract_single_occurrence_element(&start_positions)?;
-    let direction_marker = extract_single_occurrence_element(&directions)?;
+    let start_position = extract_single_occurrence_element(
+        start_positions,
+        LoadLevelError::MissingStart,
+        LoadLevelError::MultipleStarts,
+    )?;
+    let direction_marker = extract_single_occurrence_element(
+        directions,
+        LoadLevelError::MissingDirection,
+        LoadLevelError::MultipleDirections,
+    )?;
     let direction = convert_direction(start_position, direction_marker)?;
 
     Ok(Map {
@@ -180,16 +296,88 @@ pub enum LoadLevelError {
     #[error(transparent)]
     IoError(#[from] std::io::Error),
 
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+
     #[error("One of the level parameters exceeds its maximum value")]
     TooLargeValue,
 
+    #[error("The field \"{name}\" has to be at least {minimum}")]
+    TooSmallValue { name: &'static str, minimum: i32 },
+
     #[error("The format of the file describing the level is not valid")]
     InvalidFormat,
+
+    #[error("The field \"{0}\" is missing")]
+    MissingField(&'static str),
+
+    #[error("The field \"{0}\" has an invalid value")]
+    InvalidField(&'static str),
+
+    #[error("Row {}, column {}: unknown symbol \"{symbol}\"", .row + 1, .col + 1)]
+    UnknownSymbol {
+        row: usize,
+        col: usize,
+        symbol: String,
+    },
+
+    #[error("Row {} has {found} fields, but the first row has {expected}", .row + 1)]
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("The map has no start position \"s\"")]
+    MissingStart,
+
+    #[error("The map has {} start positions \"s\", but only one is allowed", .0.len())]
+    MultipleStarts(Vec<Point2D<i32, i32>>),
+
+    #[error("The map has no direction marker \"d\"")]
+    MissingDirection,
+
+    #[error("The map has {} direction markers \"d\", but only one is allowed", .0.len())]
+    MultipleDirections(Vec<Point2D<i32, i32>>),
+
+    #[error("The direction marker \"d\" is not next to the start position \"s\"")]
+    DirectionNotAdjacent,
+
+    #[error(
+        "{count} free fields can't be reached from the start position, e.g. row {}, column {}",
+        .first.y + 1,
+        .first.x + 1
+    )]
+    UnreachableFields {
+        count: usize,
+        first: Point2D<i32, i32>,
+    },
+
+    #[error("target_points ({target_points}) exceeds the number of free fields ({free_fields})")]
+    TooManyTargetPoints {
+        target_points: i64,
+        free_fields: usize,
+    },
+
+    #[error(
+        "Row {}, column {}: an obstacle can't be at the start position or the direction marker",
+        .0.y + 1,
+        .0.x + 1
+    )]
+    ObstacleOnMarker(Point2D<i32, i32>),
+
+    #[error("The level file \"{0}\" is not part of the pack")]
+    MissingLevelFile(String),
+
+    #[error("Failed to fetch \"{path}\": {message}")]
+    FetchError { path: String, message: String },
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::borders::Border;
+    use crate::rng::Rng;
     use std::io::Write;
     use std::path::Path;
     use tempfile::tempdir;
@@ -245,6 +433,7 @@ mod test {
             updates_per_second: 8,
             height: 3,
             width: 4,
+            borders: Borders::WRAP,
         };
 
         let dir = tempdir().unwrap();
@@ -260,6 +449,202 @@ mod test {
         assert_eq!(expected, result);
     }
 
+    #[test_case::test_case("target_points", -1, 0)]
+    #[test_case::test_case("updates_per_second", 0, 1)]
+    fn test_too_small_property(name: &str, value: i32, expected_minimum: i32) {
+        let mut json = json!({
+            "target_points": 10,
+            "updates_per_second": 8,
+            "map": ["sd"],
+        });
+        json[name] = json!(value);
+        assert!(matches!(
+            Level::from_json(&json),
+            Err(LoadLevelError::TooSmallValue { name: field, minimum })
+                if field == name && minimum == expected_minimum
+        ));
+    }
+
+    #[test_case::test_case(json!(["o--o", "-sd ", "o--o"]); "row strings")]
+    #[test_case::test_case(json!("o--o\n-sd \no--o\n"); "multi-line string")]
+    #[test_case::test_case(json!("\n\no--o\n-sd \no--o\n\n"); "surrounding empty lines")]
+    #[test_case::test_case(
+        json!([["o", "-", "-", "o"], ["-", "s", "d", " "], "o--o"]);
+        "mixed rows"
+    )]
+    fn test_compact_map(map: Value) {
+        let level = Level::from_json(&json!({
+            "target_points": 10,
+            "updates_per_second": 8,
+            "map": map,
+        }))
+        .unwrap();
+
+        assert_eq!(Some(Point2D::new(1, 1)), level.start_position);
+        assert_eq!(Some(Direction::Right), level.start_direction);
+        assert_eq!((4, 3), (level.width, level.height));
+        assert_eq!(
+            vec![
+                Point2D::new(0, 0),
+                Point2D::new(3, 0),
+                Point2D::new(0, 2),
+                Point2D::new(3, 2),
+            ],
+            level.obstacles
+        );
+    }
+
+    #[test]
+    fn test_compact_map_blank_row() {
+        let level = Level::from_json(&json!({
+            "target_points": 10,
+            "updates_per_second": 8,
+            "map": "o--o\n    \n-sd-\n",
+        }))
+        .unwrap();
+
+        assert_eq!((4, 3), (level.width, level.height));
+        assert_eq!(Some(Point2D::new(1, 2)), level.start_position);
+        assert_eq!(
+            vec![Point2D::new(0, 0), Point2D::new(3, 0)],
+            level.obstacles
+        );
+    }
+
+    #[test]
+    fn test_compact_map_unknown_symbol() {
+        let err = Level::from_json(&json!({
+            "target_points": 10,
+            "updates_per_second": 8,
+            "map": ["o-s", "dö-"],
+        }))
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            LoadLevelError::UnknownSymbol { row: 1, col: 1, ref symbol } if symbol == "ö"
+        ));
+    }
+
+    #[test]
+    fn test_load_level_with_borders() {
+        let file_content: &str = r#"{
+            "target_points": 10,
+            "updates_per_second": 8,
+            "borders": {"left": "solid", "right": "solid"},
+            "map": [
+                ["-","d","-"],
+                ["-","s","-"]
+            ]
+        }"#;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let file_name = "level.json";
+
+        let mut file = File::create(path.join(file_name)).unwrap();
+        writeln!(file, "{}", file_content).unwrap();
+
+        let dir_string = path.to_string_lossy().into_owned();
+        let result = Level::load_level(&dir_string, file_name).unwrap();
+        assert_eq!(
+            Borders {
+                left: Border::Solid,
+                right: Border::Solid,
+                ..Borders::WRAP
+            },
+            result.borders
+        );
+    }
+
+    #[test]
+    fn test_bundled_levels_round_trip() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("levels");
+        let dir = dir.to_string_lossy();
+        let custom_levels = search_for_custom_levels(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|name| Level::load_level(&dir, &name).unwrap());
+        for level in base_levels().into_iter().chain(custom_levels) {
+            let json = level.to_json().unwrap();
+            assert_eq!(level, Level::from_json(&json).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_random_levels_round_trip() {
+        for seed in 0..200 {
+            let mut rng = Rng::new(seed);
+            let width: i32 = rng.gen_range(3, 30);
+            let height: i32 = rng.gen_range(2, 30);
+            let start_position =
+                Point2D::new(rng.gen_range(1, width - 1), rng.gen_range(0, height));
+            let mut obstacles = vec![];
+            for _ in 0..rng.gen_range(0, width * height / 2) {
+                let obstacle = Point2D::new(rng.gen_range(0, width), rng.gen_range(0, height));
+                if obstacle.y != start_position.y || (obstacle.x - start_position.x).abs() > 1 {
+                    obstacles.push(obstacle);
+                }
+            }
+            obstacles.sort_by_key(|o| (o.y, o.x));
+            obstacles.dedup();
+            let level = Level {
+                target_points: Some(rng.gen_range(1, 100)),
+                start_position: Some(start_position),
+                start_direction: Some(if rng.gen_range(0, 2) == 0 {
+                    Direction::Left
+                } else {
+                    Direction::Right
+                }),
+                obstacles,
+                updates_per_second: rng.gen_range(1, 30),
+                height,
+                width,
+                borders: Borders {
+                    top: if rng.gen_range(0, 2) == 0 {
+                        Border::Wrap
+                    } else {
+                        Border::Solid
+                    },
+                    ..Borders::WRAP
+                },
+            };
+
+            let json = level.to_json().unwrap();
+            assert_eq!(level, Level::from_json(&json).unwrap(), "seed {seed}");
+        }
+    }
+
+    #[test_case::test_case(
+        Level::default() => matches LoadLevelError::MissingField("target_points");
+        "endless game"
+    )]
+    #[test_case::test_case(
+        Level { target_points: Some(1), ..Level::default() } => matches LoadLevelError::MissingStart;
+        "no start position"
+    )]
+    #[test_case::test_case(
+        Level {
+            target_points: Some(1),
+            start_position: Some(Point2D::new(0, 0)),
+            start_direction: Some(Direction::Up),
+            ..Level::default()
+        } => matches LoadLevelError::InvalidFormat;
+        "direction marker outside"
+    )]
+    #[test_case::test_case(
+        Level {
+            target_points: Some(1),
+            start_position: Some(Point2D::new(1, 1)),
+            start_direction: Some(Direction::Up),
+            obstacles: vec![Point2D::new(3, 3), Point2D::new(1, 0)],
+            ..Level::default()
+        } => matches LoadLevelError::ObstacleOnMarker(position) if position == Point2D::new(1, 0);
+        "obstacle on direction marker"
+    )]
+    fn try_to_convert_invalid_level(level: Level) -> LoadLevelError {
+        level.to_json().unwrap_err()
+    }
+
     #[test_case::test_case(
         r#"{
             "updates_per_second": 8,
@@ -270,6 +655,7 @@ mod test {
                 ["o","-","-","o"]
             ]
         }"#
+        => matches LoadLevelError::MissingField("target_points")
     )]
     #[test_case::test_case(
         r#"{
@@ -281,6 +667,7 @@ mod test {
                 ["o","-","-","o"]
             ]
         }"#
+        => matches LoadLevelError::MissingField("updates_per_second")
     )]
     #[test_case::test_case(
         r#"{
@@ -293,6 +680,7 @@ mod test {
                 ["o","-","-","o"]
             ]
         }"#
+        => matches LoadLevelError::UnknownSymbol { row: 0, col: 1, .. }
     )]
     #[test_case::test_case(
         r#"{
@@ -305,6 +693,7 @@ mod test {
                 ["o","-","-","o"]
             ]
         }"#
+        => matches LoadLevelError::MissingStart
     )]
     #[test_case::test_case(
         r#"{
@@ -317,6 +706,7 @@ mod test {
                 ["o","-","-","o"]
             ]
         }"#
+        => matches LoadLevelError::MultipleStarts(_)
     )]
     #[test_case::test_case(
         r#"{
@@ -329,6 +719,7 @@ mod test {
                 ["o","-","-","o"]
             ]
         }"#
+        => matches LoadLevelError::MissingDirection
     )]
     #[test_case::test_case(
         r#"{
@@ -341,6 +732,7 @@ mod test {
                 ["o","-","-","o"]
             ]
         }"#
+        => matches LoadLevelError::MultipleDirections(_)
     )]
     #[test_case::test_case(
         r#"{
@@ -353,6 +745,7 @@ mod test {
                 ["o","-","-","o"]
             ]
         }"#
+        => matches LoadLevelError::DirectionNotAdjacent
     )]
     #[test_case::test_case(
         r#"{
@@ -365,8 +758,32 @@ mod test {
                 ["o","-","-"]
             ]
         }"#
+        => matches LoadLevelError::RaggedRow { row: 3, expected: 4, found: 3 }
+    )]
+    #[test_case::test_case(
+        r#"{
+            "target_points": 10,
+            "updates_per_second": 8,
+            "borders": "walls",
+            "map": [
+                ["o","-","-","o"],
+                ["-","s","d","-"],
+                ["-","-","-","-"],
+                ["o","-","-","o"]
+            ]
+        }"#
+        => matches LoadLevelError::InvalidField("borders")
+    )]
+    #[test_case::test_case(r#"{"target_points": 10"# => matches LoadLevelError::JsonError(_))]
+    #[test_case::test_case(
+        r#"{"target_points": 10, "updates_per_second": 8}"#
+        => matches LoadLevelError::MissingField("map")
+    )]
+    #[test_case::test_case(
+        r#"{"target_points": 10, "updates_per_second": 8, "map": []}"#
+        => matches LoadLevelError::InvalidFormat
     )]
-    fn try_to_load_invalid_level(file_content: &str) {
+    fn try_to_load_invalid_level(file_content: &str) -> LoadLevelError {
         let dir = tempdir().unwrap();
         let path = dir.path();
         let file_name = "level.json";
@@ -376,7 +793,6 @@ mod test {
         writeln!(file, "{}", file_content).unwrap();
 
         let dir_string = path.to_string_lossy().into_owned();
-        let err = Level::load_level(&dir_string, file_name).unwrap_err();
-        assert!(matches!(err, LoadLevelError::InvalidFormat));
+        Level::load_level(&dir_string, file_name).unwrap_err()
     }
 }
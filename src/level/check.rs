@@ -0,0 +1,387 @@
+use super::{map_rows, parse_property, LoadLevelError, MIN_TARGET_POINTS, MIN_UPDATES_PER_SECOND};
+use crate::borders::{Border, Borders};
+use euclid::Point2D;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// A problem found in a level file, together with the line of the file it was found in if that is
+/// known. Problems of the map are located by their row and column instead.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub line: Option<usize>,
+    pub error: LoadLevelError,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "Line {line}: {}", self.error),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+impl From<LoadLevelError> for Diagnostic {
+    fn from(error: LoadLevelError) -> Self {
+        Diagnostic { line: None, error }
+    }
+}
+
+/// Returns the line of the first occurrence of the property `name` in `contents`, starting at 1.
+fn line_of_property(contents: &str, name: &str) -> Option<usize> {
+    let position = contents.find(&format!("\"{name}\""))?;
+    Some(contents[..position].matches('\n').count() + 1)
+}
+
+/// Reads the level file at `path` and checks it, see [`check_level`].
+pub fn check_file(path: &Path) -> Result<Vec<Diagnostic>, std::io::Error> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    Ok(check_level(&contents))
+}
+
+/// Checks the contents of a level file. Unlike [`super::Level::load_level`], all problems are
+/// reported instead of only the first one, including problems which make a level unwinnable
+/// although it can be loaded. An empty result means that the level is valid.
+pub fn check_level(contents: &str) -> Vec<Diagnostic> {
+    let json: Value = match serde_json::from_str(contents) {
+        Ok(json) => json,
+        Err(err) => {
+            return vec![Diagnostic {
+                line: Some(err.line()),
+                error: err.into(),
+            }]
+        }
+    };
+
+    let mut diagnostics = vec![];
+    let target_points = check_property(
+        contents,
+        &json,
+        "target_points",
+        MIN_TARGET_POINTS,
+        &mut diagnostics,
+    );
+    check_property(
+        contents,
+        &json,
+        "updates_per_second",
+        MIN_UPDATES_PER_SECOND,
+        &mut diagnostics,
+    );
+    let borders = Borders::from_json(&json["borders"]).unwrap_or_else(|| {
+        diagnostics.push(Diagnostic {
+            line: line_of_property(contents, "borders"),
+            error: LoadLevelError::InvalidField("borders"),
+        });
+        Borders::WRAP
+    });
+
+    let Some(rows) = extract_rows(&json["map"]) else {
+        diagnostics.push(if json["map"].is_null() {
+            LoadLevelError::MissingField("map").into()
+        } else {
+            Diagnostic {
+                line: line_of_property(contents, "map"),
+                error: LoadLevelError::InvalidFormat,
+            }
+        });
+        return diagnostics;
+    };
+    let map_errors = check_map(&rows, target_points.map(i64::from), borders);
+    diagnostics.extend(map_errors.into_iter().map(Diagnostic::from));
+    diagnostics
+}
+
+/// Returns the value of the integer property `name` if it is valid, see [`parse_property`].
+fn check_property(
+    contents: &str,
+    json: &Value,
+    name: &'static str,
+    minimum: i32,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<i32> {
+    parse_property(json, name, minimum)
+        .map_err(|error| {
+            diagnostics.push(Diagnostic {
+                line: line_of_property(contents, name),
+                error,
+            });
+        })
+        .ok()
+}
+
+fn extract_rows(map: &Value) -> Option<Vec<Vec<&str>>> {
+    let rows = map_rows(map)?;
+    (!rows.is_empty() && !rows[0].is_empty()).then_some(rows)
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+fn check_map(
+    rows: &[Vec<&str>],
+    target_points: Option<i64>,
+    borders: Borders,
+) -> Vec<LoadLevelError> {
+    let mut diagnostics = vec![];
+    let width = rows[0].len();
+    let mut obstacles = vec![vec![false; width]; rows.len()];
+    let mut starts = vec![];
+    let mut directions = vec![];
+
+    for (row, symbols) in rows.iter().enumerate() {
+        if symbols.len() != width {
+            diagnostics.push(LoadLevelError::RaggedRow {
+                row,
+                expected: width,
+                found: symbols.len(),
+            });
+        }
+        for (col, symbol) in symbols.iter().enumerate() {
+            let position = Point2D::new(col as i32, row as i32);
+            match *symbol {
+                "o" if col < width => obstacles[row][col] = true,
+                "o" => {}
+                "s" => starts.push(position),
+                "d" => directions.push(position),
+                "-" | " " => {}
+                _ => diagnostics.push(LoadLevelError::UnknownSymbol {
+                    row,
+                    col,
+                    symbol: (*symbol).to_owned(),
+                }),
+            }
+        }
+    }
+
+    match starts.len() {
+        0 => diagnostics.push(LoadLevelError::MissingStart),
+        1 => {}
+        _ => diagnostics.push(LoadLevelError::MultipleStarts(starts.clone())),
+    }
+    match directions.len() {
+        0 => diagnostics.push(LoadLevelError::MissingDirection),
+        1 => {}
+        _ => diagnostics.push(LoadLevelError::MultipleDirections(directions.clone())),
+    }
+    if let ([start], [direction]) = (starts.as_slice(), directions.as_slice()) {
+        let distance = (direction.x - start.x).abs() + (direction.y - start.y).abs();
+        if distance != 1 {
+            diagnostics.push(LoadLevelError::DirectionNotAdjacent);
+        }
+    }
+
+    // The remaining checks need a map which can be loaded
+    if !diagnostics.is_empty() {
+        return diagnostics;
+    }
+
+    let unreachable = unreachable_fields(&obstacles, starts[0], borders);
+    if let Some(first) = unreachable.first() {
+        diagnostics.push(LoadLevelError::UnreachableFields {
+            count: unreachable.len(),
+            first: *first,
+        });
+    }
+    let free_fields = obstacles.iter().flatten().filter(|o| !**o).count();
+    if let Some(target_points) = target_points {
+        if target_points > free_fields as i64 {
+            diagnostics.push(LoadLevelError::TooManyTargetPoints {
+                target_points,
+                free_fields,
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Returns all free fields which the snake can't reach from `start`, in row-major order.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+fn unreachable_fields(
+    obstacles: &[Vec<bool>],
+    start: Point2D<i32, i32>,
+    borders: Borders,
+) -> Vec<Point2D<i32, i32>> {
+    let height = obstacles.len() as i32;
+    let width = obstacles[0].len() as i32;
+    let mut reached = vec![vec![false; width as usize]; height as usize];
+    reached[start.y as usize][start.x as usize] = true;
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(position) = queue.pop_front() {
+        let neighbours = [
+            (position.x, position.y - 1, borders.top),
+            (position.x, position.y + 1, borders.bottom),
+            (position.x - 1, position.y, borders.left),
+            (position.x + 1, position.y, borders.right),
+        ];
+        for (x, y, border) in neighbours {
+            let outside = x < 0 || y < 0 || x >= width || y >= height;
+            if outside && border == Border::Solid {
+                continue;
+            }
+            let (x, y) = ((x + width) % width, (y + height) % height);
+            let (col, row) = (x as usize, y as usize);
+            if !obstacles[row][col] && !reached[row][col] {
+                reached[row][col] = true;
+                queue.push_back(Point2D::new(x, y));
+            }
+        }
+    }
+
+    let mut unreachable = vec![];
+    for (row, fields) in obstacles.iter().enumerate() {
+        for (col, obstacle) in fields.iter().enumerate() {
+            if !obstacle && !reached[row][col] {
+                unreachable.push(Point2D::new(col as i32, row as i32));
+            }
+        }
+    }
+    unreachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::search_for_custom_levels;
+
+    fn messages(errors: &[LoadLevelError]) -> Vec<String> {
+        errors.iter().map(ToString::to_string).collect()
+    }
+
+    fn errors(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.error.to_string())
+            .collect()
+    }
+
+    fn level(map: &str) -> String {
+        format!(r#"{{"target_points": 3, "updates_per_second": 8, "map": {map}}}"#)
+    }
+
+    #[test_case::test_case(r#"[["o","-","-"],["-","s","d"],["-","-","o"]]"#)]
+    #[test_case::test_case(r#"["o--", "-sd", "  o"]"#)]
+    #[test_case::test_case(r#""o--\n-sd\n--o""#)]
+    fn test_valid_level(map: &str) {
+        assert!(check_level(&level(map)).is_empty());
+    }
+
+    #[test_case::test_case(
+        &level(r#"[["o","x","-"],["-","s","d"],["-","-","?"]]"#),
+        vec![
+            LoadLevelError::UnknownSymbol { row: 0, col: 1, symbol: "x".to_owned() },
+            LoadLevelError::UnknownSymbol { row: 2, col: 2, symbol: "?".to_owned() },
+        ];
+        "unknown symbols"
+    )]
+    #[test_case::test_case(
+        &level(r#"[["o","-","-"],["-","s","d"],["-","-"]]"#),
+        vec![LoadLevelError::RaggedRow { row: 2, expected: 3, found: 2 }];
+        "ragged row"
+    )]
+    #[test_case::test_case(
+        &level(r#"[["o","-","-"],["-","-","-"],["-","-","o"]]"#),
+        vec![LoadLevelError::MissingStart, LoadLevelError::MissingDirection];
+        "missing start and direction"
+    )]
+    #[test_case::test_case(
+        &level(r#"[["s","-","-"],["-","s","d"],["-","-","o"]]"#),
+        vec![LoadLevelError::MultipleStarts(vec![Point2D::new(0, 0), Point2D::new(1, 1)])];
+        "multiple starts"
+    )]
+    #[test_case::test_case(
+        &level(r#"[["d","-","-"],["-","s","-"],["-","-","o"]]"#),
+        vec![LoadLevelError::DirectionNotAdjacent];
+        "direction not adjacent"
+    )]
+    #[test_case::test_case(
+        &level(r#"[["-","o","-"],["o","o","o"],["-","o","s","d"]]"#),
+        vec![LoadLevelError::RaggedRow { row: 2, expected: 3, found: 4 }];
+        "ragged row with markers"
+    )]
+    #[test_case::test_case(
+        &level(r#"[["o","o","o","o"],["o","-","o","-"],["o","o","o","-"],["s","d","-","-"]]"#),
+        vec![LoadLevelError::UnreachableFields { count: 1, first: Point2D::new(1, 1) }];
+        "unreachable fields"
+    )]
+    #[test_case::test_case(
+        &level(r#"[["o","o","o"],["o","s","d"],["o","o","o"]]"#),
+        vec![LoadLevelError::TooManyTargetPoints { target_points: 3, free_fields: 2 }];
+        "too many target points"
+    )]
+    #[test_case::test_case(
+        r#"{"updates_per_second": 0, "borders": "walls", "map": [["s","d"]]}"#,
+        vec![
+            LoadLevelError::MissingField("target_points"),
+            LoadLevelError::TooSmallValue { name: "updates_per_second", minimum: 1 },
+            LoadLevelError::InvalidField("borders"),
+        ];
+        "invalid properties"
+    )]
+    #[test_case::test_case(
+        r#"{"target_points": 1, "updates_per_second": 8, "map": [[1]]}"#,
+        vec![LoadLevelError::InvalidFormat];
+        "invalid map"
+    )]
+    fn test_invalid_level(contents: &str, expected: Vec<LoadLevelError>) {
+        assert_eq!(messages(&expected), errors(&check_level(contents)));
+    }
+
+    #[test]
+    fn test_located_property() {
+        let contents =
+            "{\n  \"target_points\": 1,\n  \"updates_per_second\": -1,\n  \"map\": [\"sd\"]\n}";
+        let diagnostics = check_level(contents);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(Some(3), diagnostics[0].line);
+        assert_eq!(
+            "Line 3: The field \"updates_per_second\" has to be at least 1",
+            diagnostics[0].to_string()
+        );
+    }
+
+    #[test]
+    fn test_unreachable_with_solid_borders() {
+        let map = r#"[["-","o","s","d"]]"#;
+        let wrap = level(map);
+        let solid = format!(
+            r#"{{"target_points": 1, "updates_per_second": 8, "borders": "solid", "map": {map}}}"#
+        );
+
+        assert!(check_level(&wrap).is_empty());
+        assert_eq!(
+            messages(&[LoadLevelError::UnreachableFields {
+                count: 1,
+                first: Point2D::new(0, 0)
+            }]),
+            errors(&check_level(&solid))
+        );
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        assert!(matches!(
+            check_level("{\n").as_slice(),
+            [Diagnostic {
+                line: Some(2),
+                error: LoadLevelError::JsonError(_)
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_shipped_level_files() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("levels");
+        for name in search_for_custom_levels(&dir.to_string_lossy()).unwrap() {
+            assert!(check_file(&dir.join(name)).unwrap().is_empty());
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use super::{Level, LoadLevelError};
+use serde_json::{Map, Value};
+use std::fs::{read_dir, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest file which turns a directory into a level pack.
+pub const MANIFEST_FILE: &str = "pack.json";
+
+/// Extension of a level pack bundled into a single file. A bundle is the manifest with an
+/// additional field "files", which maps the file names of the levels to their contents.
+pub const BUNDLE_EXTENSION: &str = "snakepack";
+
+/// A level of a pack, in the order of the manifest.
+#[derive(Debug, PartialEq)]
+pub struct PackEntry {
+    pub file: String,
+    pub title: Option<String>,
+    pub hint: Option<String>,
+}
+
+/// Metadata and level order of a level pack, stored in its [`MANIFEST_FILE`].
+#[derive(Debug, PartialEq)]
+pub struct PackManifest {
+    pub title: String,
+    pub author: Option<String>,
+    pub version: Option<String>,
+    pub levels: Vec<PackEntry>,
+}
+
+fn parse_optional_string(
+    json: &Value,
+    name: &'static str,
+) -> Result<Option<String>, LoadLevelError> {
+    match &json[name] {
+        Value::Null => Ok(None),
+        Value::String(value) => Ok(Some(value.clone())),
+        _ => Err(LoadLevelError::InvalidField(name)),
+    }
+}
+
+impl PackManifest {
+    pub fn from_json(json: &Value) -> Result<Self, LoadLevelError> {
+        let title =
+            parse_optional_string(json, "title")?.ok_or(LoadLevelError::MissingField("title"))?;
+        if json["levels"].is_null() {
+            return Err(LoadLevelError::MissingField("levels"));
+        }
+        let levels = json["levels"]
+            .as_array()
+            .ok_or(LoadLevelError::InvalidField("levels"))?
+            .iter()
+            .map(|entry| {
+                // A level is given by its file name or by an object with further information
+                if let Some(file) = entry.as_str() {
+                    return Ok(PackEntry {
+                        file: file.to_owned(),
+                        title: None,
+                        hint: None,
+                    });
+                }
+                Ok(PackEntry {
+                    file: parse_optional_string(entry, "file")?
+                        .ok_or(LoadLevelError::MissingField("file"))?,
+                    title: parse_optional_string(entry, "title")?,
+                    hint: parse_optional_string(entry, "hint")?,
+                })
+            })
+            .collect::<Result<_, LoadLevelError>>()?;
+
+        Ok(PackManifest {
+            title,
+            author: parse_optional_string(json, "author")?,
+            version: parse_optional_string(json, "version")?,
+            levels,
+        })
+    }
+}
+
+/// Where the level files of a pack are read from.
+enum PackFiles {
+    Directory(PathBuf),
+    Bundle(Map<String, Value>),
+}
+
+/// A level pack, either a directory with a [`MANIFEST_FILE`] or a single bundle file.
+pub struct Pack {
+    pub manifest: PackManifest,
+    files: PackFiles,
+}
+
+fn read_json(path: &Path) -> Result<Value, LoadLevelError> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+impl Pack {
+    /// Loads the pack in the directory or bundle file `path`.
+    pub fn load(path: &Path) -> Result<Self, LoadLevelError> {
+        if path.is_dir() {
+            Ok(Pack {
+                manifest: PackManifest::from_json(&read_json(&path.join(MANIFEST_FILE))?)?,
+                files: PackFiles::Directory(path.to_path_buf()),
+            })
+        } else {
+            Pack::from_bundle(&read_json(path)?)
+        }
+    }
+
+    /// Parses a pack given in the format of the bundle files.
+    pub fn from_bundle(json: &Value) -> Result<Self, LoadLevelError> {
+        let manifest = PackManifest::from_json(json)?;
+        if json["files"].is_null() {
+            return Err(LoadLevelError::MissingField("files"));
+        }
+        let files = json["files"]
+            .as_object()
+            .ok_or(LoadLevelError::InvalidField("files"))?
+            .clone();
+        Ok(Pack {
+            manifest,
+            files: PackFiles::Bundle(files),
+        })
+    }
+
+    /// Loads the level `file` of the pack with the same validation as [`Level::load_level`].
+    pub fn level(&self, file: &str) -> Result<Level, LoadLevelError> {
+        match &self.files {
+            PackFiles::Directory(dir) => Level::load_level(&dir.to_string_lossy(), file),
+            PackFiles::Bundle(files) => Level::from_json(
+                files
+                    .get(file)
+                    .ok_or_else(|| LoadLevelError::MissingLevelFile(file.to_owned()))?,
+            ),
+        }
+    }
+}
+
+/// Bundles the pack in the directory `dir` into a single file. Fails if the manifest or one of
+/// the levels is not valid.
+pub fn bundle(dir: &Path) -> Result<Value, LoadLevelError> {
+    let mut json = read_json(&dir.join(MANIFEST_FILE))?;
+    let manifest = PackManifest::from_json(&json)?;
+
+    let mut files = Map::new();
+    for entry in manifest.levels {
+        let level = read_json(&dir.join(&entry.file))?;
+        Level::from_json(&level)?;
+        files.insert(entry.file, level);
+    }
+    json["files"] = Value::Object(files);
+    Ok(json)
+}
+
+/// Returns the names of all subdirectories of `search_path` which contain a [`MANIFEST_FILE`] and
+/// of all bundle files in it, in alphabetical order.
+pub fn search_for_packs(search_path: &str) -> Result<Vec<String>, LoadLevelError> {
+    let mut packs: Vec<String> = read_dir(search_path)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.join(MANIFEST_FILE).is_file()
+                || (path.is_file() && path.extension().is_some_and(|ext| ext == BUNDLE_EXTENSION))
+        })
+        .filter_map(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+        .collect();
+    packs.sort();
+    Ok(packs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs::create_dir;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    const LEVEL_JSON: &str = r#"{"target_points": 3, "updates_per_second": 8, "map": ["sd-"]}"#;
+
+    #[test]
+    fn test_manifest() {
+        let json = json!({
+            "title": "Mazes",
+            "author": "Tzuzuzj",
+            "version": "1.0",
+            "levels": [
+                "intro.json",
+                {"file": "spiral.json", "title": "Spiral", "hint": "Take it slow"},
+            ],
+        });
+
+        let expected = PackManifest {
+            title: "Mazes".to_owned(),
+            author: Some("Tzuzuzj".to_owned()),
+            version: Some("1.0".to_owned()),
+            levels: vec![
+                PackEntry {
+                    file: "intro.json".to_owned(),
+                    title: None,
+                    hint: None,
+                },
+                PackEntry {
+                    file: "spiral.json".to_owned(),
+                    title: Some("Spiral".to_owned()),
+                    hint: Some("Take it slow".to_owned()),
+                },
+            ],
+        };
+        assert_eq!(expected, PackManifest::from_json(&json).unwrap());
+    }
+
+    #[test_case::test_case(json!({"levels": []}) => matches LoadLevelError::MissingField("title"))]
+    #[test_case::test_case(json!({"title": "A"}) => matches LoadLevelError::MissingField("levels"))]
+    #[test_case::test_case(
+        json!({"title": "A", "levels": [{"title": "B"}]})
+        => matches LoadLevelError::MissingField("file")
+    )]
+    #[test_case::test_case(
+        json!({"title": "A", "author": 1, "levels": []})
+        => matches LoadLevelError::InvalidField("author")
+    )]
+    fn test_invalid_manifest(json: Value) -> LoadLevelError {
+        PackManifest::from_json(&json).unwrap_err()
+    }
+
+    #[test]
+    fn test_search_for_packs() {
+        let dir = tempdir().unwrap();
+        for name in ["b_pack", "a_pack", "no_pack"] {
+            create_dir(dir.path().join(name)).unwrap();
+        }
+        File::create(dir.path().join("b_pack").join(MANIFEST_FILE)).unwrap();
+        File::create(dir.path().join("a_pack").join(MANIFEST_FILE)).unwrap();
+        File::create(dir.path().join("level.json")).unwrap();
+        File::create(dir.path().join("c_pack.snakepack")).unwrap();
+
+        let dir_string = dir.path().to_string_lossy().into_owned();
+        assert_eq!(
+            vec!["a_pack", "b_pack", "c_pack.snakepack"],
+            search_for_packs(&dir_string).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let dir = tempdir().unwrap();
+        let mut manifest = File::create(dir.path().join(MANIFEST_FILE)).unwrap();
+        writeln!(manifest, r#"{{"title": "A", "levels": ["a.json"]}}"#).unwrap();
+        let mut level = File::create(dir.path().join("a.json")).unwrap();
+        writeln!(level, "{LEVEL_JSON}").unwrap();
+
+        let bundle_path = dir.path().join("a.snakepack");
+        let mut bundle_file = File::create(&bundle_path).unwrap();
+        writeln!(bundle_file, "{}", bundle(dir.path()).unwrap()).unwrap();
+
+        let from_dir = Pack::load(dir.path()).unwrap();
+        let from_bundle = Pack::load(&bundle_path).unwrap();
+        assert_eq!(from_dir.manifest, from_bundle.manifest);
+        assert_eq!(
+            from_dir.level("a.json").unwrap(),
+            from_bundle.level("a.json").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_bundle() {
+        let pack = Pack::from_bundle(&json!({
+            "title": "A",
+            "levels": ["a.json", "b.json", "c.json"],
+            "files": {
+                "a.json": serde_json::from_str::<Value>(LEVEL_JSON).unwrap(),
+                "b.json": {"target_points": 3},
+            },
+        }))
+        .unwrap();
+
+        assert!(pack.level("a.json").is_ok());
+        assert!(matches!(
+            pack.level("b.json"),
+            Err(LoadLevelError::MissingField("updates_per_second"))
+        ));
+        assert!(matches!(
+            pack.level("c.json"),
+            Err(LoadLevelError::MissingLevelFile(file)) if file == "c.json"
+        ));
+        assert!(matches!(
+            Pack::from_bundle(&json!({"title": "A", "levels": []})),
+            Err(LoadLevelError::MissingField("files"))
+        ));
+    }
+}
@@ -0,0 +1,37 @@
+#![allow(clippy::cast_precision_loss)]
+
+pub mod borders;
+pub mod campaign;
+pub mod cli;
+pub mod constants;
+pub mod controller;
+pub mod editor;
+pub mod game;
+pub mod graphic_utils;
+pub mod highscore;
+pub mod level;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod logging;
+pub mod menu;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod net;
+pub mod replay;
+pub mod rng;
+pub mod settings;
+pub mod simulation;
+pub mod snake;
+pub mod spectator;
+pub mod storage;
+pub mod target;
+pub mod touch_fields;
+pub mod tournament;
+pub mod versus;
+
+use macroquad::text::Font;
+
+#[derive(Clone)]
+pub struct Context {
+    pub font: Font,
+    /// Seed of every game if it is fixed, e.g. via the command line.
+    pub seed: Option<u64>,
+}
@@ -0,0 +1,63 @@
+//! Tracing subscriber of the native game. Events are printed to stderr and written into a log
+//! file in [`LOG_PATH`], which is rotated daily, so problems reported by players can be diagnosed
+//! afterwards. If the log directory can't be created, events are only printed to stderr.
+
+use crate::constants::{LOG_FILE_PREFIX, LOG_PATH};
+use tracing::level_filters::LevelFilter;
+use tracing::warn;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, Layer};
+
+pub struct LogConfig {
+    pub stderr_level: LevelFilter,
+    pub file_level: LevelFilter,
+    /// Directory of the log files. No log files are written if it is `None`.
+    pub dir: Option<String>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            stderr_level: LevelFilter::WARN,
+            file_level: LevelFilter::INFO,
+            dir: Some(LOG_PATH.to_owned()),
+        }
+    }
+}
+
+/// Installs the subscriber. Must only be called once.
+pub fn init(config: &LogConfig) {
+    let stderr_layer = fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(config.stderr_level);
+    let appender = config.dir.as_ref().map(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_FILE_PREFIX)
+            .build(dir)
+    });
+    // The error can only be reported once the subscriber is installed
+    let (appender, appender_error) = match appender {
+        Some(Ok(appender)) => (Some(appender), None),
+        Some(Err(err)) => (None, Some(err)),
+        None => (None, None),
+    };
+    let file_layer = appender.map(|appender| {
+        fmt::layer()
+            .with_writer(appender)
+            .with_ansi(false)
+            .with_filter(config.file_level)
+    });
+    tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(file_layer)
+        .init();
+    if let Some(err) = appender_error {
+        warn!(
+            ?err,
+            "Failed to create the log file, logging to stderr only"
+        );
+    }
+}
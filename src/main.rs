@@ -1,46 +1,186 @@
-#![allow(clippy::cast_precision_loss)]
-
-mod constants;
-mod game;
-mod graphic_utils;
-mod level;
-mod menu;
-mod snake;
-mod target;
-mod touch_fields;
-
-use constants::{LEVEL_PATH, WINDOW_HEIGHT, WINDOW_WIDTH};
-use game::{start_game, GameOutcome};
-use graphic_utils::render_error_message;
-use level::{base_levels, search_for_custom_levels, Level};
 use macroquad::prelude::*;
 use macroquad::window;
-use menu::GameMode;
-use tracing::error;
+use rusty_head_snake::borders::Borders;
+use rusty_head_snake::campaign::source::{wait_for_pasted_level, LevelSource};
+use rusty_head_snake::campaign::{
+    find_level, level_packs, CampaignLevel, InvalidLevel, LevelPack, LevelStatus, Progress,
+};
+use rusty_head_snake::cli::{Args, Start, USAGE};
+use rusty_head_snake::constants::{
+    HEADLESS_MAX_TICKS, LEVEL_PATH, NET_DEFAULT_ADDRESS, NET_MAX_ADDRESS_LENGTH,
+    NET_MAX_NAME_LENGTH, REPLAY_PATH, VERSUS_TARGET_POINTS, WINDOW_HEIGHT, WINDOW_WIDTH,
+};
+use rusty_head_snake::controller::{autoplay, Controller, IdleController, Strategy, STRATEGIES};
+use rusty_head_snake::editor::edit_level;
+use rusty_head_snake::game::{play_demo, play_replay, play_versus, start_game, GameOutcome};
+#[cfg(not(target_arch = "wasm32"))]
+use rusty_head_snake::graphic_utils::draw_message;
+use rusty_head_snake::graphic_utils::{render_error_message, render_message};
+use rusty_head_snake::highscore::{enter_text, is_name_char, show_high_scores, HighScores};
+use rusty_head_snake::level::Level;
+#[cfg(not(target_arch = "wasm32"))]
+use rusty_head_snake::logging::{self, LogConfig};
+use rusty_head_snake::menu::{self, render_default_background, GameMode};
+#[cfg(not(target_arch = "wasm32"))]
+use rusty_head_snake::net::{bot, online::play_online, watch::ServerFeed};
+use rusty_head_snake::replay::{search_for_replays, Replay};
+use rusty_head_snake::rng::Rng;
+use rusty_head_snake::settings::{edit_settings, Settings};
+use rusty_head_snake::simulation::Simulation;
+use rusty_head_snake::spectator::{spectate, ReplayFeed};
+use rusty_head_snake::Context;
+use std::process::ExitCode;
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::level_filters::LevelFilter;
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::warn;
+use tracing::{debug, error};
+
+const ENDLESS_GAME_BORDERS: [(Borders, &str); 2] = [
+    (Borders::WRAP, "Wrap Around"),
+    (Borders::SOLID, "Solid Walls"),
+];
+
+/// Id of the endless game in the log, next to the ids of the campaign levels.
+const ENDLESS_GAME_ID: &str = "endless";
+const IMPORT_LEVEL_OPTION: &str = "Import Level";
+const LIVE_GAME_OPTION: &str = "Live Game";
 
 #[derive(PartialEq)]
 enum LevelAction {
     UserWantsToStop,
-    AllLevelsComplete,
     LoadNextLevel,
 }
 
-fn window_conf() -> window::Conf {
+fn window_conf(args: &Args) -> window::Conf {
+    let (window_width, window_height) = args.window_size.unwrap_or((WINDOW_WIDTH, WINDOW_HEIGHT));
     window::Conf {
         window_title: "Rusty Head Snake".to_owned(),
-        window_width: WINDOW_WIDTH,
-        window_height: WINDOW_HEIGHT,
+        window_width,
+        window_height,
         ..Default::default()
     }
 }
 
-#[derive(Clone)]
-pub struct Context {
-    font: Font,
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    if args.help {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    init_tracing(&args);
+    debug!(?args, "Parsed command-line arguments");
+
+    if args.headless {
+        return match &args.connect {
+            Some(address) => run_bot(&args, address),
+            None => run_headless(&args),
+        };
+    }
+    macroquad::Window::from_config(window_conf(&args), run(args));
+    ExitCode::SUCCESS
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn init_tracing(args: &Args) {
+    let default = LogConfig::default();
+    logging::init(&LogConfig {
+        stderr_level: if args.verbose {
+            LevelFilter::DEBUG
+        } else {
+            default.stderr_level
+        },
+        file_level: args.log_level.unwrap_or(default.file_level),
+        dir: if args.no_log_file {
+            None
+        } else {
+            args.log_dir.clone().or(default.dir)
+        },
+    });
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
+#[cfg(target_arch = "wasm32")]
+fn init_tracing(_args: &Args) {}
+
+/// Returns the level `start` refers to. Levels are looked up in `levels_path`.
+fn start_level(start: &Start, levels_path: &str) -> Option<Level> {
+    match start {
+        Start::Endless(borders) => Some(Level {
+            borders: *borders,
+            ..Level::default()
+        }),
+        Start::Level(name) => {
+            let (packs, _) = level_packs(levels_path);
+            let (pack_index, level_index) = find_level(&packs, name)?;
+            Some(packs[pack_index].levels[level_index].level.clone())
+        }
+    }
+}
+
+/// Simulates the game given on the command line without a window, until the snake collides, the
+/// level is completed or [`HEADLESS_MAX_TICKS`] are reached. The snake is steered by the autopilot
+/// if one is given, otherwise it keeps its direction.
+fn run_headless(args: &Args) -> ExitCode {
+    let levels_path = args.levels.as_deref().unwrap_or(LEVEL_PATH);
+    let Some(start) = &args.start else {
+        return ExitCode::from(2);
+    };
+    let Some(level) = start_level(start, levels_path) else {
+        if let Start::Level(name) = start {
+            eprintln!("Level {name} was not found in {levels_path}");
+        }
+        return ExitCode::FAILURE;
+    };
+
+    let seed = args.seed.unwrap_or_else(|| Rng::from_time().seed());
+    let mut simulation = Simulation::new(&level, seed);
+    let mut controller = args.autopilot.map_or_else(
+        || Box::new(IdleController) as Box<dyn Controller>,
+        |strategy| strategy.controller(&simulation),
+    );
+    let result = autoplay(&mut simulation, controller.as_mut(), HEADLESS_MAX_TICKS);
+    let outcome = match result.collision {
+        Some(cause) => cause.description(),
+        None if result.won => "level complete",
+        None => "tick limit reached",
+    };
+    println!(
+        "{outcome} after {} ticks with {} points (seed {seed})",
+        result.ticks, result.points
+    );
+    ExitCode::SUCCESS
+}
+
+/// Lets the autopilot play on the server at `address` without a window, until the rounds given on
+/// the command line are over or the server closes the connection.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_bot(args: &Args, address: &str) -> ExitCode {
+    let name = args.name.as_deref().unwrap_or_default();
+    let strategy = args.autopilot.unwrap_or(Strategy::ShortestPath);
+    match bot::play(address, name, strategy, args.rounds) {
+        Ok(summary) => {
+            println!("won {} of {} rounds", summary.wins, summary.rounds);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn run_bot(_args: &Args, _address: &str) -> ExitCode {
+    ExitCode::FAILURE
+}
+
+async fn run(args: Args) {
     let font = match load_ttf_font_from_bytes(include_bytes!("../assets/FiraSans-Black.ttf")) {
         Ok(font) => font,
         Err(err) => {
@@ -49,67 +189,331 @@ async fn main() {
             panic!()
         }
     };
-    let cx = Context { font };
+    let cx = Context {
+        font,
+        seed: args.seed,
+    };
+    // The levels can be loaded from another directory, a bundled pack or a single level file
+    let level_source = args
+        .levels
+        .clone()
+        .map_or_else(LevelSource::default, LevelSource::Path);
+    let mut imported_packs = vec![];
+
+    match (&args.start, args.autopilot) {
+        (Some(Start::Endless(borders)), autopilot) => {
+            let level = Level {
+                borders: *borders,
+                ..Level::default()
+            };
+            match autopilot {
+                Some(strategy) => play_demo(&cx, &level, strategy).await,
+                None => {
+                    start_game(&cx, ENDLESS_GAME_ID, &level).await;
+                }
+            }
+        }
+        (Some(Start::Level(name)), Some(strategy)) => {
+            start_demo_level(&cx, &level_source, name, strategy).await;
+        }
+        (Some(Start::Level(name)), None) => start_into_level(&cx, &level_source, name).await,
+        (None, _) => {}
+    }
+    match (&args.connect, args.spectate) {
+        (Some(address), true) => spectate_server(&cx, address).await,
+        (Some(address), false) => {
+            play_online_game(&cx, address, args.name.as_deref().unwrap_or_default()).await;
+        }
+        (None, true) => spectate_local_game(&cx).await,
+        (None, false) => {}
+    }
 
     loop {
         next_frame().await;
         let game_mode = menu::start(&cx).await;
 
         match game_mode {
-            GameMode::EndlessGame => {
-                start_game(&cx, &Level::default()).await;
+            GameMode::EndlessGame => play_endless_game(&cx).await,
+            GameMode::Versus => play_versus_game(&cx).await,
+            GameMode::Online => play_online_menu(&cx, &args).await,
+            GameMode::Levels => play_levels(&cx, &level_source, &mut imported_packs).await,
+            GameMode::HighScores => {
+                show_high_scores(&cx, &HighScores::load_or_default(), None).await;
             }
-            GameMode::Levels => {
-                if play_custom_levels(&cx).await == LevelAction::UserWantsToStop {
-                    continue;
-                }
-                if play_base_levels(&cx).await == LevelAction::UserWantsToStop {
-                    continue;
-                }
+            GameMode::Replays => play_replays(&cx).await,
+            GameMode::Demo => play_demo_mode(&cx).await,
+            GameMode::Editor => edit_level(&cx).await,
+            GameMode::Settings => {
+                edit_settings(
+                    &cx,
+                    &mut Settings::load_or_default(),
+                    &render_default_background,
+                )
+                .await;
             }
             GameMode::Exit => break,
         }
     }
 }
 
-async fn play_custom_levels(cx: &Context) -> LevelAction {
-    let custom_level_names = match search_for_custom_levels(LEVEL_PATH) {
-        Ok(paths) => paths,
+/// Plays the level `name` and the following levels of its pack, regardless of whether they are
+/// unlocked.
+async fn start_into_level(cx: &Context, source: &LevelSource, name: &str) {
+    let (packs, invalid_levels) = source.load().await;
+    report_invalid_levels(invalid_levels).await;
+    let Some((pack_index, level_index)) = find_level(&packs, name) else {
+        render_error_message(&format!("Level {name} was not found")).await;
+        return;
+    };
+    let mut progress = Progress::load_or_default();
+    play_from(cx, &packs[pack_index].levels[level_index..], &mut progress).await;
+}
+
+/// Lets the autopilot play the level `name` until the player presses a key.
+async fn start_demo_level(cx: &Context, source: &LevelSource, name: &str, strategy: Strategy) {
+    let (packs, invalid_levels) = source.load().await;
+    report_invalid_levels(invalid_levels).await;
+    let Some((pack_index, level_index)) = find_level(&packs, name) else {
+        render_error_message(&format!("Level {name} was not found")).await;
+        return;
+    };
+    play_demo(cx, &packs[pack_index].levels[level_index].level, strategy).await;
+}
+
+/// Lets the player choose a strategy which then plays the Endless Game on its own.
+async fn play_demo_mode(cx: &Context) {
+    let options = STRATEGIES
+        .iter()
+        .map(|strategy| strategy.label().to_owned())
+        .collect();
+    if let Some(index) = menu::select(cx, "Autopilot Demo", options).await {
+        play_demo(cx, &Level::default(), STRATEGIES[index]).await;
+    }
+}
+
+/// Lets the player choose between wrapping and solid walls before starting the Endless Game.
+async fn play_endless_game(cx: &Context) {
+    let options = ENDLESS_GAME_BORDERS
+        .iter()
+        .map(|(_, label)| (*label).to_owned())
+        .collect();
+    if let Some(index) = menu::select(cx, "Endless Game", options).await {
+        let level = Level {
+            borders: ENDLESS_GAME_BORDERS[index].0,
+            ..Level::default()
+        };
+        start_game(cx, ENDLESS_GAME_ID, &level).await;
+    }
+}
+
+/// Lets the players choose between wrapping and solid walls before starting a versus match.
+async fn play_versus_game(cx: &Context) {
+    let options = ENDLESS_GAME_BORDERS
+        .iter()
+        .map(|(_, label)| (*label).to_owned())
+        .collect();
+    if let Some(index) = menu::select(cx, "Versus", options).await {
+        let level = Level {
+            borders: ENDLESS_GAME_BORDERS[index].0,
+            target_points: Some(VERSUS_TARGET_POINTS),
+            ..Level::default()
+        };
+        play_versus(cx, &level).await;
+    }
+}
+
+/// Whether the character may be part of a server address, e.g. `example.com:7878` or `[::1]:7878`.
+fn is_address_char(character: char) -> bool {
+    character.is_ascii_alphanumeric() || matches!(character, '.' | ':' | '-' | '[' | ']')
+}
+
+/// Asks for the address of the server, prefilled with the one given on the command line, and
+/// lets the player choose between joining and watching it. Joining asks for the name as well.
+async fn play_online_menu(cx: &Context, args: &Args) {
+    let default_address = args.connect.as_deref().unwrap_or(NET_DEFAULT_ADDRESS);
+    let address = enter_text(
+        cx,
+        "Online",
+        "Type the address of the server and press Enter",
+        default_address,
+        NET_MAX_ADDRESS_LENGTH,
+        is_address_char,
+    )
+    .await;
+    let address = match address.trim() {
+        "" => default_address,
+        address => address,
+    };
+
+    let options = vec![format!("Join {address}"), format!("Spectate {address}")];
+    match menu::select(cx, "Online", options).await {
+        Some(0) => {
+            let default_name = args
+                .name
+                .clone()
+                .unwrap_or_else(|| HighScores::load_or_default().last_name);
+            let name = enter_text(
+                cx,
+                "Online",
+                "Type your name and press Enter",
+                &default_name,
+                NET_MAX_NAME_LENGTH,
+                is_name_char,
+            )
+            .await;
+            play_online_game(cx, address, &name).await;
+        }
+        Some(_) => spectate_server(cx, address).await,
+        None => {}
+    }
+}
+
+/// Joins the server at `address` as `name`.
+#[cfg(not(target_arch = "wasm32"))]
+async fn play_online_game(cx: &Context, address: &str, name: &str) {
+    play_online(cx, address, name).await;
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn play_online_game(_cx: &Context, _address: &str, _name: &str) {
+    render_error_message("Online games are not available in the browser").await;
+}
+
+/// Watches the rounds on the server at `address` without taking part.
+#[cfg(not(target_arch = "wasm32"))]
+async fn spectate_server(cx: &Context, address: &str) {
+    draw_message(&format!("Connecting to {address}"), WHITE);
+    next_frame().await;
+    match ServerFeed::connect(address) {
+        Ok(mut feed) => spectate(cx, &mut feed).await,
+        Err(err) => {
+            warn!(address, ?err, "Failed to connect");
+            render_error_message(&format!("Could not connect to {address}: {err}")).await;
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn spectate_server(_cx: &Context, _address: &str) {
+    render_error_message("Online games are not available in the browser").await;
+}
+
+/// Watches the game which is played in another window of this computer.
+async fn spectate_local_game(cx: &Context) {
+    spectate(cx, &mut ReplayFeed::new(REPLAY_PATH)).await;
+}
+
+/// Lets the player choose a saved replay, or watch the game running in another window with the
+/// last option.
+async fn play_replays(cx: &Context) {
+    let replay_names = match search_for_replays(REPLAY_PATH) {
+        Ok(names) => names,
         Err(err) => {
-            error!(?err, "Failed to search for custom levels");
+            error!(?err, "Failed to search for replays");
             vec![]
         }
     };
-    for level_name in &custom_level_names {
-        match Level::load_level(LEVEL_PATH, level_name) {
-            Ok(level) => {
-                if loop_level(cx, &level).await == LevelAction::UserWantsToStop {
-                    return LevelAction::UserWantsToStop;
-                }
-            }
-            Err(err) => {
-                error!(
-                    ?err,
-                    "Custom level {} is not valid and therefore skipped", level_name
-                );
-            }
+    let mut options: Vec<String> = replay_names
+        .iter()
+        .map(|name| name.trim_end_matches(".json").to_owned())
+        .collect();
+    options.push(LIVE_GAME_OPTION.to_owned());
+
+    while let Some(index) = menu::select(cx, "Replays", options.clone()).await {
+        let Some(name) = replay_names.get(index) else {
+            spectate_local_game(cx).await;
+            continue;
+        };
+        match Replay::load(REPLAY_PATH, name) {
+            Ok(replay) => play_replay(cx, &replay).await,
+            Err(err) => error!(?err, "Replay {name} is not valid"),
+        }
+    }
+}
+
+/// Lets the player choose a level pack or import one. Imported packs are kept until the game is
+/// closed. Levels and packs which are not valid are reported beforehand.
+async fn play_levels(cx: &Context, source: &LevelSource, imported_packs: &mut Vec<LevelPack>) {
+    let (mut packs, invalid_levels) = source.load().await;
+    report_invalid_levels(invalid_levels).await;
+    // The base levels stay last, after the imported packs
+    let base_pack = packs.pop();
+    let mut progress = Progress::load_or_default();
+
+    loop {
+        let all_packs: Vec<&LevelPack> = packs
+            .iter()
+            .chain(imported_packs.iter())
+            .chain(base_pack.iter())
+            .collect();
+        let mut options: Vec<String> = all_packs.iter().map(|pack| pack.label()).collect();
+        options.push(IMPORT_LEVEL_OPTION.to_owned());
+        let Some(index) = menu::select(cx, "Level Packs", options).await else {
+            return;
+        };
+
+        if let Some(pack) = all_packs.get(index) {
+            play_pack(cx, pack, &mut progress).await;
+        } else if let Some(pasted_level) = wait_for_pasted_level().await {
+            let (mut pasted_packs, invalid_levels) = pasted_level.load().await;
+            report_invalid_levels(invalid_levels).await;
+            // The base levels are listed already
+            pasted_packs.pop();
+            imported_packs.extend(pasted_packs);
+        }
+    }
+}
+
+async fn report_invalid_levels(invalid_levels: Vec<InvalidLevel>) {
+    for invalid_level in invalid_levels {
+        render_error_message(&format!(
+            "Level {} is skipped: {}",
+            invalid_level.name, invalid_level.error
+        ))
+        .await;
+    }
+}
+
+/// Shows the level select screen of a pack. Starting an unlocked level continues with the
+/// following levels once it is completed.
+async fn play_pack(cx: &Context, pack: &LevelPack, progress: &mut Progress) {
+    let levels = &pack.levels;
+    loop {
+        let options = (0..levels.len())
+            .map(|i| progress.label(levels, i))
+            .collect();
+        let Some(index) = menu::select(cx, &pack.title, options).await else {
+            return;
         };
+        if progress.status(levels, index) == LevelStatus::Locked {
+            continue;
+        }
+
+        play_from(cx, &levels[index..], progress).await;
     }
-    LevelAction::AllLevelsComplete
 }
 
-async fn play_base_levels(cx: &Context) -> LevelAction {
-    for level in base_levels() {
-        if loop_level(cx, &level).await == LevelAction::UserWantsToStop {
-            return LevelAction::UserWantsToStop;
+/// Plays the given levels one after another until the player stops.
+async fn play_from(cx: &Context, levels: &[CampaignLevel], progress: &mut Progress) {
+    for level in levels {
+        if loop_level(cx, level, progress).await == LevelAction::UserWantsToStop {
+            break;
         }
     }
-    LevelAction::AllLevelsComplete
 }
 
-async fn loop_level(cx: &Context, level: &Level) -> LevelAction {
+async fn loop_level(cx: &Context, level: &CampaignLevel, progress: &mut Progress) -> LevelAction {
+    if let Some(hint) = &level.hint {
+        render_message(&format!("{}: {hint}", level.title), WHITE).await;
+    }
     loop {
-        match start_game(cx, level).await {
+        let (game_outcome, points) = start_game(cx, &level.id, &level.level).await;
+        progress.record(&level.id, points, game_outcome == GameOutcome::Win);
+        if let Err(err) = progress.save() {
+            error!(?err, "Failed to save level progress");
+        }
+
+        match game_outcome {
             GameOutcome::Win => return LevelAction::LoadNextLevel,
             GameOutcome::Exit => return LevelAction::UserWantsToStop,
             GameOutcome::Lose => {}
@@ -12,18 +12,53 @@ use macroquad::math::Vec2;
 use macroquad::prelude::{clear_background, next_frame};
 use macroquad::window::{screen_height, screen_width};
 
+const MAX_VISIBLE_OPTIONS: i32 = 10;
+
+/// Vertical list of options, selectable by keyboard, mouse and touch. Lists which are longer than
+/// [`MAX_VISIBLE_OPTIONS`] scroll along with the cursor.
 pub struct Menu {
+    title: String,
+    options: Vec<String>,
     cursor: i32,
+    first_visible: i32,
+    /// Whether the input of a frame was handled already. The key press or click which opened the
+    /// menu is still reported in the first frame and must not select an option of the new menu.
+    opened: bool,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Selection {
+    Option(usize),
+    Back,
 }
 
-const NUMBER_OF_OPTIONS: i32 = 3;
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum GameMode {
     EndlessGame,
+    Versus,
+    Online,
     Levels,
+    HighScores,
+    Replays,
+    Demo,
+    Editor,
+    Settings,
     Exit,
 }
 
+const GAME_MODES: [(GameMode, &str); 10] = [
+    (GameMode::Levels, "Levels"),
+    (GameMode::EndlessGame, "Endless Game"),
+    (GameMode::Versus, "Versus"),
+    (GameMode::Online, "Online"),
+    (GameMode::HighScores, "High Scores"),
+    (GameMode::Replays, "Replays"),
+    (GameMode::Demo, "Autopilot Demo"),
+    (GameMode::Editor, "Level Editor"),
+    (GameMode::Settings, "Settings"),
+    (GameMode::Exit, "Exit"),
+];
+
 #[derive(PartialEq, Debug)]
 enum TouchMouseEvent {
     Floating,
@@ -32,7 +67,7 @@ enum TouchMouseEvent {
 }
 
 #[derive(Debug)]
-struct TouchMouseData {
+pub(crate) struct TouchMouseData {
     event: TouchMouseEvent,
     position: Vec2,
 }
@@ -62,142 +97,212 @@ impl TouchMouseData {
 }
 
 impl Menu {
-    fn height_segment() -> f32 {
-        screen_height() / (NUMBER_OF_OPTIONS + 1) as f32
+    pub fn new(title: &str, options: Vec<String>) -> Self {
+        Menu {
+            title: title.to_owned(),
+            options,
+            cursor: 0,
+            first_visible: 0,
+            opened: false,
+        }
     }
 
-    fn render_menu(&mut self, cx: &Context, height_segment: f32) {
-        clear_background(BACKGROUND_COLOR);
-        self.render_boxes(height_segment);
-        Menu::render_text(height_segment, cx);
+    fn number_of_options(&self) -> i32 {
+        i32::try_from(self.options.len()).unwrap_or(i32::MAX)
     }
 
-    fn render_boxes(&mut self, height_segment: f32) {
-        for i in 0..NUMBER_OF_OPTIONS {
-            render_x_centered_rect(
-                height_segment * (i as f32 + NUMBER_OF_OPTIONS as f32 / 2.0),
-                height_segment / 2.0,
-                OBSTACLE_COLOR,
-            );
-        }
+    fn number_of_visible_options(&self) -> i32 {
+        self.number_of_options().min(MAX_VISIBLE_OPTIONS)
+    }
 
-        if self.cursor >= 0 && self.cursor < NUMBER_OF_OPTIONS {
+    fn height_segment(&self) -> f32 {
+        screen_height() / (self.number_of_visible_options() + 1) as f32
+    }
+
+    fn option_center(slot: i32, height_segment: f32) -> f32 {
+        height_segment * (slot as f32 + 1.5)
+    }
+
+    fn render_menu(&self, cx: &Context, height_segment: f32) {
+        self.render_boxes(height_segment);
+        self.render_text(height_segment, cx);
+    }
+
+    fn render_boxes(&self, height_segment: f32) {
+        for slot in 0..self.number_of_visible_options() {
+            let color = if self.first_visible + slot == self.cursor {
+                SNAKE_HEAD_COLOR
+            } else {
+                OBSTACLE_COLOR
+            };
             render_x_centered_rect(
-                height_segment * (self.cursor as f32 + NUMBER_OF_OPTIONS as f32 / 2.0),
+                Menu::option_center(slot, height_segment),
                 height_segment / 2.0,
-                SNAKE_HEAD_COLOR,
+                color,
             );
         }
     }
 
-    fn render_text(height_segment: f32, cx: &Context) {
-        let text: [&str; 4] = ["Rusty Head Snake", "Levels", "Endless Game", "Exit"];
-
-        for i in 0..=NUMBER_OF_OPTIONS {
-            let (color, font_size) = match i {
-                0 => (SNAKE_HEAD_COLOR, TITLE_TEXT_SIZE),
-                _ => (BACKGROUND_COLOR, OPTION_TEXT_SIZE),
-            };
+    fn render_text(&self, height_segment: f32, cx: &Context) {
+        render_text(
+            &self.title,
+            Point2D::new(screen_width() / 2.0, height_segment / 2.0),
+            Some(&cx.font),
+            TITLE_TEXT_SIZE,
+            SNAKE_HEAD_COLOR,
+        );
 
+        for slot in 0..self.number_of_visible_options() {
+            #[allow(clippy::cast_sign_loss)]
             render_text(
-                #[allow(clippy::cast_sign_loss)]
-                text[i as usize],
+                &self.options[(self.first_visible + slot) as usize],
                 Point2D::new(
                     screen_width() / 2.0,
-                    height_segment * i as f32 + height_segment / 2.0,
+                    Menu::option_center(slot, height_segment),
                 ),
                 Some(&cx.font),
-                font_size,
-                color,
+                OPTION_TEXT_SIZE,
+                BACKGROUND_COLOR,
             );
         }
     }
 
-    fn game_mode_from_cursor_position(&self) -> Option<GameMode> {
-        match self.cursor {
-            0 => Some(GameMode::Levels),
-            1 => Some(GameMode::EndlessGame),
-            2 => Some(GameMode::Exit),
-            _ => None,
+    fn selection_from_cursor_position(&self) -> Option<Selection> {
+        usize::try_from(self.cursor)
+            .ok()
+            .filter(|cursor| *cursor < self.options.len())
+            .map(Selection::Option)
+    }
+
+    fn move_cursor(&mut self, offset: i32) {
+        let number_of_options = self.number_of_options();
+        if number_of_options == 0 {
+            return;
+        }
+        self.cursor = (self.cursor + offset).rem_euclid(number_of_options);
+
+        let number_of_visible_options = self.number_of_visible_options();
+        if self.cursor < self.first_visible {
+            self.first_visible = self.cursor;
+        } else if self.cursor >= self.first_visible + number_of_visible_options {
+            self.first_visible = self.cursor - number_of_visible_options + 1;
         }
     }
 
-    fn handle_key_press(&mut self, key: Option<KeyCode>) -> Option<GameMode> {
+    fn handle_key_press(&mut self, key: Option<KeyCode>) -> Option<Selection> {
         if let Some(key) = key {
             match key {
-                KeyCode::Up | KeyCode::W => {
-                    self.cursor = (self.cursor - 1) % NUMBER_OF_OPTIONS;
-                    if self.cursor < 0 {
-                        self.cursor = NUMBER_OF_OPTIONS - 1;
-                    }
-                }
-                KeyCode::Down | KeyCode::S => {
-                    self.cursor = (self.cursor + 1) % NUMBER_OF_OPTIONS;
-                }
-                KeyCode::Space | KeyCode::Enter => return self.game_mode_from_cursor_position(),
+                KeyCode::Up | KeyCode::W => self.move_cursor(-1),
+                KeyCode::Down | KeyCode::S => self.move_cursor(1),
+                KeyCode::Space | KeyCode::Enter => return self.selection_from_cursor_position(),
+                KeyCode::Escape | KeyCode::Backspace => return Some(Selection::Back),
                 _ => {}
             }
         }
         None
     }
 
+    /// Handles the key press, touches and mouse events of a frame. The input of the first frame is
+    /// ignored.
+    pub(crate) fn handle_input(
+        &mut self,
+        key: Option<KeyCode>,
+        touch_mouse: &[TouchMouseData],
+        height_segment: f32,
+    ) -> Option<Selection> {
+        if !self.opened {
+            self.opened = true;
+            return None;
+        }
+        if let Some(selection) = self.handle_key_press(key) {
+            return Some(selection);
+        }
+        touch_mouse
+            .iter()
+            .find_map(|touch_mouse| self.handle_touch_mouse(touch_mouse, height_segment))
+    }
+
     fn handle_touch_mouse(
         &mut self,
         touch_mouse: &TouchMouseData,
         height_segment: f32,
-    ) -> Option<GameMode> {
+    ) -> Option<Selection> {
+        let is_in_slot = |slot: i32| {
+            let center_position = Menu::option_center(slot, height_segment);
+            touch_mouse.position.y > center_position - height_segment / 4.0
+                && touch_mouse.position.y < center_position + height_segment / 4.0
+        };
+
         if touch_mouse.event == TouchMouseEvent::Floating {
-            for i in 0..NUMBER_OF_OPTIONS {
-                let center_position = height_segment * (i as f32 + NUMBER_OF_OPTIONS as f32 / 2.0);
-                if touch_mouse.position.y > center_position - height_segment / 4.0
-                    && touch_mouse.position.y < center_position + height_segment / 4.0
-                {
-                    self.cursor = i;
+            for slot in 0..self.number_of_visible_options() {
+                if is_in_slot(slot) {
+                    self.cursor = self.first_visible + slot;
                 }
             }
         }
 
-        if touch_mouse.event == TouchMouseEvent::Enter {
-            let center_position =
-                height_segment * (self.cursor as f32 + NUMBER_OF_OPTIONS as f32 / 2.0);
-            if touch_mouse.position.y > center_position - height_segment / 4.0
-                && touch_mouse.position.y < center_position + height_segment / 4.0
-            {
-                return self.game_mode_from_cursor_position();
-            }
+        if touch_mouse.event == TouchMouseEvent::Enter
+            && is_in_slot(self.cursor - self.first_visible)
+        {
+            return self.selection_from_cursor_position();
         }
         None
     }
 }
 
-pub async fn start(cx: &Context) -> GameMode {
-    let mut menu = Menu { cursor: 0 };
-    menu_loop(&mut menu, cx).await
+pub fn render_default_background() {
+    clear_background(BACKGROUND_COLOR);
 }
 
-pub async fn menu_loop(menu: &mut Menu, cx: &Context) -> GameMode {
+pub async fn start(cx: &Context) -> GameMode {
+    let options = GAME_MODES.iter().map(|(_, text)| (*text).to_owned());
+    let mut menu = Menu::new("Rusty Head Snake", options.collect());
     loop {
-        let height_segment = Menu::height_segment();
-
-        if let Some(game_mode) = menu.handle_key_press(get_last_key_pressed()) {
-            return game_mode;
+        if let Selection::Option(index) = menu_loop(&mut menu, cx, &render_default_background).await
+        {
+            return GAME_MODES[index].0;
         }
+    }
+}
 
-        for touch in touches() {
-            let touch = TouchMouseData::from_touch_event(&touch);
-            if let Some(game_mode) = menu.handle_touch_mouse(&touch, height_segment) {
-                return game_mode;
-            }
-        }
+/// Lets the player pick one of `options`. Returns `None` if the player navigates back instead.
+pub async fn select(cx: &Context, title: &str, options: Vec<String>) -> Option<usize> {
+    select_with_background(cx, title, options, &render_default_background).await
+}
 
-        let mouse = TouchMouseData::from_mouse_event(
+/// Like [`select`], but renders the menu on top of whatever `background` draws.
+pub async fn select_with_background(
+    cx: &Context,
+    title: &str,
+    options: Vec<String>,
+    background: &dyn Fn(),
+) -> Option<usize> {
+    let mut menu = Menu::new(title, options);
+    match menu_loop(&mut menu, cx, background).await {
+        Selection::Option(index) => Some(index),
+        Selection::Back => None,
+    }
+}
+
+pub async fn menu_loop(menu: &mut Menu, cx: &Context, background: &dyn Fn()) -> Selection {
+    loop {
+        let height_segment = menu.height_segment();
+
+        let mut touch_mouse: Vec<TouchMouseData> = touches()
+            .iter()
+            .map(TouchMouseData::from_touch_event)
+            .collect();
+        touch_mouse.push(TouchMouseData::from_mouse_event(
             is_mouse_button_released(MouseButton::Left),
             mouse_position(),
-        );
-        if let Some(game_mode) = menu.handle_touch_mouse(&mouse, height_segment) {
-            return game_mode;
+        ));
+        if let Some(selection) =
+            menu.handle_input(get_last_key_pressed(), &touch_mouse, height_segment)
+        {
+            return selection;
         }
 
+        background();
         menu.render_menu(cx, height_segment);
 
         next_frame().await;
@@ -209,15 +314,24 @@ mod tests {
     use super::*;
 
     const INITIAL_CURSOR_POSITION: i32 = 0;
+    const NUMBER_OF_OPTIONS: i32 = GAME_MODES.len() as i32;
 
     fn init() -> Menu {
-        Menu {
-            cursor: INITIAL_CURSOR_POSITION,
-        }
+        Menu::new(
+            "Title",
+            GAME_MODES
+                .iter()
+                .map(|(_, text)| (*text).to_owned())
+                .collect(),
+        )
     }
 
     fn key_event(menu: &mut Menu, keys: KeyCode, expected_result: Option<GameMode>) {
-        assert_eq!(expected_result, menu.handle_key_press(Some(keys)));
+        let game_mode = match menu.handle_key_press(Some(keys)) {
+            Some(Selection::Option(index)) => Some(GAME_MODES[index].0),
+            _ => None,
+        };
+        assert_eq!(expected_result, game_mode);
     }
 
     fn press_space_or_enter(menu: &mut Menu, game_mode: GameMode) {
@@ -275,13 +389,72 @@ mod tests {
         assert_eq!(expected_cursor_position, cursor_position);
     }
 
+    #[test]
+    fn key_press_back() {
+        let mut menu = init();
+        assert_eq!(
+            Some(Selection::Back),
+            menu.handle_key_press(Some(KeyCode::Escape))
+        );
+    }
+
+    #[test]
+    fn ignore_input_of_first_frame() {
+        let mut menu = init();
+        let click = || TouchMouseData::from_mouse_event(true, (0.0, 1.5));
+        assert_eq!(
+            None,
+            menu.handle_input(Some(KeyCode::Enter), &[click()], 1.0)
+        );
+        assert_eq!(None, menu.handle_input(None, &[], 1.0));
+        assert_eq!(
+            Some(Selection::Option(0)),
+            menu.handle_input(None, &[click()], 1.0)
+        );
+        assert_eq!(
+            Some(Selection::Back),
+            menu.handle_input(Some(KeyCode::Escape), &[], 1.0)
+        );
+    }
+
+    #[test]
+    fn empty_menu() {
+        let mut menu = Menu::new("Title", vec![]);
+        assert_eq!(None, menu.handle_key_press(Some(KeyCode::Down)));
+        assert_eq!(None, menu.handle_key_press(Some(KeyCode::Enter)));
+    }
+
+    #[test]
+    fn scroll_long_menu() {
+        let options = (0..MAX_VISIBLE_OPTIONS * 2)
+            .map(|i| i.to_string())
+            .collect();
+        let mut menu = Menu::new("Title", options);
+
+        for _ in 0..MAX_VISIBLE_OPTIONS {
+            menu.handle_key_press(Some(KeyCode::Down));
+        }
+        assert_eq!(MAX_VISIBLE_OPTIONS, menu.cursor);
+        assert_eq!(1, menu.first_visible);
+
+        menu.handle_key_press(Some(KeyCode::Up));
+        menu.handle_key_press(Some(KeyCode::Up));
+        assert_eq!(MAX_VISIBLE_OPTIONS - 2, menu.cursor);
+        assert_eq!(1, menu.first_visible);
+
+        let height_segment = 1.0;
+        let touch_mouse_data = TouchMouseData::from_mouse_event(false, (0.0, 1.5));
+        menu.handle_touch_mouse(&touch_mouse_data, height_segment);
+        assert_eq!(1, menu.cursor);
+    }
+
     #[test]
     fn test_handle_touch() {
         let mut menu = init();
         let height_segment = 1.0;
 
         for i in 0..NUMBER_OF_OPTIONS {
-            let position = i as f32 + NUMBER_OF_OPTIONS as f32 / 2.0;
+            let position = i as f32 + 1.5;
             let mut touch = Touch {
                 id: 0,
                 phase: TouchPhase::Started,
@@ -301,7 +474,7 @@ mod tests {
             touch.phase = TouchPhase::Ended;
             let touch_mouse_data = TouchMouseData::from_touch_event(&touch);
             let selected_game_mode = menu.handle_touch_mouse(&touch_mouse_data, height_segment);
-            assert_eq!(selected_game_mode, menu.game_mode_from_cursor_position());
+            assert_eq!(selected_game_mode, menu.selection_from_cursor_position());
         }
     }
 
@@ -311,7 +484,7 @@ mod tests {
         let height_segment = 1.0;
 
         for i in 0..NUMBER_OF_OPTIONS {
-            let position = i as f32 + NUMBER_OF_OPTIONS as f32 / 2.0;
+            let position = i as f32 + 1.5;
 
             let touch_mouse_data = TouchMouseData::from_mouse_event(false, (0.0, position));
             assert_eq!(
@@ -322,7 +495,7 @@ mod tests {
 
             let touch_mouse_data = TouchMouseData::from_mouse_event(true, (0.0, position));
             let selected_game_mode = menu.handle_touch_mouse(&touch_mouse_data, height_segment);
-            assert_eq!(selected_game_mode, menu.game_mode_from_cursor_position());
+            assert_eq!(selected_game_mode, menu.selection_from_cursor_position());
         }
     }
 }
@@ -0,0 +1,10 @@
+//! Versus games over TCP. The [`server`] runs the rounds, [`client`] connects to it, [`bot`]
+//! plays without a window, [`online`] is the game's screen for playing on a server and [`watch`]
+//! follows a server as a spectator.
+
+pub mod bot;
+pub mod client;
+pub mod online;
+pub mod protocol;
+pub mod server;
+pub mod watch;
@@ -0,0 +1,125 @@
+//! Headless client which plays on a server with an autopilot strategy, to fill up games and to
+//! test the server without opening windows.
+
+use super::client::{Client, OnlineState};
+use super::protocol::{ClientMessage, ServerMessage};
+use crate::constants::NET_CLIENT_TIMEOUT;
+use crate::controller::{Controller, Strategy};
+use crate::simulation::Simulation;
+use crate::versus::Versus;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::thread;
+use std::time::Duration;
+use tracing::info;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Rounds a bot took part in.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BotSummary {
+    pub rounds: u32,
+    pub wins: u32,
+}
+
+/// Joins the server at `address` as `name` and plays with `strategy` until `rounds` rounds are
+/// over, or until the connection is closed if `rounds` is `None`.
+pub fn play(
+    address: impl ToSocketAddrs,
+    name: &str,
+    strategy: Strategy,
+    rounds: Option<u32>,
+) -> Result<BotSummary, BotError> {
+    let mut client = Client::connect(address, name)?;
+    let mut state = OnlineState::default();
+    let mut controller: Option<Box<dyn Controller>> = None;
+    let mut summary = BotSummary { rounds: 0, wins: 0 };
+
+    while rounds.is_none_or(|rounds| summary.rounds < rounds) {
+        for message in client.poll() {
+            let snapshot = matches!(message, ServerMessage::Snapshot { .. });
+            let round_over = match &message {
+                ServerMessage::RoundStart { .. } => {
+                    controller = None;
+                    None
+                }
+                ServerMessage::RoundOver { winner } => Some(*winner),
+                _ => None,
+            };
+            state.apply(message);
+
+            if let Some(error) = state.error.take() {
+                return Err(BotError::Refused(error));
+            }
+            if let Some(winner) = round_over {
+                summary.rounds += 1;
+                if winner.is_some() && winner == state.id {
+                    summary.wins += 1;
+                }
+            }
+            let Some((round, player)) = state.round.as_ref().zip(state.own_snake()) else {
+                continue;
+            };
+            if !snapshot || round.winner.is_some() || !round.versus.players[player].alive {
+                continue;
+            }
+            let simulation = view(&round.versus, player);
+            let controller = controller.get_or_insert_with(|| strategy.controller(&simulation));
+            let direction = controller.direction(&simulation);
+            if direction != simulation.snake.current_direction {
+                client.send(&ClientMessage::Turn {
+                    direction,
+                    tick: round.versus.tick,
+                })?;
+            }
+        }
+
+        if !client.is_connected() {
+            break;
+        }
+        if client.last_received().elapsed().as_secs_f64() > NET_CLIENT_TIMEOUT {
+            return Err(BotError::Disconnected);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    info!(name, ?summary, "Bot finished");
+    client.leave();
+    Ok(summary)
+}
+
+/// The round from the point of view of the snake of `player`: the other snakes are obstacles.
+fn view(versus: &Versus, player: usize) -> Simulation {
+    let mut obstacles = versus.obstacles.clone();
+    obstacles.extend(
+        versus
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(i, other)| *i != player && other.alive)
+            .flat_map(|(_, other)| other.snake.position.iter().copied()),
+    );
+    Simulation {
+        snake: versus.players[player].snake.clone(),
+        target: versus.target.clone(),
+        obstacles,
+        width: versus.width,
+        height: versus.height,
+        borders: versus.borders,
+        target_points: versus.target_points,
+        points: versus.players[player].points,
+        tick: versus.tick,
+        rng: versus.rng.clone(),
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum BotError {
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+
+    #[error("The server refused to let the bot join: {0}")]
+    Refused(String),
+
+    #[error("The server stopped answering")]
+    Disconnected,
+}
@@ -0,0 +1,378 @@
+//! Connection to a [`super::server::Server`] and the state of the game as the server reported it.
+
+use super::protocol::{ClientMessage, LobbyPlayer, ServerMessage};
+use crate::constants::{NET_CONNECT_TIMEOUT, NET_PING_INTERVAL};
+use crate::level::Level;
+use crate::versus::Versus;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+pub struct Client {
+    stream: TcpStream,
+    inbox: Receiver<ServerMessage>,
+    connected: bool,
+    started: Instant,
+    last_ping: Option<Instant>,
+    /// When the last message arrived, pongs included.
+    last_received: Instant,
+    /// Round trip time measured with the last answered ping.
+    pub rtt: Option<Duration>,
+}
+
+impl Client {
+    /// Connects to the server at `address` and joins the game as `name`.
+    pub fn connect(address: impl ToSocketAddrs, name: &str) -> io::Result<Self> {
+        Client::open(
+            address,
+            &ClientMessage::Join {
+                name: name.to_owned(),
+            },
+        )
+    }
+
+    /// Connects to the server at `address` as a spectator.
+    pub fn watch(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Client::open(address, &ClientMessage::Watch)
+    }
+
+    /// Connects to the server at `address` and sends `first_message`.
+    fn open(address: impl ToSocketAddrs, first_message: &ClientMessage) -> io::Result<Self> {
+        let stream = connect(address)?;
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+        let (sender, inbox) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                match ServerMessage::parse(&line) {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => warn!(?err, "Received invalid message"),
+                }
+            }
+        });
+
+        let mut client = Client {
+            stream,
+            inbox,
+            connected: true,
+            started: Instant::now(),
+            last_ping: None,
+            last_received: Instant::now(),
+            rtt: None,
+        };
+        client.send(first_message)?;
+        Ok(client)
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        let result = writeln!(self.stream, "{}", message.to_json());
+        if result.is_err() {
+            self.connected = false;
+        }
+        result
+    }
+
+    /// Returns the messages received since the last call. Pings the server regularly, which keeps
+    /// the connection alive and measures the round trip time.
+    pub fn poll(&mut self) -> Vec<ServerMessage> {
+        let now = Instant::now();
+        if self.connected
+            && self.last_ping.is_none_or(|last_ping| {
+                now.duration_since(last_ping).as_secs_f64() >= NET_PING_INTERVAL
+            })
+        {
+            self.last_ping = Some(now);
+            let time = now.duration_since(self.started).as_secs_f64();
+            // A failed ping marks the client as disconnected
+            let _ = self.send(&ClientMessage::Ping { time });
+        }
+
+        let mut messages = vec![];
+        loop {
+            let message = self.inbox.try_recv();
+            if message.is_ok() {
+                self.last_received = now;
+            }
+            match message {
+                Ok(ServerMessage::Pong { time }) => {
+                    let sent = Duration::from_secs_f64(time.max(0.0));
+                    self.rtt = Some(now.duration_since(self.started).saturating_sub(sent));
+                }
+                Ok(message) => messages.push(message),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.connected = false;
+                    break;
+                }
+            }
+        }
+        messages
+    }
+
+    /// When [`Client::poll`] last received a message. Pongs count as well, so a server which
+    /// answers the pings is not taken for gone while nothing else happens, e.g. in the lobby.
+    pub fn last_received(&self) -> Instant {
+        self.last_received
+    }
+
+    /// Whether the connection is still open. Messages received before the connection was closed
+    /// are still returned by [`Client::poll`].
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Leaves the game and closes the connection.
+    pub fn leave(mut self) {
+        // The server notices a closed connection anyway
+        let _ = self.send(&ClientMessage::Leave);
+    }
+}
+
+/// Connects to the first of the resolved addresses which accepts the connection within
+/// [`NET_CONNECT_TIMEOUT`].
+fn connect(address: impl ToSocketAddrs) -> io::Result<TcpStream> {
+    let timeout = Duration::from_secs_f64(NET_CONNECT_TIMEOUT);
+    let mut last_error = None;
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_error = Some(err),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The address could not be resolved",
+        )
+    }))
+}
+
+/// A round as seen by a client.
+#[derive(Debug)]
+pub struct OnlineRound {
+    pub number: u32,
+    pub level: Level,
+    /// Players in the order of the snakes.
+    pub players: Vec<LobbyPlayer>,
+    /// Mirrors the snapshots of the server. It is never stepped by the client.
+    pub versus: Versus,
+    /// Set once the round is over, to the winner or `None` on a draw.
+    pub winner: Option<Option<u32>>,
+}
+
+impl OnlineRound {
+    /// Index of the snake of the player with the id.
+    pub fn snake_of(&self, id: u32) -> Option<usize> {
+        self.players.iter().position(|player| player.id == id)
+    }
+
+    pub fn player_name(&self, id: u32) -> Option<&str> {
+        self.players
+            .iter()
+            .find(|player| player.id == id)
+            .map(|player| player.name.as_str())
+    }
+}
+
+/// Everything a client knows about the game, updated with the messages of the server.
+#[derive(Debug, Default)]
+pub struct OnlineState {
+    /// Id of the own player, once the server welcomed the client.
+    pub id: Option<u32>,
+    pub lobby: Vec<LobbyPlayer>,
+    /// When the next round starts, if enough players are there.
+    pub countdown_end: Option<Instant>,
+    /// The current round, or the last one until the next round starts.
+    pub round: Option<OnlineRound>,
+    /// Reason the server refused the client.
+    pub error: Option<String>,
+}
+
+impl OnlineState {
+    pub fn apply(&mut self, message: ServerMessage) {
+        match message {
+            ServerMessage::Welcome { id } => self.id = Some(id),
+            ServerMessage::Lobby { players, countdown } => {
+                self.lobby = players;
+                self.countdown_end = countdown
+                    .map(|countdown| Instant::now() + Duration::from_secs_f64(countdown.max(0.0)));
+            }
+            ServerMessage::RoundStart {
+                round,
+                level,
+                players,
+            } => {
+                self.countdown_end = None;
+                self.round = Some(OnlineRound {
+                    number: round,
+                    versus: Versus::new(&level, players.len(), 0),
+                    level,
+                    players,
+                    winner: None,
+                });
+            }
+            ServerMessage::Snapshot {
+                tick,
+                target,
+                snakes,
+            } => {
+                let Some(round) = &mut self.round else {
+                    return;
+                };
+                round.versus.tick = tick;
+                round.versus.target.position = target;
+                for state in snakes {
+                    let Some(player) = round
+                        .snake_of(state.id)
+                        .and_then(|index| round.versus.players.get_mut(index))
+                    else {
+                        continue;
+                    };
+                    player.alive = state.alive;
+                    player.points = state.points;
+                    player.snake.position = state.position;
+                    player.snake.current_direction = state.direction;
+                }
+            }
+            ServerMessage::RoundOver { winner } => {
+                if let Some(round) = &mut self.round {
+                    round.versus.round_over = true;
+                    round.winner = Some(winner);
+                }
+            }
+            ServerMessage::Pong { .. } => {}
+            ServerMessage::Error { message } => self.error = Some(message),
+        }
+    }
+
+    /// Title of the lobby: the countdown to the next round, the result of the last round or that
+    /// the server waits for more players.
+    pub fn lobby_title(&self) -> String {
+        if let Some(countdown_end) = self.countdown_end {
+            let seconds = countdown_end
+                .saturating_duration_since(Instant::now())
+                .as_secs();
+            return format!("Next round in {}", seconds + 1);
+        }
+        let Some((round, winner)) = self
+            .round
+            .as_ref()
+            .and_then(|round| Some((round, round.winner?)))
+        else {
+            return "Waiting for players".to_owned();
+        };
+        match winner.and_then(|id| round.player_name(id)) {
+            Some(name) => format!("{name} wins round {}", round.number),
+            None => format!("Round {} is a draw", round.number),
+        }
+    }
+
+    /// Index of the own snake in the current round, if the client takes part in it.
+    pub fn own_snake(&self) -> Option<usize> {
+        self.round.as_ref()?.snake_of(self.id?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::protocol::SnakeState;
+    use crate::snake::Direction;
+    use euclid::Point2D;
+    use std::net::TcpListener;
+
+    fn player(id: u32, name: &str) -> LobbyPlayer {
+        LobbyPlayer {
+            id,
+            name: name.to_owned(),
+            wins: 0,
+        }
+    }
+
+    #[test]
+    fn test_online_state() {
+        let mut state = OnlineState::default();
+        state.apply(ServerMessage::Welcome { id: 7 });
+        state.apply(ServerMessage::Lobby {
+            players: vec![player(2, "Ada"), player(7, "Bob")],
+            countdown: Some(1.0),
+        });
+        assert!(state.countdown_end.is_some());
+        assert_eq!("Next round in 1", state.lobby_title());
+        state.apply(ServerMessage::RoundStart {
+            round: 1,
+            level: Level::default(),
+            players: vec![player(2, "Ada"), player(7, "Bob")],
+        });
+        assert_eq!(None, state.countdown_end);
+        assert_eq!(Some(1), state.own_snake());
+
+        state.apply(ServerMessage::Snapshot {
+            tick: 4,
+            target: Point2D::new(3, 3),
+            snakes: vec![SnakeState {
+                id: 7,
+                alive: false,
+                points: 2,
+                direction: Direction::Down,
+                position: vec![Point2D::new(5, 6), Point2D::new(5, 5)],
+            }],
+        });
+        let round = state.round.as_ref().unwrap();
+        assert_eq!(4, round.versus.tick);
+        assert_eq!(Point2D::new(3, 3), round.versus.target.position);
+        let bob = &round.versus.players[1];
+        assert!(!bob.alive);
+        assert_eq!(2, bob.points);
+        assert_eq!(Direction::Down, bob.snake.current_direction);
+        assert_eq!(
+            vec![Point2D::new(5, 6), Point2D::new(5, 5)],
+            bob.snake.position
+        );
+        assert!(round.versus.players[0].alive);
+
+        state.apply(ServerMessage::RoundOver { winner: Some(2) });
+        let round = state.round.as_ref().unwrap();
+        assert_eq!(Some(Some(2)), round.winner);
+        assert_eq!(Some("Ada"), round.player_name(2));
+        assert_eq!("Ada wins round 1", state.lobby_title());
+    }
+
+    #[test]
+    fn test_connect_refused() {
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        // The listener is closed again
+        assert!(Client::connect(address, "Ada").is_err());
+    }
+
+    #[test]
+    fn test_pong_counts_as_received() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = Client::watch(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        let connected = client.last_received();
+
+        thread::sleep(Duration::from_millis(10));
+        writeln!(server, "{}", ServerMessage::Pong { time: 0.0 }.to_json()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while client.last_received() == connected && Instant::now() < deadline {
+            assert!(client.poll().is_empty());
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(client.last_received() > connected);
+        assert!(client.rtt.is_some());
+    }
+}
@@ -0,0 +1,211 @@
+//! Macroquad frontend for playing on a server. The client only sends the turns of its snake and
+//! renders the snapshots of the server.
+
+use super::client::{Client, OnlineRound, OnlineState};
+use super::protocol::{ClientMessage, LobbyPlayer};
+use crate::constants::{OPTION_TEXT_SIZE, PLAYER_COLORS, SNAKE_HEAD_COLOR, TITLE_TEXT_SIZE};
+use crate::game::{render_versus_board, VERSUS_KEYS};
+use crate::graphic_utils::{
+    draw_message, render_dim_overlay, render_error_message, render_scoreboard, render_text,
+};
+use crate::snake::Direction;
+use crate::Context;
+use euclid::Point2D;
+use macroquad::color::Color;
+use macroquad::input::{get_keys_pressed, KeyCode};
+use macroquad::window::{clear_background, next_frame, screen_height, screen_width};
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Color of the players in the lobby, which have no snake yet.
+const LOBBY_COLOR: Color = SNAKE_HEAD_COLOR;
+
+/// Joins the server at `address` as `name` and plays until Escape is pressed or the connection is
+/// lost.
+pub async fn play_online(cx: &Context, address: &str, name: &str) {
+    draw_message(&format!("Connecting to {address}"), SNAKE_HEAD_COLOR);
+    next_frame().await;
+    let mut client = match Client::connect(address, name) {
+        Ok(client) => client,
+        Err(err) => {
+            warn!(address, ?err, "Failed to connect");
+            render_error_message(&format!("Could not connect to {address}: {err}")).await;
+            return;
+        }
+    };
+    info!(address, name, "Connected");
+    let mut state = OnlineState::default();
+
+    loop {
+        for message in client.poll() {
+            state.apply(message);
+        }
+        if let Some(error) = state.error.take() {
+            render_error_message(&error).await;
+            return;
+        }
+        if !client.is_connected() {
+            render_error_message("The connection to the server was lost").await;
+            return;
+        }
+
+        let keys = get_keys_pressed();
+        if keys.contains(&KeyCode::Escape) {
+            info!("Left the server");
+            client.leave();
+            return;
+        }
+        if let Some((round, player)) = state.round.as_ref().zip(state.own_snake()) {
+            let snake = &round.versus.players[player];
+            if let Some(direction) = online_turn(&keys).filter(|_| snake.alive) {
+                // A failed send shows up as a lost connection in the next frame
+                let _ = client.send(&ClientMessage::Turn {
+                    direction,
+                    tick: round.versus.tick,
+                });
+            }
+        }
+
+        render_online(&state, &client, cx);
+        next_frame().await;
+    }
+}
+
+/// Any of the keys of the local versus game steers the own snake.
+fn online_turn(keys: &HashSet<KeyCode>) -> Option<Direction> {
+    VERSUS_KEYS
+        .iter()
+        .flatten()
+        .find(|(key, _)| keys.contains(key))
+        .map(|(_, direction)| *direction)
+}
+
+fn render_online(state: &OnlineState, client: &Client, cx: &Context) {
+    match &state.round {
+        Some(round) => {
+            render_versus_board(&round.versus);
+            render_scoreboard(&round_scoreboard(round, state.id), Some(&cx.font));
+        }
+        None => clear_background(macroquad::color::BLACK),
+    }
+    let round_running = state
+        .round
+        .as_ref()
+        .is_some_and(|round| round.winner.is_none());
+    if !round_running {
+        render_lobby(state, cx);
+    }
+    if let Some(rtt) = client.rtt {
+        render_text(
+            &format!("{} ms", rtt.as_millis()),
+            Point2D::new(screen_width() - 40.0, screen_height() - 20.0),
+            Some(&cx.font),
+            OPTION_TEXT_SIZE,
+            SNAKE_HEAD_COLOR,
+        );
+    }
+}
+
+/// Lines of the scoreboard of a round, marking the own player and players whose snake collided.
+fn round_scoreboard(round: &OnlineRound, own_id: Option<u32>) -> Vec<(String, Color)> {
+    round
+        .players
+        .iter()
+        .zip(&round.versus.players)
+        .enumerate()
+        .map(|(i, (lobby_player, player))| {
+            let marker = if Some(lobby_player.id) == own_id {
+                "> "
+            } else {
+                ""
+            };
+            let state = if player.alive { "" } else { " (out)" };
+            (
+                format!("{marker}{}: {}{state}", lobby_player.name, player.points),
+                PLAYER_COLORS[i % PLAYER_COLORS.len()].0,
+            )
+        })
+        .collect()
+}
+
+/// Shows the result of the last round, the players waiting for the next one and the countdown.
+fn render_lobby(state: &OnlineState, cx: &Context) {
+    render_dim_overlay();
+    let center: Point2D<f32, f32> = Point2D::new(screen_width() / 2.0, screen_height() / 2.0);
+    let title = state.lobby_title();
+    render_text(
+        &title,
+        Point2D::new(center.x, center.y - 2.0 * f32::from(TITLE_TEXT_SIZE)),
+        Some(&cx.font),
+        TITLE_TEXT_SIZE,
+        SNAKE_HEAD_COLOR,
+    );
+    for (i, line) in lobby_lines(&state.lobby, state.id).iter().enumerate() {
+        render_text(
+            line,
+            Point2D::new(
+                center.x,
+                center.y + i as f32 * f32::from(OPTION_TEXT_SIZE) * 1.5,
+            ),
+            Some(&cx.font),
+            OPTION_TEXT_SIZE,
+            LOBBY_COLOR,
+        );
+    }
+}
+
+fn lobby_lines(players: &[LobbyPlayer], own_id: Option<u32>) -> Vec<String> {
+    players
+        .iter()
+        .map(|player| {
+            let you = if Some(player.id) == own_id {
+                " (you)"
+            } else {
+                ""
+            };
+            let wins = match player.wins {
+                1 => "1 win".to_owned(),
+                wins => format!("{wins} wins"),
+            };
+            format!("{}{you} - {wins}", player.name)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_online_turn() {
+        assert_eq!(None, online_turn(&HashSet::new()));
+        assert_eq!(
+            Some(Direction::Left),
+            online_turn(&HashSet::from([KeyCode::A]))
+        );
+        assert_eq!(
+            Some(Direction::Up),
+            online_turn(&HashSet::from([KeyCode::Up]))
+        );
+    }
+
+    #[test]
+    fn test_lobby_lines() {
+        let players = [
+            LobbyPlayer {
+                id: 1,
+                name: "Ada".to_owned(),
+                wins: 1,
+            },
+            LobbyPlayer {
+                id: 2,
+                name: "Bob".to_owned(),
+                wins: 3,
+            },
+        ];
+        assert_eq!(
+            vec!["Ada - 1 win", "Bob (you) - 3 wins"],
+            lobby_lines(&players, Some(2))
+        );
+    }
+}
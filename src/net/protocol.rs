@@ -0,0 +1,382 @@
+//! Messages between the server and the clients. Every message is a JSON object with a `type`
+//! field, sent as a single line.
+
+use crate::level::Level;
+use crate::replay::{
+    level_from_json, level_to_json, parse_direction, parse_optional, parse_point, parse_u32,
+};
+use crate::snake::Direction;
+use euclid::Point2D;
+use serde_json::{json, Value};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ClientMessage {
+    /// First message of a client, which asks to take part in the next rounds.
+    Join {
+        name: String,
+    },
+    /// First message of a spectator, which receives the same messages as the players without
+    /// taking part in the rounds.
+    Watch,
+    /// Turn of the client's snake. `tick` is the tick of the newest snapshot the client knew of,
+    /// which tells the server how far the client lags behind.
+    Turn {
+        direction: Direction,
+        tick: u32,
+    },
+    /// Measures the round trip time. The server answers with [`ServerMessage::Pong`].
+    Ping {
+        time: f64,
+    },
+    Leave,
+}
+
+/// A player as listed in the lobby.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LobbyPlayer {
+    pub id: u32,
+    pub name: String,
+    /// Rounds the player won since joining.
+    pub wins: u32,
+}
+
+/// State of a snake at a tick.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SnakeState {
+    pub id: u32,
+    pub alive: bool,
+    pub points: i32,
+    pub direction: Direction,
+    pub position: Vec<Point2D<i32, i32>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ServerMessage {
+    /// Answer to [`ClientMessage::Join`] with the id of the client's player.
+    Welcome {
+        id: u32,
+    },
+    /// Sent whenever a player joins or leaves between rounds. `countdown` is the number of
+    /// seconds until the next round starts, if enough players are there.
+    Lobby {
+        players: Vec<LobbyPlayer>,
+        countdown: Option<f64>,
+    },
+    /// A round starts on `level`. The snakes of the players are listed in the order of `players`.
+    RoundStart {
+        round: u32,
+        level: Level,
+        players: Vec<LobbyPlayer>,
+    },
+    /// State of the round after `tick`.
+    Snapshot {
+        tick: u32,
+        target: Point2D<i32, i32>,
+        snakes: Vec<SnakeState>,
+    },
+    /// `winner` is `None` on a draw.
+    RoundOver {
+        winner: Option<u32>,
+    },
+    Pong {
+        time: f64,
+    },
+    /// The server refuses a request, e.g. because it is full. The connection is closed afterwards.
+    Error {
+        message: String,
+    },
+}
+
+fn point_to_json(point: Point2D<i32, i32>) -> Value {
+    json!([point.x, point.y])
+}
+
+fn parse_string(value: &Value) -> Result<String, ProtocolError> {
+    value
+        .as_str()
+        .map(str::to_owned)
+        .ok_or(ProtocolError::InvalidMessage)
+}
+
+fn parse_f64(value: &Value) -> Result<f64, ProtocolError> {
+    value.as_f64().ok_or(ProtocolError::InvalidMessage)
+}
+
+fn parse_array<T>(
+    value: &Value,
+    parse: impl Fn(&Value) -> Result<T, ProtocolError>,
+) -> Result<Vec<T>, ProtocolError> {
+    value
+        .as_array()
+        .ok_or(ProtocolError::InvalidMessage)?
+        .iter()
+        .map(parse)
+        .collect()
+}
+
+fn parse_message(line: &str) -> Result<(String, Value), ProtocolError> {
+    let json: Value = serde_json::from_str(line)?;
+    let message_type = parse_string(&json["type"])?;
+    Ok((message_type, json))
+}
+
+impl ClientMessage {
+    pub fn to_json(&self) -> Value {
+        match self {
+            ClientMessage::Join { name } => json!({"type": "join", "name": name}),
+            ClientMessage::Watch => json!({"type": "watch"}),
+            ClientMessage::Turn { direction, tick } => {
+                json!({"type": "turn", "direction": direction.name(), "tick": tick})
+            }
+            ClientMessage::Ping { time } => json!({"type": "ping", "time": time}),
+            ClientMessage::Leave => json!({"type": "leave"}),
+        }
+    }
+
+    /// Parses a single line received from a client.
+    pub fn parse(line: &str) -> Result<Self, ProtocolError> {
+        let (message_type, json) = parse_message(line)?;
+        Ok(match message_type.as_str() {
+            "join" => ClientMessage::Join {
+                name: parse_string(&json["name"])?,
+            },
+            "watch" => ClientMessage::Watch,
+            "turn" => ClientMessage::Turn {
+                direction: parse_direction(&json["direction"])?,
+                tick: parse_u32(&json["tick"])?,
+            },
+            "ping" => ClientMessage::Ping {
+                time: parse_f64(&json["time"])?,
+            },
+            "leave" => ClientMessage::Leave,
+            _ => return Err(ProtocolError::UnknownType(message_type)),
+        })
+    }
+}
+
+impl LobbyPlayer {
+    fn to_json(&self) -> Value {
+        json!({"id": self.id, "name": self.name, "wins": self.wins})
+    }
+
+    fn from_json(json: &Value) -> Result<Self, ProtocolError> {
+        Ok(LobbyPlayer {
+            id: parse_u32(&json["id"])?,
+            name: parse_string(&json["name"])?,
+            wins: parse_u32(&json["wins"])?,
+        })
+    }
+}
+
+impl SnakeState {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "alive": self.alive,
+            "points": self.points,
+            "direction": self.direction.name(),
+            "position": self.position.iter().copied().map(point_to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    fn from_json(json: &Value) -> Result<Self, ProtocolError> {
+        Ok(SnakeState {
+            id: parse_u32(&json["id"])?,
+            alive: json["alive"]
+                .as_bool()
+                .ok_or(ProtocolError::InvalidMessage)?,
+            points: json["points"]
+                .as_i64()
+                .and_then(|points| i32::try_from(points).ok())
+                .ok_or(ProtocolError::InvalidMessage)?,
+            direction: parse_direction(&json["direction"])?,
+            position: parse_array(&json["position"], |point| Ok(parse_point(point)?))?,
+        })
+    }
+}
+
+impl ServerMessage {
+    pub fn to_json(&self) -> Value {
+        match self {
+            ServerMessage::Welcome { id } => json!({"type": "welcome", "id": id}),
+            ServerMessage::Lobby { players, countdown } => json!({
+                "type": "lobby",
+                "players": players.iter().map(LobbyPlayer::to_json).collect::<Vec<_>>(),
+                "countdown": countdown,
+            }),
+            ServerMessage::RoundStart {
+                round,
+                level,
+                players,
+            } => json!({
+                "type": "round_start",
+                "round": round,
+                "level": level_to_json(level),
+                "players": players.iter().map(LobbyPlayer::to_json).collect::<Vec<_>>(),
+            }),
+            ServerMessage::Snapshot {
+                tick,
+                target,
+                snakes,
+            } => json!({
+                "type": "snapshot",
+                "tick": tick,
+                "target": point_to_json(*target),
+                "snakes": snakes.iter().map(SnakeState::to_json).collect::<Vec<_>>(),
+            }),
+            ServerMessage::RoundOver { winner } => {
+                json!({"type": "round_over", "winner": winner})
+            }
+            ServerMessage::Pong { time } => json!({"type": "pong", "time": time}),
+            ServerMessage::Error { message } => json!({"type": "error", "message": message}),
+        }
+    }
+
+    /// Parses a single line received from the server.
+    pub fn parse(line: &str) -> Result<Self, ProtocolError> {
+        let (message_type, json) = parse_message(line)?;
+        Ok(match message_type.as_str() {
+            "welcome" => ServerMessage::Welcome {
+                id: parse_u32(&json["id"])?,
+            },
+            "lobby" => ServerMessage::Lobby {
+                players: parse_array(&json["players"], LobbyPlayer::from_json)?,
+                countdown: json["countdown"].as_f64(),
+            },
+            "round_start" => ServerMessage::RoundStart {
+                round: parse_u32(&json["round"])?,
+                level: level_from_json(&json["level"])?,
+                players: parse_array(&json["players"], LobbyPlayer::from_json)?,
+            },
+            "snapshot" => ServerMessage::Snapshot {
+                tick: parse_u32(&json["tick"])?,
+                target: parse_point(&json["target"])?,
+                snakes: parse_array(&json["snakes"], SnakeState::from_json)?,
+            },
+            "round_over" => ServerMessage::RoundOver {
+                winner: parse_optional(&json["winner"], parse_u32)?,
+            },
+            "pong" => ServerMessage::Pong {
+                time: parse_f64(&json["time"])?,
+            },
+            "error" => ServerMessage::Error {
+                message: parse_string(&json["message"])?,
+            },
+            _ => return Err(ProtocolError::UnknownType(message_type)),
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProtocolError {
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("The message is not valid")]
+    InvalidMessage,
+
+    #[error("Unknown message type \"{0}\"")]
+    UnknownType(String),
+}
+
+impl From<crate::replay::ReplayError> for ProtocolError {
+    fn from(_: crate::replay::ReplayError) -> Self {
+        ProtocolError::InvalidMessage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::borders::Borders;
+
+    #[test]
+    fn test_client_messages() {
+        let messages = [
+            ClientMessage::Join {
+                name: "Ada".to_owned(),
+            },
+            ClientMessage::Turn {
+                direction: Direction::Left,
+                tick: 12,
+            },
+            ClientMessage::Watch,
+            ClientMessage::Ping { time: 1.5 },
+            ClientMessage::Leave,
+        ];
+        for message in messages {
+            assert_eq!(
+                message,
+                ClientMessage::parse(&message.to_json().to_string()).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_server_messages() {
+        let players = vec![LobbyPlayer {
+            id: 3,
+            name: "Ada".to_owned(),
+            wins: 2,
+        }];
+        let messages = [
+            ServerMessage::Welcome { id: 3 },
+            ServerMessage::Lobby {
+                players: players.clone(),
+                countdown: Some(2.5),
+            },
+            ServerMessage::Lobby {
+                players: vec![],
+                countdown: None,
+            },
+            ServerMessage::RoundStart {
+                round: 1,
+                level: Level {
+                    borders: Borders::SOLID,
+                    obstacles: vec![Point2D::new(1, 2)],
+                    ..Level::default()
+                },
+                players,
+            },
+            ServerMessage::Snapshot {
+                tick: 7,
+                target: Point2D::new(4, 5),
+                snakes: vec![SnakeState {
+                    id: 3,
+                    alive: true,
+                    points: 1,
+                    direction: Direction::Up,
+                    position: vec![Point2D::new(1, 1), Point2D::new(1, 2)],
+                }],
+            },
+            ServerMessage::RoundOver { winner: Some(3) },
+            ServerMessage::RoundOver { winner: None },
+            ServerMessage::Pong { time: 0.25 },
+            ServerMessage::Error {
+                message: "full".to_owned(),
+            },
+        ];
+        for message in messages {
+            assert_eq!(
+                message,
+                ServerMessage::parse(&message.to_json().to_string()).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_messages() {
+        assert!(matches!(
+            ClientMessage::parse("{\"type\": \"dance\"}"),
+            Err(ProtocolError::UnknownType(_))
+        ));
+        assert!(matches!(
+            ClientMessage::parse("{\"type\": \"turn\", \"direction\": \"north\", \"tick\": 1}"),
+            Err(ProtocolError::InvalidMessage)
+        ));
+        assert!(matches!(
+            ServerMessage::parse("not json"),
+            Err(ProtocolError::JsonError(_))
+        ));
+    }
+}
@@ -0,0 +1,831 @@
+//! Authoritative server of networked versus games. The server runs the tick loop, the clients only
+//! send their turns and render the snapshots the server sends after every tick.
+
+use super::protocol::{ClientMessage, LobbyPlayer, ServerMessage, SnakeState};
+use crate::borders::{Border, Borders};
+use crate::cli::{parsed_value, value, ArgsError};
+use crate::constants::{
+    LEVEL_PATH, NET_CLIENT_TIMEOUT, NET_COUNTDOWN, NET_DEFAULT_ADDRESS, NET_MAX_INPUT_LAG,
+    NET_MAX_NAME_LENGTH, NET_SEND_QUEUE, NET_WRITE_TIMEOUT,
+};
+use crate::level::Level;
+use crate::rng::Rng;
+use crate::snake::Direction;
+use crate::versus::{Versus, VersusEvent, MAX_PLAYERS};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Pause of the server loop between two updates.
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+const DEFAULT_NAME: &str = "Player";
+/// Players needed to start a round.
+pub const MIN_PLAYERS: usize = 2;
+
+pub const USAGE: &str = "\
+Usage: snake-server [options]
+
+Options:
+      --address <host:port>  Address to listen on (default 127.0.0.1:7878)
+      --endless <wrap|solid> Play the Endless Game map (default wrap)
+      --level <id or title>  Play a level instead, e.g. \"base/2\" or \"Level 2\"
+  -l, --levels <path>        Load the levels from a directory, a bundled pack or a level file
+      --max-players <number> Players of a round, between 2 and 8 (default 8)
+      --countdown <seconds>  Time between enough players joining and the start of a round
+      --seed <number>        Seed of the first round, the following rounds count up
+  -v, --verbose              Print debug output
+  -h, --help                 Print this help";
+
+/// Command-line arguments of the `snake-server` binary.
+#[derive(Debug, PartialEq)]
+pub struct ServerArgs {
+    pub address: String,
+    /// Borders of the Endless Game map, which is played unless a level is given.
+    pub borders: Borders,
+    pub level: Option<String>,
+    pub levels: String,
+    pub max_players: usize,
+    pub countdown: f64,
+    pub seed: Option<u64>,
+    pub verbose: bool,
+    pub help: bool,
+}
+
+impl Default for ServerArgs {
+    fn default() -> Self {
+        ServerArgs {
+            address: NET_DEFAULT_ADDRESS.to_owned(),
+            borders: Borders::WRAP,
+            level: None,
+            levels: LEVEL_PATH.to_owned(),
+            max_players: MAX_PLAYERS,
+            countdown: NET_COUNTDOWN,
+            seed: None,
+            verbose: false,
+            help: false,
+        }
+    }
+}
+
+impl ServerArgs {
+    /// Parses the arguments without the name of the program.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = ServerArgs::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let args = &mut args;
+            match arg.as_str() {
+                "--address" => parsed.address = value(args, "--address")?,
+                "--endless" => {
+                    let border = parsed_value(args, "--endless", Border::from_name)?;
+                    parsed.borders = Borders::all(border);
+                }
+                "--level" => parsed.level = Some(value(args, "--level")?),
+                "-l" | "--levels" => parsed.levels = value(args, "--levels")?,
+                "--max-players" => {
+                    parsed.max_players = parsed_value(args, "--max-players", |players| {
+                        players
+                            .parse()
+                            .ok()
+                            .filter(|players| (MIN_PLAYERS..=MAX_PLAYERS).contains(players))
+                    })?;
+                }
+                "--countdown" => {
+                    parsed.countdown = parsed_value(args, "--countdown", |countdown| {
+                        countdown
+                            .parse()
+                            .ok()
+                            .filter(|countdown: &f64| countdown.is_finite() && *countdown >= 0.0)
+                    })?;
+                }
+                "--seed" => {
+                    parsed.seed = Some(parsed_value(args, "--seed", |seed| seed.parse().ok())?)
+                }
+                "-v" | "--verbose" => parsed.verbose = true,
+                "-h" | "--help" => parsed.help = true,
+                _ if arg.starts_with('-') => return Err(ArgsError::UnknownOption(arg)),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub level: Level,
+    /// At most [`MAX_PLAYERS`] players can join, but not fewer than [`MIN_PLAYERS`].
+    pub max_players: usize,
+    /// Seconds between enough players being in the lobby and the start of a round.
+    pub countdown: f64,
+    /// Seed of the first round, the following rounds count up. Rounds are seeded by the time if
+    /// it is `None`.
+    pub seed: Option<u64>,
+}
+
+impl ServerConfig {
+    pub fn new(level: Level) -> Self {
+        ServerConfig {
+            level,
+            max_players: MAX_PLAYERS,
+            countdown: NET_COUNTDOWN,
+            seed: None,
+        }
+    }
+}
+
+struct Connection {
+    /// Queue of the lines the writer thread sends to the client.
+    outbox: SyncSender<String>,
+    /// Set once the client joined.
+    name: Option<String>,
+    /// Spectators receive the messages of the game without taking part.
+    spectator: bool,
+    wins: u32,
+    last_seen: Instant,
+}
+
+/// What the reader thread of a connection received.
+enum Inbound {
+    Message(ClientMessage),
+    Closed,
+}
+
+enum State {
+    Lobby {
+        countdown_end: Option<Instant>,
+    },
+    Round {
+        versus: Versus,
+        /// Players in the order of their snakes, as announced at the start of the round.
+        players: Vec<LobbyPlayer>,
+        /// Ids of the players in the order of their snakes.
+        ids: Vec<u32>,
+        turns: Vec<(usize, Direction)>,
+        next_tick: Instant,
+    },
+}
+
+pub struct Server {
+    listener: TcpListener,
+    config: ServerConfig,
+    connections: BTreeMap<u32, Connection>,
+    inbox: Receiver<(u32, Inbound)>,
+    sender: Sender<(u32, Inbound)>,
+    next_id: u32,
+    rounds: u32,
+    state: State,
+    lobby_changed: bool,
+}
+
+impl Server {
+    pub fn bind(address: impl ToSocketAddrs, config: ServerConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let (sender, inbox) = channel();
+        Ok(Server {
+            listener,
+            config: ServerConfig {
+                max_players: config.max_players.clamp(MIN_PLAYERS, MAX_PLAYERS),
+                ..config
+            },
+            connections: BTreeMap::new(),
+            inbox,
+            sender,
+            next_id: 1,
+            rounds: 0,
+            state: State::Lobby {
+                countdown_end: None,
+            },
+            lobby_changed: false,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Runs the server until the process is stopped.
+    pub fn run(&mut self) -> ! {
+        loop {
+            self.update(Instant::now());
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Accepts new connections, handles the received messages and advances the game to `now`.
+    pub fn update(&mut self, now: Instant) {
+        self.accept(now);
+        while let Ok((id, inbound)) = self.inbox.try_recv() {
+            match inbound {
+                Inbound::Message(message) => self.handle_message(id, message, now),
+                Inbound::Closed => self.disconnect(id, "connection closed"),
+            }
+        }
+
+        let timeout = Duration::from_secs_f64(NET_CLIENT_TIMEOUT);
+        let timed_out: Vec<u32> = self
+            .connections
+            .iter()
+            .filter(|(_, connection)| now.duration_since(connection.last_seen) > timeout)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in timed_out {
+            self.disconnect(id, "timed out");
+        }
+
+        match &mut self.state {
+            State::Lobby { .. } => self.update_lobby(now),
+            State::Round { next_tick, .. } if now >= *next_tick => self.tick(now),
+            State::Round { .. } => {}
+        }
+    }
+
+    fn accept(&mut self, now: Instant) {
+        loop {
+            let stream = match self.listener.accept() {
+                Ok((stream, address)) => {
+                    debug!(%address, "Client connected");
+                    stream
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return,
+                Err(err) => {
+                    warn!(?err, "Failed to accept connection");
+                    return;
+                }
+            };
+            let id = self.next_id;
+            self.next_id += 1;
+            let reader = stream
+                .set_nonblocking(false)
+                .and_then(|()| stream.set_nodelay(true))
+                .and_then(|()| {
+                    stream.set_write_timeout(Some(Duration::from_secs_f64(NET_WRITE_TIMEOUT)))
+                })
+                .and_then(|()| stream.try_clone());
+            match reader {
+                Ok(reader) => {
+                    spawn_reader(id, reader, self.sender.clone());
+                    self.connections.insert(
+                        id,
+                        Connection {
+                            outbox: spawn_writer(id, stream),
+                            name: None,
+                            spectator: false,
+                            wins: 0,
+                            last_seen: now,
+                        },
+                    );
+                }
+                Err(err) => warn!(?err, "Failed to set up connection"),
+            }
+        }
+    }
+
+    fn handle_message(&mut self, id: u32, message: ClientMessage, now: Instant) {
+        let Some(connection) = self.connections.get_mut(&id) else {
+            return;
+        };
+        connection.last_seen = now;
+        match message {
+            ClientMessage::Join { name } => self.join(id, &name),
+            ClientMessage::Watch => self.watch(id),
+            ClientMessage::Turn { direction, tick } => {
+                if let State::Round {
+                    versus, ids, turns, ..
+                } = &mut self.state
+                {
+                    let lag = versus.tick.saturating_sub(tick);
+                    match ids.iter().position(|player| *player == id) {
+                        Some(player) if lag <= NET_MAX_INPUT_LAG => turns.push((player, direction)),
+                        Some(_) => debug!(id, lag, "Ignored turn of lagging client"),
+                        None => {}
+                    }
+                }
+            }
+            ClientMessage::Ping { time } => self.send(id, &ServerMessage::Pong { time }),
+            ClientMessage::Leave => self.disconnect(id, "left"),
+        }
+    }
+
+    fn join(&mut self, id: u32, name: &str) {
+        let joined = self.players().len();
+        let Some(connection) = self.connections.get_mut(&id) else {
+            return;
+        };
+        if connection.name.is_some() || connection.spectator {
+            return;
+        }
+        if joined >= self.config.max_players {
+            self.send(
+                id,
+                &ServerMessage::Error {
+                    message: "The server is full".to_owned(),
+                },
+            );
+            self.disconnect(id, "server full");
+            return;
+        }
+
+        let name: String = name.trim().chars().take(NET_MAX_NAME_LENGTH).collect();
+        let name = if name.is_empty() {
+            DEFAULT_NAME.to_owned()
+        } else {
+            name
+        };
+        info!(id, name, "Player joined");
+        connection.name = Some(name);
+        self.send(id, &ServerMessage::Welcome { id });
+        // Players joining during a round are told about the lobby right away, everyone else
+        // once the round is over
+        if matches!(self.state, State::Round { .. }) {
+            let lobby = self.lobby_message(None);
+            self.send(id, &lobby);
+        }
+        self.lobby_changed = true;
+    }
+
+    /// Lets the client follow the game. Spectators joining during a round get its start and the
+    /// current snapshot right away.
+    fn watch(&mut self, id: u32) {
+        let Some(connection) = self.connections.get_mut(&id) else {
+            return;
+        };
+        if connection.name.is_some() || connection.spectator {
+            return;
+        }
+        info!(id, "Spectator joined");
+        connection.spectator = true;
+
+        let countdown_end = match &self.state {
+            State::Lobby { countdown_end } => *countdown_end,
+            State::Round { .. } => None,
+        };
+        let lobby = self.lobby_message(countdown_end);
+        self.send(id, &lobby);
+        if let State::Round {
+            versus,
+            players,
+            ids,
+            ..
+        } = &self.state
+        {
+            let round_start = ServerMessage::RoundStart {
+                round: self.rounds,
+                level: self.config.level.clone(),
+                players: players.clone(),
+            };
+            let snapshot = snapshot(versus, ids);
+            self.send(id, &round_start);
+            self.send(id, &snapshot);
+        }
+    }
+
+    fn disconnect(&mut self, id: u32, reason: &str) {
+        let Some(connection) = self.connections.remove(&id) else {
+            return;
+        };
+        info!(id, name = connection.name, reason, "Client disconnected");
+        if connection.name.is_none() {
+            return;
+        }
+        self.lobby_changed = true;
+        // The snake is removed, the next tick decides whether the round is over
+        if let State::Round { versus, ids, .. } = &mut self.state {
+            if let Some(player) = ids.iter().position(|player| *player == id) {
+                versus.players[player].alive = false;
+            }
+        }
+    }
+
+    /// Players which joined, ordered by their id.
+    fn players(&self) -> Vec<LobbyPlayer> {
+        self.connections
+            .iter()
+            .filter_map(|(id, connection)| {
+                Some(LobbyPlayer {
+                    id: *id,
+                    name: connection.name.clone()?,
+                    wins: connection.wins,
+                })
+            })
+            .collect()
+    }
+
+    fn lobby_message(&self, countdown_end: Option<Instant>) -> ServerMessage {
+        let now = Instant::now();
+        ServerMessage::Lobby {
+            players: self.players(),
+            countdown: countdown_end.map(|end| end.saturating_duration_since(now).as_secs_f64()),
+        }
+    }
+
+    fn update_lobby(&mut self, now: Instant) {
+        let State::Lobby { countdown_end } = &mut self.state else {
+            return;
+        };
+        let enough_players = self
+            .connections
+            .values()
+            .filter(|connection| connection.name.is_some())
+            .count()
+            >= MIN_PLAYERS;
+        let countdown_changed = enough_players != countdown_end.is_some();
+        if countdown_changed {
+            *countdown_end =
+                enough_players.then(|| now + Duration::from_secs_f64(self.config.countdown));
+        }
+        let countdown_end = *countdown_end;
+
+        if countdown_end.is_some_and(|end| now >= end) {
+            self.start_round(now);
+        } else if countdown_changed || self.lobby_changed {
+            self.lobby_changed = false;
+            self.broadcast(&self.lobby_message(countdown_end));
+        }
+    }
+
+    fn start_round(&mut self, now: Instant) {
+        let players = self.players();
+        self.rounds += 1;
+        let seed = self.config.seed.map_or_else(
+            || Rng::from_time().seed(),
+            |seed| seed.wrapping_add(u64::from(self.rounds - 1)),
+        );
+        info!(
+            round = self.rounds,
+            seed,
+            players = players.len(),
+            "Round started"
+        );
+        let versus = Versus::new(&self.config.level, players.len(), seed);
+        let ids: Vec<u32> = players.iter().map(|player| player.id).collect();
+
+        self.broadcast(&ServerMessage::RoundStart {
+            round: self.rounds,
+            level: self.config.level.clone(),
+            players: players.clone(),
+        });
+        self.broadcast(&snapshot(&versus, &ids));
+        let interval = tick_interval(&self.config.level);
+        self.state = State::Round {
+            versus,
+            players,
+            ids,
+            turns: vec![],
+            next_tick: now + interval,
+        };
+        self.lobby_changed = false;
+    }
+
+    fn tick(&mut self, now: Instant) {
+        let interval = tick_interval(&self.config.level);
+        let State::Round {
+            versus,
+            ids,
+            turns,
+            next_tick,
+            ..
+        } = &mut self.state
+        else {
+            return;
+        };
+        let events = versus.step(turns);
+        turns.clear();
+        // A server which fell behind doesn't try to catch up, which would fast-forward the game
+        *next_tick = (*next_tick + interval).max(now);
+        let snapshot = snapshot(versus, ids);
+
+        let mut winner = None;
+        for event in events {
+            match event {
+                VersusEvent::Collision { player, cause, .. } => {
+                    info!(tick = versus.tick, id = ids[player], ?cause, "Collision");
+                }
+                VersusEvent::TargetHit { .. } => {}
+                VersusEvent::RoundOver {
+                    winner: round_winner,
+                } => {
+                    winner = Some(round_winner.map(|player| ids[player]));
+                }
+            }
+        }
+
+        self.broadcast(&snapshot);
+        if let Some(winner) = winner {
+            info!(round = self.rounds, winner, "Round ended");
+            if let Some(connection) = winner.and_then(|id| self.connections.get_mut(&id)) {
+                connection.wins += 1;
+            }
+            self.broadcast(&ServerMessage::RoundOver { winner });
+            self.state = State::Lobby {
+                countdown_end: None,
+            };
+            self.lobby_changed = true;
+        }
+    }
+
+    fn send(&mut self, id: u32, message: &ServerMessage) {
+        let line = message.to_json().to_string();
+        let Some(connection) = self.connections.get(&id) else {
+            return;
+        };
+        if let Err(reason) = queue(connection, line) {
+            self.disconnect(id, reason);
+        }
+    }
+
+    /// Sends `message` to all players which joined and to all spectators.
+    fn broadcast(&mut self, message: &ServerMessage) {
+        let line = message.to_json().to_string();
+        let failed: Vec<(u32, &str)> = self
+            .connections
+            .iter()
+            .filter(|(_, connection)| connection.name.is_some() || connection.spectator)
+            .filter_map(|(id, connection)| {
+                queue(connection, line.clone())
+                    .err()
+                    .map(|reason| (*id, reason))
+            })
+            .collect();
+        for (id, reason) in failed {
+            self.disconnect(id, reason);
+        }
+    }
+}
+
+/// Hands `line` to the writer thread of the connection without waiting for the client. Returns
+/// why the connection has to be dropped if that fails.
+fn queue(connection: &Connection, line: String) -> Result<(), &'static str> {
+    match connection.outbox.try_send(line) {
+        Ok(()) => Ok(()),
+        Err(TrySendError::Full(_)) => Err("too slow"),
+        Err(TrySendError::Disconnected(_)) => Err("not reachable"),
+    }
+}
+
+fn tick_interval(level: &Level) -> Duration {
+    Duration::from_secs_f64(1.0 / f64::from(level.updates_per_second.max(1)))
+}
+
+fn snapshot(versus: &Versus, ids: &[u32]) -> ServerMessage {
+    ServerMessage::Snapshot {
+        tick: versus.tick,
+        target: versus.target.position,
+        snakes: versus
+            .players
+            .iter()
+            .zip(ids)
+            .map(|(player, id)| SnakeState {
+                id: *id,
+                alive: player.alive,
+                points: player.points,
+                direction: player.snake.current_direction,
+                position: player.snake.position.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Writes the queued lines to a client on its own thread, so a slow client doesn't block the
+/// server loop. The connection is closed once the queue is dropped with the connection or a write
+/// fails, which also ends the reader thread.
+fn spawn_writer(id: u32, mut stream: TcpStream) -> SyncSender<String> {
+    let (outbox, lines) = sync_channel::<String>(NET_SEND_QUEUE);
+    thread::spawn(move || {
+        for line in lines {
+            if let Err(err) = writeln!(stream, "{line}") {
+                debug!(id, ?err, "Failed to send message");
+                break;
+            }
+        }
+        // The client may have closed the connection already
+        let _ = stream.shutdown(Shutdown::Both);
+    });
+    outbox
+}
+
+/// Reads the messages of a client on its own thread and forwards them to the server loop.
+fn spawn_reader(id: u32, stream: TcpStream, sender: Sender<(u32, Inbound)>) {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            match ClientMessage::parse(&line) {
+                Ok(message) => {
+                    if sender.send((id, Inbound::Message(message))).is_err() {
+                        return;
+                    }
+                }
+                Err(err) => warn!(id, ?err, "Received invalid message"),
+            }
+        }
+        // The server may be gone already
+        let _ = sender.send((id, Inbound::Closed));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::borders::Borders;
+    use crate::controller::Strategy;
+    use crate::net::bot;
+    use crate::net::client::Client;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    fn parse(args: &str) -> Result<ServerArgs, ArgsError> {
+        ServerArgs::parse(args.split_whitespace().map(str::to_owned))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(ServerArgs::default(), parse("").unwrap());
+        assert_eq!(
+            ServerArgs {
+                address: "0.0.0.0:9000".to_owned(),
+                borders: Borders::SOLID,
+                level: Some("base/2".to_owned()),
+                levels: "packs".to_owned(),
+                max_players: MIN_PLAYERS,
+                countdown: 0.5,
+                seed: Some(7),
+                verbose: true,
+                help: false,
+            },
+            parse(
+                "--address 0.0.0.0:9000 --endless solid --level base/2 -l packs --max-players 2 \
+                 --countdown 0.5 --seed 7 -v"
+            )
+            .unwrap()
+        );
+        assert!(parse("--help").unwrap().help);
+    }
+
+    #[test_case("--max-players 1" => ArgsError::InvalidValue { option: "--max-players", value: "1".to_owned() })]
+    #[test_case("--max-players 9" => ArgsError::InvalidValue { option: "--max-players", value: "9".to_owned() })]
+    #[test_case("--countdown -1" => ArgsError::InvalidValue { option: "--countdown", value: "-1".to_owned() })]
+    #[test_case("--countdown inf" => ArgsError::InvalidValue { option: "--countdown", value: "inf".to_owned() })]
+    #[test_case("--endless round" => ArgsError::InvalidValue { option: "--endless", value: "round".to_owned() })]
+    #[test_case("--seed x" => ArgsError::InvalidValue { option: "--seed", value: "x".to_owned() })]
+    #[test_case("--address" => ArgsError::MissingValue("--address"))]
+    #[test_case("--port 80" => ArgsError::UnknownOption("--port".to_owned()))]
+    #[test_case("levels" => ArgsError::UnexpectedArgument("levels".to_owned()))]
+    fn test_invalid_args(args: &str) -> ArgsError {
+        parse(args).unwrap_err()
+    }
+
+    /// Runs a server on a free port of localhost until the returned flag is set.
+    fn start_server(max_players: usize) -> (SocketAddr, Arc<AtomicBool>) {
+        let level = Level {
+            width: 12,
+            height: 12,
+            updates_per_second: 200,
+            target_points: Some(3),
+            borders: Borders::SOLID,
+            ..Level::default()
+        };
+        let config = ServerConfig {
+            max_players,
+            countdown: 0.0,
+            seed: Some(1),
+            ..ServerConfig::new(level)
+        };
+        let mut server = Server::bind("127.0.0.1:0", config).unwrap();
+        let address = server.local_addr().unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                server.update(Instant::now());
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+        (address, stop)
+    }
+
+    #[test]
+    fn test_bots_play_rounds() {
+        let (address, stop) = start_server(MAX_PLAYERS);
+        let bots: Vec<_> = ["Ada", "Bob"]
+            .into_iter()
+            .map(|name| {
+                thread::spawn(move || bot::play(address, name, Strategy::ShortestPath, Some(2)))
+            })
+            .collect();
+        let summaries: Vec<_> = bots
+            .into_iter()
+            .map(|bot| bot.join().unwrap().unwrap())
+            .collect();
+        stop.store(true, Ordering::Relaxed);
+
+        for summary in &summaries {
+            assert_eq!(2, summary.rounds);
+        }
+        assert!(summaries.iter().map(|summary| summary.wins).sum::<u32>() <= 2);
+    }
+
+    #[test]
+    fn test_spectator() {
+        let (address, stop) = start_server(MAX_PLAYERS);
+        let mut spectator = Client::watch(address).unwrap();
+        let bots: Vec<_> = ["Ada", "Bob"]
+            .into_iter()
+            .map(|name| thread::spawn(move || bot::play(address, name, Strategy::Greedy, Some(1))))
+            .collect();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut messages = vec![];
+        while !messages
+            .iter()
+            .any(|message| matches!(message, ServerMessage::RoundOver { .. }))
+            && Instant::now() < deadline
+        {
+            messages.extend(spectator.poll());
+            thread::sleep(POLL_INTERVAL);
+        }
+        for bot in bots {
+            bot.join().unwrap().unwrap();
+        }
+        stop.store(true, Ordering::Relaxed);
+
+        let round_start = messages.iter().find_map(|message| match message {
+            ServerMessage::RoundStart { players, .. } => Some(players.len()),
+            _ => None,
+        });
+        assert_eq!(Some(2), round_start);
+        assert!(messages
+            .iter()
+            .any(|message| matches!(message, ServerMessage::Snapshot { .. })));
+        assert!(messages
+            .iter()
+            .any(|message| matches!(message, ServerMessage::RoundOver { .. })));
+        assert!(!messages
+            .iter()
+            .any(|message| matches!(message, ServerMessage::Welcome { .. })));
+    }
+
+    #[test]
+    fn test_queue_overflow() {
+        let (outbox, lines) = sync_channel(NET_SEND_QUEUE);
+        let connection = Connection {
+            outbox,
+            name: Some("Ada".to_owned()),
+            spectator: false,
+            wins: 0,
+            last_seen: Instant::now(),
+        };
+        for _ in 0..NET_SEND_QUEUE {
+            assert_eq!(Ok(()), queue(&connection, String::new()));
+        }
+        assert_eq!(Err("too slow"), queue(&connection, String::new()));
+        drop(lines);
+        assert_eq!(Err("not reachable"), queue(&connection, String::new()));
+    }
+
+    #[test]
+    fn test_full_server() {
+        let (address, stop) = start_server(MIN_PLAYERS);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut players: Vec<_> = ["Ada", "Bob"]
+            .into_iter()
+            .map(|name| Client::connect(address, name).unwrap())
+            .collect();
+        for player in &mut players {
+            let mut welcomed = false;
+            while !welcomed && Instant::now() < deadline {
+                welcomed = player
+                    .poll()
+                    .iter()
+                    .any(|message| matches!(message, ServerMessage::Welcome { .. }));
+                thread::sleep(POLL_INTERVAL);
+            }
+            assert!(welcomed);
+        }
+
+        let mut third = Client::connect(address, "Cleo").unwrap();
+        let mut refusal = None;
+        while refusal.is_none() && Instant::now() < deadline {
+            refusal = third.poll().into_iter().find_map(|message| match message {
+                ServerMessage::Error { message } => Some(message),
+                _ => None,
+            });
+            thread::sleep(POLL_INTERVAL);
+        }
+        assert_eq!(Some("The server is full".to_owned()), refusal);
+        for player in &mut players {
+            assert!(player
+                .poll()
+                .iter()
+                .all(|message| !matches!(message, ServerMessage::Error { .. })));
+        }
+        stop.store(true, Ordering::Relaxed);
+    }
+}
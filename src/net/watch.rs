@@ -0,0 +1,155 @@
+//! Follows the rounds of a server as a spectator, see [`crate::spectator`].
+
+use super::client::{Client, OnlineState};
+use crate::spectator::{Feed, LeaderboardEntry};
+use crate::versus::Versus;
+use std::io;
+use std::net::ToSocketAddrs;
+
+pub struct ServerFeed {
+    client: Client,
+    state: OnlineState,
+}
+
+impl ServerFeed {
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(ServerFeed {
+            client: Client::watch(address)?,
+            state: OnlineState::default(),
+        })
+    }
+}
+
+impl Feed for ServerFeed {
+    fn update(&mut self) -> Result<(), String> {
+        for message in self.client.poll() {
+            self.state.apply(message);
+        }
+        if let Some(error) = self.state.error.take() {
+            return Err(error);
+        }
+        if !self.client.is_connected() {
+            return Err("The connection to the server was lost".to_owned());
+        }
+        Ok(())
+    }
+
+    fn board(&self) -> Option<&Versus> {
+        self.state.round.as_ref().map(|round| &round.versus)
+    }
+
+    fn names(&self) -> Vec<String> {
+        self.state.round.as_ref().map_or(vec![], |round| {
+            round
+                .players
+                .iter()
+                .map(|player| player.name.clone())
+                .collect()
+        })
+    }
+
+    fn leaderboard(&self) -> Vec<LeaderboardEntry> {
+        leaderboard(&self.state)
+    }
+
+    fn status(&self) -> String {
+        match &self.state.round {
+            Some(round) if round.winner.is_none() => format!("Round {}", round.number),
+            _ => self.state.lobby_title(),
+        }
+    }
+}
+
+/// All players in the lobby with their wins and their points in the current or last round.
+fn leaderboard(state: &OnlineState) -> Vec<LeaderboardEntry> {
+    state
+        .lobby
+        .iter()
+        .map(|player| {
+            let snake = state.round.as_ref().and_then(|round| {
+                let index = round.snake_of(player.id)?;
+                Some((index, round.versus.players.get(index)?))
+            });
+            LeaderboardEntry {
+                name: player.name.clone(),
+                points: snake.map_or(0, |(_, snake)| snake.points),
+                wins: Some(player.wins),
+                snake: snake.map(|(index, _)| index),
+                alive: snake.is_some_and(|(_, snake)| snake.alive),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+    use crate::net::protocol::{LobbyPlayer, ServerMessage, SnakeState};
+    use crate::snake::Direction;
+    use euclid::Point2D;
+
+    fn player(id: u32, name: &str, wins: u32) -> LobbyPlayer {
+        LobbyPlayer {
+            id,
+            name: name.to_owned(),
+            wins,
+        }
+    }
+
+    #[test]
+    fn test_leaderboard() {
+        let mut state = OnlineState::default();
+        state.apply(ServerMessage::RoundStart {
+            round: 2,
+            level: Level::default(),
+            players: vec![player(1, "Ada", 1), player(2, "Bob", 0)],
+        });
+        state.apply(ServerMessage::Lobby {
+            players: vec![
+                player(1, "Ada", 1),
+                player(2, "Bob", 0),
+                player(3, "Eve", 0),
+            ],
+            countdown: None,
+        });
+        state.apply(ServerMessage::Snapshot {
+            tick: 3,
+            target: Point2D::new(1, 1),
+            snakes: vec![SnakeState {
+                id: 2,
+                alive: false,
+                points: 4,
+                direction: Direction::Up,
+                position: vec![Point2D::new(2, 2)],
+            }],
+        });
+
+        assert_eq!(
+            vec![
+                LeaderboardEntry {
+                    name: "Ada".to_owned(),
+                    points: 0,
+                    wins: Some(1),
+                    snake: Some(0),
+                    alive: true,
+                },
+                LeaderboardEntry {
+                    name: "Bob".to_owned(),
+                    points: 4,
+                    wins: Some(0),
+                    snake: Some(1),
+                    alive: false,
+                },
+                LeaderboardEntry {
+                    name: "Eve".to_owned(),
+                    points: 0,
+                    wins: Some(0),
+                    snake: None,
+                    alive: false,
+                },
+            ],
+            leaderboard(&state)
+        );
+    }
+}
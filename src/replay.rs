@@ -0,0 +1,519 @@
+use crate::borders::Borders;
+use crate::level::Level;
+use crate::simulation::{Event, Simulation};
+use crate::snake::Direction;
+use euclid::Point2D;
+use macroquad::miniquad::date;
+use serde_json::{json, Value};
+use std::fs::{create_dir_all, metadata, read_dir, remove_file, File};
+use std::io::{Read, Write};
+use std::time::Duration;
+use tracing::debug;
+
+const MAX_SAVED_REPLAYS: usize = 20;
+/// Extension of the files written by [`ReplayStream`], which keeps them apart from the replays.
+const STREAM_EXTENSION: &str = "jsonl";
+/// Streams which weren't written for this long belong to games which are over, or which were
+/// paused so long that nobody is waiting for them.
+const STALE_STREAM_AGE: Duration = Duration::from_secs(300);
+
+/// Everything needed to reproduce a game session: the level, the seed of the random number
+/// generator and the turns which were applied on each tick.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Replay {
+    pub level: Level,
+    pub seed: u64,
+    pub ticks: u32,
+    pub inputs: Vec<(u32, Vec<Direction>)>,
+}
+
+impl Replay {
+    pub fn new(level: &Level, seed: u64) -> Self {
+        Replay {
+            level: level.clone(),
+            seed,
+            ticks: 0,
+            inputs: vec![],
+        }
+    }
+
+    /// Stores the turns applied on `tick`. Ticks without turns are not stored.
+    pub fn record(&mut self, tick: u32, turns: &[Direction]) {
+        if !turns.is_empty() {
+            self.inputs.push((tick, turns.to_vec()));
+        }
+        self.ticks = tick + 1;
+    }
+
+    pub fn turns_at(&self, tick: u32) -> &[Direction] {
+        match self.inputs.binary_search_by_key(&tick, |(t, _)| *t) {
+            Ok(index) => &self.inputs[index].1,
+            Err(_) => &[],
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "seed": self.seed,
+            "ticks": self.ticks,
+            "level": level_to_json(&self.level),
+            "inputs": self.inputs.iter().map(|(tick, turns)| json!({
+                "tick": tick,
+                "turns": turns.iter().map(|t| t.name()).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn from_json(json: &Value) -> Result<Self, ReplayError> {
+        let level = level_from_json(&json["level"])?;
+
+        let inputs = json["inputs"]
+            .as_array()
+            .ok_or(ReplayError::InvalidFormat)?
+            .iter()
+            .map(|input| {
+                let turns = input["turns"]
+                    .as_array()
+                    .ok_or(ReplayError::InvalidFormat)?
+                    .iter()
+                    .map(parse_direction)
+                    .collect::<Result<_, _>>()?;
+                Ok((parse_u32(&input["tick"])?, turns))
+            })
+            .collect::<Result<Vec<_>, ReplayError>>()?;
+        if !inputs.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return Err(ReplayError::InvalidFormat);
+        }
+
+        Ok(Replay {
+            level,
+            seed: json["seed"].as_u64().ok_or(ReplayError::InvalidFormat)?,
+            ticks: parse_u32(&json["ticks"])?,
+            inputs,
+        })
+    }
+
+    /// Saves the replay into `dir` and returns the name of the created file. Only the newest
+    /// replays are kept, older ones are deleted.
+    pub fn save(&self, dir: &str) -> Result<String, ReplayError> {
+        create_dir_all(dir)?;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let name = format!("replay_{}.json", (date::now() * 1000.0) as u64);
+        let mut file = File::create(format!("{dir}/{name}"))?;
+        file.write_all(self.to_json().to_string().as_bytes())?;
+
+        for old_replay in search_for_replays(dir)?.iter().skip(MAX_SAVED_REPLAYS) {
+            remove_file(format!("{dir}/{old_replay}"))?;
+        }
+        Ok(name)
+    }
+
+    pub fn load(dir: &str, name: &str) -> Result<Self, ReplayError> {
+        let mut file = File::open(format!("{dir}/{name}"))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        Replay::from_json(&serde_json::from_str(&contents)?)
+    }
+}
+
+/// Plays a [`Replay`] back by feeding the recorded turns into a fresh [`Simulation`].
+pub struct Playback<'a> {
+    replay: &'a Replay,
+    pub simulation: Simulation,
+    finished: bool,
+}
+
+impl<'a> Playback<'a> {
+    pub fn new(replay: &'a Replay) -> Self {
+        Playback {
+            replay,
+            simulation: Simulation::new(&replay.level, replay.seed),
+            finished: replay.ticks == 0,
+        }
+    }
+
+    pub fn step(&mut self) -> Vec<Event> {
+        if self.finished {
+            return vec![];
+        }
+        let turns = self.replay.turns_at(self.simulation.tick);
+        let events = self.simulation.step(turns);
+        if self.simulation.tick >= self.replay.ticks
+            || events
+                .iter()
+                .any(|event| matches!(event, Event::Collision { .. }))
+            || events.contains(&Event::LevelComplete)
+        {
+            self.finished = true;
+        }
+        events
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn ticks(&self) -> u32 {
+        self.replay.ticks
+    }
+}
+
+/// Writes the turns of a running game into a file while it is played, so spectators can follow
+/// the game. The file holds a JSON object per line: the first one the level and the seed, each
+/// following one the turns applied on a tick.
+pub struct ReplayStream {
+    file: File,
+}
+
+impl ReplayStream {
+    /// Creates the stream of a new game in `dir`. Stale streams of earlier games are deleted,
+    /// while the streams of games running in other windows are kept.
+    pub fn create(dir: &str, level: &Level, seed: u64) -> Result<Self, ReplayError> {
+        create_dir_all(dir)?;
+        for old_stream in search_for_streams(dir)? {
+            let path = format!("{dir}/{old_stream}");
+            if is_stale(&path) {
+                // Another window may be deleting it at the same time
+                if let Err(err) = remove_file(&path) {
+                    debug!(?err, path, "Failed to delete stale replay stream");
+                }
+            }
+        }
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let name = format!("live_{}.{STREAM_EXTENSION}", (date::now() * 1000.0) as u64);
+        let mut stream = ReplayStream {
+            file: File::create(format!("{dir}/{name}"))?,
+        };
+        stream.write(&json!({"seed": seed, "level": level_to_json(level)}))?;
+        Ok(stream)
+    }
+
+    /// Writes the turns applied on `tick`. Unlike [`Replay::record`], ticks without turns are
+    /// written as well, so spectators see the game advance.
+    pub fn record(&mut self, tick: u32, turns: &[Direction]) -> Result<(), ReplayError> {
+        let turns: Vec<_> = turns.iter().map(|turn| turn.name()).collect();
+        self.write(&json!({"tick": tick, "turns": turns}))
+    }
+
+    fn write(&mut self, json: &Value) -> Result<(), ReplayError> {
+        // A single write per line, so readers never see a line which is only partly written
+        self.file.write_all(format!("{json}\n").as_bytes())?;
+        Ok(())
+    }
+}
+
+fn is_stale(path: &str) -> bool {
+    metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| {
+            modified
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed > STALE_STREAM_AGE)
+        })
+}
+
+/// A line of a [`ReplayStream`].
+#[derive(Debug, PartialEq)]
+pub enum StreamEntry {
+    Start { level: Level, seed: u64 },
+    Tick { tick: u32, turns: Vec<Direction> },
+}
+
+/// Follows the file of a [`ReplayStream`] while it is written.
+pub struct StreamReader {
+    file: File,
+    /// Start of a line which isn't completely written yet.
+    partial_line: String,
+}
+
+impl StreamReader {
+    pub fn open(path: &str) -> Result<Self, ReplayError> {
+        Ok(StreamReader {
+            file: File::open(path)?,
+            partial_line: String::new(),
+        })
+    }
+
+    /// Returns the entries which were written completely since the last call.
+    pub fn read(&mut self) -> Result<Vec<StreamEntry>, ReplayError> {
+        self.file.read_to_string(&mut self.partial_line)?;
+        let Some(end) = self.partial_line.rfind('\n') else {
+            return Ok(vec![]);
+        };
+        let lines: String = self.partial_line.drain(..=end).collect();
+        lines.lines().map(parse_stream_entry).collect()
+    }
+}
+
+fn parse_stream_entry(line: &str) -> Result<StreamEntry, ReplayError> {
+    let json: Value = serde_json::from_str(line)?;
+    if json["level"].is_null() {
+        Ok(StreamEntry::Tick {
+            tick: parse_u32(&json["tick"])?,
+            turns: json["turns"]
+                .as_array()
+                .ok_or(ReplayError::InvalidFormat)?
+                .iter()
+                .map(parse_direction)
+                .collect::<Result<_, _>>()?,
+        })
+    } else {
+        Ok(StreamEntry::Start {
+            level: level_from_json(&json["level"])?,
+            seed: json["seed"].as_u64().ok_or(ReplayError::InvalidFormat)?,
+        })
+    }
+}
+
+/// Returns the names of all replays in `search_path`, newest first.
+pub fn search_for_replays(search_path: &str) -> Result<Vec<String>, ReplayError> {
+    search_by_extension(search_path, "json")
+}
+
+/// Returns the names of all replay streams in `search_path`, newest first.
+pub fn search_for_streams(search_path: &str) -> Result<Vec<String>, ReplayError> {
+    search_by_extension(search_path, STREAM_EXTENSION)
+}
+
+fn search_by_extension(search_path: &str, extension: &str) -> Result<Vec<String>, ReplayError> {
+    let mut files = Vec::new();
+    for path in read_dir(search_path)?.filter_map(Result::ok) {
+        let path = path.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == extension) {
+            if let Some(file_name) = path.file_name() {
+                files.push(file_name.to_string_lossy().into_owned());
+            }
+        }
+    }
+    files.sort_by(|a, b| b.cmp(a));
+    Ok(files)
+}
+
+/// Converts a level into JSON. Unlike [`Level::to_json`], this works for every level, including
+/// the Endless Game.
+pub(crate) fn level_to_json(level: &Level) -> Value {
+    json!({
+        "target_points": level.target_points,
+        "updates_per_second": level.updates_per_second,
+        "width": level.width,
+        "height": level.height,
+        "start_position": level.start_position.map(|p| [p.x, p.y]),
+        "start_direction": level.start_direction.map(Direction::name),
+        "obstacles": level.obstacles.iter().map(|p| [p.x, p.y]).collect::<Vec<_>>(),
+        "borders": level.borders.to_json(),
+    })
+}
+
+pub(crate) fn level_from_json(level: &Value) -> Result<Level, ReplayError> {
+    Ok(Level {
+        target_points: parse_optional(&level["target_points"], parse_i32)?,
+        start_position: parse_optional(&level["start_position"], parse_point)?,
+        start_direction: parse_optional(&level["start_direction"], parse_direction)?,
+        obstacles: level["obstacles"]
+            .as_array()
+            .ok_or(ReplayError::InvalidFormat)?
+            .iter()
+            .map(parse_point)
+            .collect::<Result<_, _>>()?,
+        updates_per_second: parse_i32(&level["updates_per_second"])?,
+        height: parse_i32(&level["height"])?,
+        width: parse_i32(&level["width"])?,
+        borders: Borders::from_json(&level["borders"]).ok_or(ReplayError::InvalidFormat)?,
+    })
+}
+
+pub(crate) fn parse_optional<T>(
+    value: &Value,
+    parse: fn(&Value) -> Result<T, ReplayError>,
+) -> Result<Option<T>, ReplayError> {
+    if value.is_null() {
+        Ok(None)
+    } else {
+        parse(value).map(Some)
+    }
+}
+
+pub(crate) fn parse_i32(value: &Value) -> Result<i32, ReplayError> {
+    i32::try_from(value.as_i64().ok_or(ReplayError::InvalidFormat)?)
+        .map_err(|_| ReplayError::InvalidFormat)
+}
+
+pub(crate) fn parse_u32(value: &Value) -> Result<u32, ReplayError> {
+    u32::try_from(value.as_u64().ok_or(ReplayError::InvalidFormat)?)
+        .map_err(|_| ReplayError::InvalidFormat)
+}
+
+pub(crate) fn parse_point(value: &Value) -> Result<Point2D<i32, i32>, ReplayError> {
+    match value.as_array().map(Vec::as_slice) {
+        Some([x, y]) => Ok(Point2D::new(parse_i32(x)?, parse_i32(y)?)),
+        _ => Err(ReplayError::InvalidFormat),
+    }
+}
+
+pub(crate) fn parse_direction(value: &Value) -> Result<Direction, ReplayError> {
+    value
+        .as_str()
+        .and_then(Direction::from_name)
+        .ok_or(ReplayError::InvalidFormat)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReplayError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("The format of the replay file is not valid")]
+    InvalidFormat,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::borders::Border;
+    use std::thread;
+    use std::time::SystemTime;
+    use tempfile::tempdir;
+
+    fn record_game(level: &Level, seed: u64, turns: &[Vec<Direction>]) -> (Replay, Simulation) {
+        let mut simulation = Simulation::new(level, seed);
+        let mut replay = Replay::new(level, seed);
+        for turn in turns {
+            replay.record(simulation.tick, turn);
+            simulation.step(turn);
+        }
+        (replay, simulation)
+    }
+
+    fn example_turns() -> Vec<Vec<Direction>> {
+        vec![
+            vec![Direction::Up],
+            vec![],
+            vec![Direction::Left, Direction::Down],
+            vec![],
+            vec![],
+            vec![Direction::Right],
+        ]
+    }
+
+    #[test]
+    fn test_playback_reproduces_game() {
+        let (replay, simulation) = record_game(&Level::default(), 99, &example_turns());
+        assert_eq!(6, replay.ticks);
+        assert_eq!(3, replay.inputs.len());
+
+        let mut playback = Playback::new(&replay);
+        while !playback.is_finished() {
+            playback.step();
+        }
+        assert_eq!(simulation.snake, playback.simulation.snake);
+        assert_eq!(simulation.target, playback.simulation.target);
+        assert_eq!(simulation.tick, playback.simulation.tick);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let level = Level {
+            target_points: Some(5),
+            start_position: Some(Point2D::new(3, 4)),
+            start_direction: Some(Direction::Left),
+            obstacles: vec![Point2D::new(0, 0), Point2D::new(5, 1)],
+            borders: Borders {
+                top: Border::Solid,
+                ..Borders::WRAP
+            },
+            ..Level::default()
+        };
+        let (replay, _) = record_game(&level, 7, &example_turns());
+
+        assert_eq!(replay, Replay::from_json(&replay.to_json()).unwrap());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let (replay, _) = record_game(&Level::default(), 3, &example_turns());
+
+        let dir = tempdir().unwrap();
+        let dir_string = dir.path().to_string_lossy().into_owned();
+        let name = replay.save(&dir_string).unwrap();
+
+        assert_eq!(vec![name.clone()], search_for_replays(&dir_string).unwrap());
+        assert_eq!(replay, Replay::load(&dir_string, &name).unwrap());
+    }
+
+    #[test]
+    fn test_stream() {
+        let dir = tempdir().unwrap();
+        let dir_string = dir.path().to_string_lossy().into_owned();
+        // The stale stream of a finished game is deleted, the one of a running game is kept
+        ReplayStream::create(&dir_string, &Level::default(), 1).unwrap();
+        let stale = format!(
+            "{dir_string}/{}",
+            search_for_streams(&dir_string).unwrap()[0]
+        );
+        File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_modified(SystemTime::now() - 2 * STALE_STREAM_AGE)
+            .unwrap();
+        thread::sleep(Duration::from_millis(2));
+        ReplayStream::create(&dir_string, &Level::default(), 3).unwrap();
+        thread::sleep(Duration::from_millis(2));
+        let mut stream = ReplayStream::create(&dir_string, &Level::default(), 2).unwrap();
+        let names = search_for_streams(&dir_string).unwrap();
+        assert_eq!(2, names.len());
+        assert!(!names.iter().any(|name| stale.ends_with(name.as_str())));
+        assert!(search_for_replays(&dir_string).unwrap().is_empty());
+
+        let mut reader = StreamReader::open(&format!("{dir_string}/{}", names[0])).unwrap();
+        assert_eq!(
+            vec![StreamEntry::Start {
+                level: Level::default(),
+                seed: 2
+            }],
+            reader.read().unwrap()
+        );
+        assert!(reader.read().unwrap().is_empty());
+
+        stream.record(0, &[Direction::Up]).unwrap();
+        stream.record(1, &[]).unwrap();
+        // A line which is still being written is returned once it is complete
+        stream.file.write_all(b"{\"tick\": 2, ").unwrap();
+        assert_eq!(
+            vec![
+                StreamEntry::Tick {
+                    tick: 0,
+                    turns: vec![Direction::Up]
+                },
+                StreamEntry::Tick {
+                    tick: 1,
+                    turns: vec![]
+                },
+            ],
+            reader.read().unwrap()
+        );
+        stream.file.write_all(b"\"turns\": [\"left\"]}\n").unwrap();
+        assert_eq!(
+            vec![StreamEntry::Tick {
+                tick: 2,
+                turns: vec![Direction::Left]
+            }],
+            reader.read().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_replay() {
+        let (replay, _) = record_game(&Level::default(), 3, &example_turns());
+        let mut json = replay.to_json();
+        json["inputs"][0]["turns"][0] = json!("sideways");
+
+        assert!(matches!(
+            Replay::from_json(&json).unwrap_err(),
+            ReplayError::InvalidFormat
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+use macroquad::miniquad::date;
+
+/// Seedable pseudo random number generator (SplitMix64). A game which is started with the same
+/// seed and receives the same inputs always plays out identically.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rng {
+    seed: u64,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { seed, state: seed }
+    }
+
+    pub fn from_time() -> Self {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        Rng::new((date::now() * 1000.0) as u64)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in the half-open range `low..high`.
+    pub fn gen_range<T>(&mut self, low: T, high: T) -> T
+    where
+        T: Into<i64> + TryFrom<i64>,
+    {
+        let (low, high) = (low.into(), high.into());
+        assert!(low < high, "gen_range called with an empty range");
+        #[allow(clippy::cast_sign_loss, clippy::cast_possible_wrap)]
+        let offset = (self.next_u64() % (high - low) as u64) as i64;
+        T::try_from(low + offset).unwrap_or_else(|_| unreachable!())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut rng_1 = Rng::new(42);
+        let mut rng_2 = Rng::new(42);
+
+        for _ in 0..100 {
+            assert_eq!(rng_1.gen_range(0, 1000), rng_2.gen_range(0, 1000));
+        }
+        assert_eq!(42, rng_1.seed());
+    }
+
+    #[test]
+    fn gen_range_bounds() {
+        let mut rng = Rng::new(7);
+
+        for _ in 0..1000 {
+            let value = rng.gen_range(-3, 4);
+            assert!((-3..4).contains(&value));
+        }
+        assert_eq!(5u8, rng.gen_range(5u8, 6u8));
+    }
+}
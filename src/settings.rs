@@ -0,0 +1,150 @@
+use crate::menu;
+use crate::storage::{self, StorageError};
+use crate::Context;
+use serde_json::{json, Value};
+use tracing::error;
+
+const STORAGE_KEY: &str = "settings";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Settings {
+    pub show_touch_fields: bool,
+    pub pause_on_focus_loss: bool,
+    /// Whether games write a replay stream, which spectators can follow from another window.
+    pub live_stream: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            show_touch_fields: true,
+            pause_on_focus_loss: true,
+            live_stream: false,
+        }
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+impl Settings {
+    fn options(&self) -> Vec<String> {
+        vec![
+            format!("Touch Fields: {}", on_off(self.show_touch_fields)),
+            format!("Focus Pause: {}", on_off(self.pause_on_focus_loss)),
+            format!("Live Stream: {}", on_off(self.live_stream)),
+            "Back".to_owned(),
+        ]
+    }
+
+    /// Toggles the setting at `index` of [`Settings::options`]. Returns `false` for "Back".
+    fn toggle(&mut self, index: usize) -> bool {
+        match index {
+            0 => self.show_touch_fields = !self.show_touch_fields,
+            1 => self.pause_on_focus_loss = !self.pause_on_focus_loss,
+            2 => self.live_stream = !self.live_stream,
+            _ => return false,
+        }
+        true
+    }
+
+    fn to_json(self) -> Value {
+        json!({
+            "show_touch_fields": self.show_touch_fields,
+            "pause_on_focus_loss": self.pause_on_focus_loss,
+            "live_stream": self.live_stream,
+        })
+    }
+
+    /// Missing values fall back to their defaults, so older settings files stay valid.
+    fn from_json(json: &Value) -> Self {
+        let default = Settings::default();
+        Settings {
+            show_touch_fields: json["show_touch_fields"]
+                .as_bool()
+                .unwrap_or(default.show_touch_fields),
+            pause_on_focus_loss: json["pause_on_focus_loss"]
+                .as_bool()
+                .unwrap_or(default.pause_on_focus_loss),
+            live_stream: json["live_stream"].as_bool().unwrap_or(default.live_stream),
+        }
+    }
+
+    pub fn load() -> Result<Self, SettingsError> {
+        match storage::load(STORAGE_KEY)? {
+            Some(contents) => Ok(Settings::from_json(&serde_json::from_str(&contents)?)),
+            None => Ok(Settings::default()),
+        }
+    }
+
+    pub fn load_or_default() -> Self {
+        Settings::load().unwrap_or_else(|err| {
+            error!(?err, "Failed to load settings");
+            Settings::default()
+        })
+    }
+
+    pub fn save(self) -> Result<(), SettingsError> {
+        storage::save(STORAGE_KEY, &self.to_json().to_string())?;
+        Ok(())
+    }
+}
+
+/// Shows the settings screen on top of `background` and saves every change immediately.
+pub async fn edit_settings(cx: &Context, settings: &mut Settings, background: &dyn Fn()) {
+    while let Some(index) =
+        menu::select_with_background(cx, "Settings", settings.options(), background).await
+    {
+        if !settings.toggle(index) {
+            return;
+        }
+        if let Err(err) = settings.save() {
+            error!(?err, "Failed to save settings");
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SettingsError {
+    #[error(transparent)]
+    StorageError(#[from] StorageError),
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle() {
+        let mut settings = Settings::default();
+
+        assert!(settings.toggle(0));
+        assert!(!settings.show_touch_fields);
+        assert_eq!("Touch Fields: Off", settings.options()[0]);
+        assert!(settings.toggle(1));
+        assert!(!settings.pause_on_focus_loss);
+        assert!(settings.toggle(2));
+        assert!(settings.live_stream);
+        assert_eq!("Live Stream: On", settings.options()[2]);
+        assert!(!settings.toggle(3));
+    }
+
+    #[test]
+    fn test_json() {
+        let settings = Settings {
+            show_touch_fields: false,
+            pause_on_focus_loss: true,
+            live_stream: true,
+        };
+        assert_eq!(settings, Settings::from_json(&settings.to_json()));
+        assert_eq!(Settings::default(), Settings::from_json(&json!({})));
+    }
+}
@@ -0,0 +1,389 @@
+use crate::borders::Borders;
+use crate::level::Level;
+use crate::rng::Rng;
+use crate::snake::{Direction, Snake};
+use crate::target::Target;
+use euclid::Point2D;
+
+/// Game state which is advanced tick by tick, independent of any rendering or input device.
+pub struct Simulation {
+    pub snake: Snake,
+    pub target: Target,
+    pub obstacles: Vec<Point2D<i32, i32>>,
+    pub width: i32,
+    pub height: i32,
+    pub borders: Borders,
+    pub target_points: Option<i32>,
+    pub points: i32,
+    pub tick: u32,
+    pub rng: Rng,
+}
+
+/// What the head of the snake ran into.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum CollisionCause {
+    Wall,
+    Obstacle,
+    SelfBite,
+    /// Another snake in a versus game, head to head or into its body.
+    OtherSnake,
+}
+
+pub const COLLISION_CAUSES: [CollisionCause; 4] = [
+    CollisionCause::Wall,
+    CollisionCause::Obstacle,
+    CollisionCause::SelfBite,
+    CollisionCause::OtherSnake,
+];
+
+impl CollisionCause {
+    /// Identifier of the cause in machine-readable output.
+    pub fn name(self) -> &'static str {
+        match self {
+            CollisionCause::Wall => "wall",
+            CollisionCause::Obstacle => "obstacle",
+            CollisionCause::SelfBite => "self_bite",
+            CollisionCause::OtherSnake => "other_snake",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            CollisionCause::Wall => "Hit the wall",
+            CollisionCause::Obstacle => "Hit an obstacle",
+            CollisionCause::SelfBite => "Bit itself",
+            CollisionCause::OtherSnake => "Ran into another snake",
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Event {
+    TargetHit,
+    LevelComplete,
+    /// The snake collided at `cell`. For a wall, this is the field in front of it.
+    Collision {
+        cause: CollisionCause,
+        cell: Point2D<i32, i32>,
+    },
+}
+
+impl Simulation {
+    /// Creates the simulation of a level. All random decisions are derived from `seed`.
+    pub fn new(level: &Level, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let start_position = level.start_position.or_else(|| {
+            // Keep a random start away from solid edges, so the player has time to react
+            level.borders.has_solid_edge().then(|| {
+                Point2D::new(
+                    rng.gen_range(level.width / 4, level.width - level.width / 4),
+                    rng.gen_range(level.height / 4, level.height - level.height / 4),
+                )
+            })
+        });
+        Simulation {
+            snake: Snake::new(
+                &mut rng,
+                start_position,
+                level.start_direction,
+                level.width,
+                level.height,
+            ),
+            target: Target::new(&mut rng, &level.obstacles, level.width, level.height),
+            obstacles: level.obstacles.clone(),
+            width: level.width,
+            height: level.height,
+            borders: level.borders,
+            target_points: level.target_points,
+            points: 0,
+            tick: 0,
+            rng,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
+    /// Applies the given turns in order and moves the snake by one field.
+    pub fn step(&mut self, turns: &[Direction]) -> Vec<Event> {
+        for direction in turns {
+            self.snake.set_direction(*direction);
+        }
+
+        let snake_hit_target = self.snake_hit_target();
+        self.snake
+            .move_snake(snake_hit_target, self.width, self.height, &self.borders);
+        self.tick += 1;
+
+        let mut events = vec![];
+        if let Some(cause) = self.collision_cause() {
+            let head = self.snake.position[0];
+            let cell = if cause == CollisionCause::Wall {
+                let (dx, dy) = self.snake.current_direction.offset();
+                Point2D::new(head.x - dx, head.y - dy)
+            } else {
+                head
+            };
+            events.push(Event::Collision { cause, cell });
+        } else if snake_hit_target {
+            self.points += 1;
+            events.push(Event::TargetHit);
+            if self
+                .target_points
+                .is_some_and(|target_points| self.points >= target_points)
+            {
+                events.push(Event::LevelComplete);
+            }
+        }
+        events
+    }
+
+    /// Returns what the head of the snake collides with at the moment, if anything.
+    pub fn collision_cause(&self) -> Option<CollisionCause> {
+        if self.snake.is_out_of_bounds(self.width, self.height) {
+            Some(CollisionCause::Wall)
+        } else if self.snake_hit_obstacle() {
+            Some(CollisionCause::Obstacle)
+        } else if self.snake.is_overlapping() {
+            Some(CollisionCause::SelfBite)
+        } else {
+            None
+        }
+    }
+
+    fn snake_hit_target(&mut self) -> bool {
+        if self.snake.position.contains(&self.target.position) {
+            self.target = Target::new(&mut self.rng, &self.obstacles, self.width, self.height);
+            return true;
+        }
+        false
+    }
+
+    fn snake_hit_obstacle(&self) -> bool {
+        self.obstacles.contains(&self.snake.position[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init(
+        snake: Snake,
+        target: Target,
+        obstacles: Vec<Point2D<i32, i32>>,
+        width: i32,
+        height: i32,
+        target_points: Option<i32>,
+    ) -> Simulation {
+        Simulation {
+            snake,
+            target,
+            obstacles,
+            width,
+            height,
+            borders: Borders::WRAP,
+            target_points,
+            points: 0,
+            tick: 0,
+            rng: Rng::new(0),
+        }
+    }
+
+    #[test]
+    fn test_snake_hit_target() {
+        let width = 5;
+        let height = 5;
+
+        let target = Target {
+            position: Point2D::new(2, 2),
+        };
+        let snake = Snake::new(
+            &mut Rng::new(0),
+            Some(Point2D::new(1, 2)),
+            Some(Direction::Right),
+            width,
+            height,
+        );
+        let mut simulation = init(snake, target, vec![], width, height, None);
+
+        assert_eq!(Vec::<Event>::new(), simulation.step(&[]));
+        assert_eq!(vec![Event::TargetHit], simulation.step(&[]));
+        assert_eq!(Vec::<Event>::new(), simulation.step(&[]));
+        assert_eq!(1, simulation.points);
+    }
+
+    #[test]
+    fn test_level_complete() {
+        let width = 5;
+        let height = 5;
+
+        let target = Target {
+            position: Point2D::new(2, 2),
+        };
+        let snake = Snake::new(
+            &mut Rng::new(0),
+            Some(Point2D::new(1, 2)),
+            Some(Direction::Right),
+            width,
+            height,
+        );
+        let mut simulation = init(snake, target, vec![], width, height, Some(1));
+
+        assert_eq!(Vec::<Event>::new(), simulation.step(&[]));
+        assert_eq!(
+            vec![Event::TargetHit, Event::LevelComplete],
+            simulation.step(&[])
+        );
+    }
+
+    #[test]
+    fn test_snake_hit_obstacle() {
+        let width = 5;
+        let height = 5;
+
+        let target = Target {
+            position: Point2D::new(0, 0),
+        };
+        let snake = Snake::new(
+            &mut Rng::new(0),
+            Some(Point2D::new(0, 2)),
+            Some(Direction::Right),
+            width,
+            height,
+        );
+        let obstacle = vec![Point2D::new(2, 2)];
+        let mut simulation = init(snake, target, obstacle, width, height, None);
+
+        assert_eq!(Vec::<Event>::new(), simulation.step(&[]));
+        assert_eq!(
+            vec![Event::Collision {
+                cause: CollisionCause::Obstacle,
+                cell: Point2D::new(2, 2)
+            }],
+            simulation.step(&[])
+        );
+        assert_eq!(Vec::<Event>::new(), simulation.step(&[]));
+    }
+
+    #[test]
+    fn test_snake_hit_solid_border() {
+        let width = 5;
+        let height = 5;
+
+        let target = Target {
+            position: Point2D::new(0, 0),
+        };
+        let snake = Snake::new(
+            &mut Rng::new(0),
+            Some(Point2D::new(3, 2)),
+            Some(Direction::Right),
+            width,
+            height,
+        );
+        let mut simulation = init(snake, target, vec![], width, height, None);
+        simulation.borders = Borders::SOLID;
+
+        assert_eq!(Vec::<Event>::new(), simulation.step(&[]));
+        assert_eq!(
+            vec![Event::Collision {
+                cause: CollisionCause::Wall,
+                cell: Point2D::new(4, 2)
+            }],
+            simulation.step(&[])
+        );
+    }
+
+    #[test]
+    fn test_snake_bites_itself() {
+        let width = 5;
+        let height = 5;
+
+        let target = Target {
+            position: Point2D::new(0, 0),
+        };
+        let mut snake = Snake::new(
+            &mut Rng::new(0),
+            Some(Point2D::new(2, 2)),
+            Some(Direction::Right),
+            width,
+            height,
+        );
+        snake.position = vec![
+            Point2D::new(2, 2),
+            Point2D::new(1, 2),
+            Point2D::new(1, 3),
+            Point2D::new(2, 3),
+            Point2D::new(3, 3),
+        ];
+        let mut simulation = init(snake, target, vec![], width, height, None);
+
+        assert_eq!(
+            vec![Event::Collision {
+                cause: CollisionCause::SelfBite,
+                cell: Point2D::new(2, 3)
+            }],
+            simulation.step(&[Direction::Down])
+        );
+    }
+
+    #[test]
+    fn test_random_start_away_from_solid_borders() {
+        let level = Level {
+            borders: Borders::SOLID,
+            ..Level::default()
+        };
+        for seed in 0..100 {
+            let head = Simulation::new(&level, seed).snake.position[0];
+            assert!((5..15).contains(&head.x) && (5..15).contains(&head.y));
+        }
+    }
+
+    #[test]
+    fn test_step_applies_turns() {
+        let width = 5;
+        let height = 5;
+
+        let target = Target {
+            position: Point2D::new(0, 0),
+        };
+        let snake = Snake::new(
+            &mut Rng::new(0),
+            Some(Point2D::new(2, 2)),
+            Some(Direction::Right),
+            width,
+            height,
+        );
+        let mut simulation = init(snake, target, vec![], width, height, None);
+
+        simulation.step(&[Direction::Down]);
+        assert_eq!(Point2D::new(2, 3), simulation.snake.position[0]);
+        simulation.step(&[Direction::Up, Direction::Left]);
+        assert_eq!(Point2D::new(1, 3), simulation.snake.position[0]);
+    }
+
+    #[test]
+    fn test_same_seed_same_game() {
+        let level = Level::default();
+        let turns = [
+            vec![Direction::Up],
+            vec![],
+            vec![Direction::Left],
+            vec![Direction::Down],
+            vec![],
+        ];
+
+        let mut simulation_1 = Simulation::new(&level, 1234);
+        let mut simulation_2 = Simulation::new(&level, 1234);
+        assert_eq!(simulation_1.snake, simulation_2.snake);
+        assert_eq!(simulation_1.target, simulation_2.target);
+
+        for turn in &turns {
+            assert_eq!(simulation_1.step(turn), simulation_2.step(turn));
+            assert_eq!(simulation_1.snake, simulation_2.snake);
+            assert_eq!(simulation_1.target, simulation_2.target);
+        }
+        assert_eq!(1234, simulation_1.seed());
+    }
+}
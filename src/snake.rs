@@ -1,10 +1,13 @@
 use crate::{
-    constants::{SNAKE_HEAD_COLOR, SNAKE_TAIL_COLOR, SNAKE_WIDTH},
+    borders::{Border, Borders},
+    constants::{MAX_QUEUED_TURNS, SNAKE_HEAD_COLOR, SNAKE_TAIL_COLOR, SNAKE_WIDTH},
     graphic_utils::render_scaled_square,
+    rng::Rng,
 };
 use euclid::{approxord::max, Point2D};
-use macroquad::rand::gen_range;
+use macroquad::color::Color;
 use num_enum::TryFromPrimitive;
+use std::collections::VecDeque;
 use std::ops::Range;
 
 const NUMBER_OF_DIRECTIONS: u8 = 4;
@@ -17,21 +20,64 @@ pub enum Direction {
     Right,
 }
 
-#[derive(Debug, PartialEq)]
+impl Direction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "up" => Some(Direction::Up),
+            "down" => Some(Direction::Down),
+            "left" => Some(Direction::Left),
+            "right" => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    /// Change of the position when moving one field into this direction.
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Snake {
     pub position: Vec<Point2D<i32, i32>>,
-    pub direction: Direction,
     pub current_direction: Direction,
+    /// Turns which have not been applied yet. One of them is consumed per move.
+    pub queued_turns: VecDeque<Direction>,
 }
 
 impl Snake {
     pub fn new(
+        rng: &mut Rng,
         start_position: Option<Point2D<i32, i32>>,
         start_direction: Option<Direction>,
         width: i32,
         height: i32,
     ) -> Self {
         Snake::new_inner(
+            rng,
             start_position,
             start_direction,
             0..width,
@@ -41,6 +87,7 @@ impl Snake {
     }
 
     fn new_inner(
+        rng: &mut Rng,
         start_position: Option<Point2D<i32, i32>>,
         start_direction: Option<Direction>,
         width: Range<i32>,
@@ -49,51 +96,50 @@ impl Snake {
     ) -> Self {
         let position = start_position.unwrap_or_else(|| {
             Point2D::new(
-                gen_range(width.start, width.end),
-                gen_range(height.start, height.end),
+                rng.gen_range(width.start, width.end),
+                rng.gen_range(height.start, height.end),
             )
         });
         let direction = start_direction.unwrap_or_else(|| {
-            let direction_value = gen_range(direction_range.start, direction_range.end);
+            let direction_value = rng.gen_range(direction_range.start, direction_range.end);
             Direction::try_from(direction_value).unwrap()
         });
 
         Snake {
             position: vec![position],
-            direction,
             current_direction: direction,
+            queued_turns: VecDeque::new(),
         }
     }
 
+    /// Queues a turn. Turns which don't change the direction or reverse it are ignored, compared
+    /// to the last queued direction, as well as turns exceeding [`MAX_QUEUED_TURNS`].
     pub fn set_direction(&mut self, direction: Direction) {
-        match direction {
-            Direction::Up => {
-                if self.current_direction != Direction::Down {
-                    self.direction = Direction::Up;
-                }
-            }
-            Direction::Down => {
-                if self.current_direction != Direction::Up {
-                    self.direction = Direction::Down;
-                }
-            }
-            Direction::Left => {
-                if self.current_direction != Direction::Right {
-                    self.direction = Direction::Left;
-                }
-            }
-            Direction::Right => {
-                if self.current_direction != Direction::Left {
-                    self.direction = Direction::Right;
-                }
-            }
+        let last_direction = self
+            .queued_turns
+            .back()
+            .copied()
+            .unwrap_or(self.current_direction);
+        if direction != last_direction
+            && direction != last_direction.opposite()
+            && self.queued_turns.len() < MAX_QUEUED_TURNS
+        {
+            self.queued_turns.push_back(direction);
         }
     }
 
-    pub fn move_snake(&mut self, snake_hit_target: bool, width: i32, height: i32) {
-        self.current_direction = self.direction;
+    pub fn move_snake(
+        &mut self,
+        snake_hit_target: bool,
+        width: i32,
+        height: i32,
+        borders: &Borders,
+    ) {
+        if let Some(direction) = self.queued_turns.pop_front() {
+            self.current_direction = direction;
+        }
         self.propagate_position(snake_hit_target);
-        self.adjust_head(width, height);
+        self.adjust_head(width, height, borders);
     }
 
     fn propagate_position(&mut self, keep_tail: bool) {
@@ -106,19 +152,34 @@ impl Snake {
         }
     }
 
-    fn adjust_head(&mut self, width: i32, height: i32) {
-        match self.direction {
+    /// Moves the head by one field. At wrapping edges the head enters the map again at the
+    /// opposite edge, at solid edges it leaves the map.
+    fn adjust_head(&mut self, width: i32, height: i32, borders: &Borders) {
+        let head = &mut self.position[0];
+        match self.current_direction {
             Direction::Up => {
-                self.position[0].y = (self.position[0].y + height - 1) % height;
+                head.y -= 1;
+                if head.y < 0 && borders.top == Border::Wrap {
+                    head.y = height - 1;
+                }
             }
             Direction::Down => {
-                self.position[0].y = (self.position[0].y + 1) % height;
+                head.y += 1;
+                if head.y >= height && borders.bottom == Border::Wrap {
+                    head.y = 0;
+                }
             }
             Direction::Left => {
-                self.position[0].x = (self.position[0].x + width - 1) % width;
+                head.x -= 1;
+                if head.x < 0 && borders.left == Border::Wrap {
+                    head.x = width - 1;
+                }
             }
             Direction::Right => {
-                self.position[0].x = (self.position[0].x + 1) % width;
+                head.x += 1;
+                if head.x >= width && borders.right == Border::Wrap {
+                    head.x = 0;
+                }
             }
         }
     }
@@ -127,11 +188,21 @@ impl Snake {
         self.position[1..].contains(&self.position[0])
     }
 
-    pub fn render(&mut self, scaling: (f32, f32)) {
-        render_scaled_square(SNAKE_HEAD_COLOR, self.position[0], SNAKE_WIDTH, scaling);
+    pub fn is_out_of_bounds(&self, width: i32, height: i32) -> bool {
+        let head = self.position[0];
+        head.x < 0 || head.y < 0 || head.x >= width || head.y >= height
+    }
+
+    pub fn render(&self, scaling: (f32, f32)) {
+        self.render_with_colors(scaling, SNAKE_HEAD_COLOR, SNAKE_TAIL_COLOR);
+    }
+
+    /// Renders the snake in other colors, to tell several snakes apart.
+    pub fn render_with_colors(&self, scaling: (f32, f32), head_color: Color, tail_color: Color) {
+        render_scaled_square(head_color, self.position[0], SNAKE_WIDTH, scaling);
 
         for (i, position) in self.position[1..].iter().enumerate() {
-            let mut color = SNAKE_TAIL_COLOR;
+            let mut color = tail_color;
             color.a = max(1.0 - i as f32 * 0.075, 0.25);
             render_scaled_square(color, *position, SNAKE_WIDTH, scaling);
         }
@@ -149,10 +220,11 @@ mod tests {
 
         let expected_snake = Snake {
             position: vec![start_position],
-            direction: start_direction,
             current_direction: start_direction,
+            queued_turns: VecDeque::new(),
         };
         let snake = Snake::new_inner(
+            &mut Rng::new(0),
             Some(start_position),
             Some(start_direction),
             Range { start: 0, end: 10 },
@@ -172,10 +244,11 @@ mod tests {
 
         let expected_snake = Snake {
             position: vec![start_position],
-            direction: start_direction,
             current_direction: start_direction,
+            queued_turns: VecDeque::new(),
         };
         let snake = Snake::new_inner(
+            &mut Rng::new(0),
             None,
             Some(start_direction),
             Range {
@@ -201,10 +274,11 @@ mod tests {
 
         let expected_snake = Snake {
             position: vec![start_position],
-            direction: start_direction,
             current_direction: start_direction,
+            queued_turns: VecDeque::new(),
         };
         let snake = Snake::new_inner(
+            &mut Rng::new(0),
             Some(start_position),
             None,
             Range { start: 0, end: 10 },
@@ -226,17 +300,21 @@ mod tests {
     ) {
         let mut snake = Snake {
             position: positions[0].to_vec(),
-            direction: directions[0],
             current_direction: directions[0],
+            queued_turns: VecDeque::new(),
         };
 
         for i in 1..directions.len() {
-            snake.move_snake(target_hit[i - 1], width, height);
+            snake.move_snake(target_hit[i - 1], width, height, &Borders::WRAP);
             snake.set_direction(directions[i]);
             let expected_snake = Snake {
                 position: positions[i].to_vec(),
-                direction: directions[i],
                 current_direction: directions[i - 1],
+                queued_turns: if directions[i] == directions[i - 1] {
+                    VecDeque::new()
+                } else {
+                    VecDeque::from([directions[i]])
+                },
             };
             assert_eq!(snake, expected_snake);
         }
@@ -350,6 +428,108 @@ mod tests {
         move_snake(&positions, &directions, &target_hit, width, height)
     }
 
+    #[test_case::test_case(Direction::Up, Point2D::new(1, 0), Border::Solid, true)]
+    #[test_case::test_case(Direction::Down, Point2D::new(1, 2), Border::Solid, true)]
+    #[test_case::test_case(Direction::Left, Point2D::new(0, 1), Border::Solid, true)]
+    #[test_case::test_case(Direction::Right, Point2D::new(2, 1), Border::Solid, true)]
+    #[test_case::test_case(Direction::Up, Point2D::new(1, 0), Border::Wrap, false)]
+    #[test_case::test_case(Direction::Right, Point2D::new(2, 1), Border::Wrap, false)]
+    fn move_snake_into_border(
+        direction: Direction,
+        start_position: Point2D<i32, i32>,
+        border: Border,
+        expected_out_of_bounds: bool,
+    ) {
+        let mut snake = Snake {
+            position: vec![start_position],
+            current_direction: direction,
+            queued_turns: VecDeque::new(),
+        };
+        snake.move_snake(false, 3, 3, &Borders::all(border));
+        assert_eq!(expected_out_of_bounds, snake.is_out_of_bounds(3, 3));
+    }
+
+    #[test]
+    fn move_snake_per_edge_borders() {
+        let borders = Borders {
+            left: Border::Solid,
+            ..Borders::WRAP
+        };
+        let mut snake = Snake {
+            position: vec![Point2D::new(2, 1)],
+            current_direction: Direction::Right,
+            queued_turns: VecDeque::new(),
+        };
+        snake.move_snake(false, 3, 3, &borders);
+        assert_eq!(Point2D::new(0, 1), snake.position[0]);
+
+        snake.set_direction(Direction::Down);
+        snake.move_snake(false, 3, 3, &borders);
+        snake.set_direction(Direction::Left);
+        snake.move_snake(false, 3, 3, &borders);
+        assert!(snake.is_out_of_bounds(3, 3));
+    }
+
+    fn move_with_turns(snake: &mut Snake, turns: &[Direction]) {
+        for turn in turns {
+            snake.set_direction(*turn);
+        }
+        snake.move_snake(false, 10, 10, &Borders::WRAP);
+    }
+
+    #[test]
+    fn queued_turns_are_applied_one_per_move() {
+        let mut snake = Snake {
+            position: vec![Point2D::new(5, 5)],
+            current_direction: Direction::Right,
+            queued_turns: VecDeque::new(),
+        };
+
+        move_with_turns(&mut snake, &[Direction::Up, Direction::Left]);
+        assert_eq!(Point2D::new(5, 4), snake.position[0]);
+        assert_eq!(VecDeque::from([Direction::Left]), snake.queued_turns);
+
+        move_with_turns(&mut snake, &[]);
+        assert_eq!(Point2D::new(4, 4), snake.position[0]);
+        assert_eq!(Direction::Left, snake.current_direction);
+        assert!(snake.queued_turns.is_empty());
+    }
+
+    #[test_case::test_case(Direction::Right, &[Direction::Left], &[]; "reverse")]
+    #[test_case::test_case(Direction::Right, &[Direction::Right], &[]; "same direction")]
+    #[test_case::test_case(
+        Direction::Right,
+        &[Direction::Up, Direction::Up, Direction::Down],
+        &[Direction::Up];
+        "reverse of queued turn"
+    )]
+    #[test_case::test_case(
+        Direction::Right,
+        &[Direction::Up, Direction::Left],
+        &[Direction::Up, Direction::Left];
+        "u-turn in two steps"
+    )]
+    #[test_case::test_case(
+        Direction::Right,
+        &[Direction::Up, Direction::Left, Direction::Down, Direction::Right, Direction::Up],
+        &[Direction::Up, Direction::Left, Direction::Down];
+        "bounded"
+    )]
+    fn queue_turns(start_direction: Direction, turns: &[Direction], expected: &[Direction]) {
+        let mut snake = Snake {
+            position: vec![Point2D::new(5, 5)],
+            current_direction: start_direction,
+            queued_turns: VecDeque::new(),
+        };
+        for turn in turns {
+            snake.set_direction(*turn);
+        }
+        assert_eq!(
+            expected.iter().copied().collect::<VecDeque<_>>(),
+            snake.queued_turns
+        );
+    }
+
     #[test]
     fn overlapping() {
         let expected_snake = Snake {
@@ -360,8 +540,8 @@ mod tests {
                 Point2D::new(2, 0),
                 Point2D::new(3, 0),
             ],
-            direction: Direction::Up,
             current_direction: Direction::Up,
+            queued_turns: VecDeque::new(),
         };
         assert!(expected_snake.is_overlapping());
     }
@@ -375,8 +555,8 @@ mod tests {
                 Point2D::new(2, 1),
                 Point2D::new(2, 0),
             ],
-            direction: Direction::Up,
             current_direction: Direction::Up,
+            queued_turns: VecDeque::new(),
         };
         assert!(!expected_snake.is_overlapping());
     }
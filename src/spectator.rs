@@ -0,0 +1,605 @@
+//! Read-only view of a running game for an audience, e.g. to project a match onto a screen. The
+//! game is followed through a [`Feed`], either the replay stream of a local game or a server.
+
+use crate::constants::{
+    CAMERA_MIN_VISIBLE_FIELDS, CAMERA_PAN_SPEED, CAMERA_ZOOM_STEP, OBSTACLE_COLOR, PLAYER_COLORS,
+    POINTS_TEXT_SIZE, SNAKE_HEAD_COLOR, STREAM_SEARCH_INTERVAL, TABLE_TEXT_SIZE,
+};
+use crate::game::render_versus_board;
+use crate::graphic_utils::{render_error_message, render_scoreboard, render_text};
+use crate::replay::{search_for_streams, ReplayError, StreamEntry, StreamReader};
+use crate::simulation::{Event, Simulation};
+use crate::versus::{Player, Versus};
+use crate::Context;
+use euclid::Point2D;
+use macroquad::camera::{set_camera, set_default_camera, Camera2D};
+use macroquad::color::Color;
+use macroquad::input::{get_keys_down, get_keys_pressed, mouse_wheel, KeyCode};
+use macroquad::math::{vec2, Rect};
+use macroquad::miniquad::date;
+use macroquad::time::get_frame_time;
+use macroquad::window::{clear_background, next_frame, screen_height, screen_width};
+use std::collections::HashSet;
+use tracing::warn;
+
+const CONTROLS: &str =
+    "Arrows: Move - +/-: Zoom - Tab: Follow Snake - Home: Whole Map - Escape: Leave";
+
+/// Source of the game a spectator watches.
+pub trait Feed {
+    /// Takes in what happened in the game since the last call. Returns why the game can't be
+    /// followed any longer, e.g. because the connection was lost.
+    fn update(&mut self) -> Result<(), String>;
+
+    /// The map with all snakes, once there is something to show.
+    fn board(&self) -> Option<&Versus>;
+
+    /// Names of the snakes of [`Feed::board`], in the same order.
+    fn names(&self) -> Vec<String>;
+
+    fn leaderboard(&self) -> Vec<LeaderboardEntry>;
+
+    /// What is going on, e.g. the current round.
+    fn status(&self) -> String;
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub points: i32,
+    /// Rounds won, if the game is played in rounds.
+    pub wins: Option<u32>,
+    /// Index of the entry's snake on the board, if it has one.
+    pub snake: Option<usize>,
+    pub alive: bool,
+}
+
+/// Sorts the entries by wins, then by points.
+pub fn rank(entries: &mut [LeaderboardEntry]) {
+    entries.sort_by(|a, b| {
+        b.wins
+            .cmp(&a.wins)
+            .then(b.points.cmp(&a.points))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+/// Lines of the leaderboard, each in the color of the entry's snake.
+fn leaderboard_lines(mut entries: Vec<LeaderboardEntry>) -> Vec<(String, Color)> {
+    rank(&mut entries);
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let wins = match entry.wins {
+                Some(1) => " - 1 win".to_owned(),
+                Some(wins) => format!(" - {wins} wins"),
+                None => String::new(),
+            };
+            let out = if entry.snake.is_some() && !entry.alive {
+                " (out)"
+            } else {
+                ""
+            };
+            let color = entry.snake.map_or(OBSTACLE_COLOR, |snake| {
+                PLAYER_COLORS[snake % PLAYER_COLORS.len()].0
+            });
+            (
+                format!("{}. {}: {}{wins}{out}", i + 1, entry.name, entry.points),
+                color,
+            )
+        })
+        .collect()
+}
+
+/// Part of the map the spectator sees. Without zooming in, the whole map is visible.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Camera {
+    /// Width and height of the map in fields.
+    map: (f32, f32),
+    /// Point of the map in the middle of the view, in fields.
+    pub center: Point2D<f32, f32>,
+    /// 1 shows the whole map, 2 half of its width and height and so on.
+    pub zoom: f32,
+    /// Snake the camera keeps in the middle of the view.
+    pub follow: Option<usize>,
+}
+
+impl Camera {
+    pub fn new(board: &Versus) -> Self {
+        let map = (board.width as f32, board.height as f32);
+        Camera {
+            map,
+            center: Point2D::new(map.0 / 2.0, map.1 / 2.0),
+            zoom: 1.0,
+            follow: None,
+        }
+    }
+
+    /// Whether the camera was made for a map of the size of `board`.
+    fn fits(&self, board: &Versus) -> bool {
+        self.map == (board.width as f32, board.height as f32)
+    }
+
+    /// Moves the view by `offset` fields and stops following a snake.
+    pub fn pan(&mut self, offset: (f32, f32)) {
+        self.follow = None;
+        self.center = Point2D::new(self.center.x + offset.0, self.center.y + offset.1);
+        self.clamp();
+    }
+
+    pub fn zoom_by(&mut self, factor: f32) {
+        let max_zoom = (self.map.0.max(self.map.1) / CAMERA_MIN_VISIBLE_FIELDS).max(1.0);
+        self.zoom = (self.zoom * factor).clamp(1.0, max_zoom);
+        self.clamp();
+    }
+
+    /// Follows the next snake which is alive, or none after the last one.
+    pub fn follow_next(&mut self, board: &Versus) {
+        let first = self.follow.map_or(0, |snake| snake + 1);
+        self.follow = (first..board.players.len()).find(|snake| board.players[*snake].alive);
+    }
+
+    /// Shows the whole map again.
+    pub fn reset(&mut self) {
+        self.center = Point2D::new(self.map.0 / 2.0, self.map.1 / 2.0);
+        self.zoom = 1.0;
+        self.follow = None;
+    }
+
+    /// Keeps the followed snake in view. The camera stops following snakes which collided.
+    pub fn update(&mut self, board: &Versus) {
+        let Some(snake) = self.follow else {
+            return;
+        };
+        match board.players.get(snake).filter(|player| player.alive) {
+            Some(player) => {
+                let head = player.snake.position[0];
+                self.center = Point2D::new(head.x as f32 + 0.5, head.y as f32 + 0.5);
+                self.clamp();
+            }
+            None => self.follow = None,
+        }
+    }
+
+    /// Top left corner and size of the visible part of the map, in fields.
+    pub fn visible_area(&self) -> (Point2D<f32, f32>, (f32, f32)) {
+        let size = (self.map.0 / self.zoom, self.map.1 / self.zoom);
+        (
+            Point2D::new(self.center.x - size.0 / 2.0, self.center.y - size.1 / 2.0),
+            size,
+        )
+    }
+
+    /// Keeps the view inside the map.
+    fn clamp(&mut self) {
+        let (_, size) = self.visible_area();
+        self.center = Point2D::new(
+            self.center.x.clamp(size.0 / 2.0, self.map.0 - size.0 / 2.0),
+            self.center.y.clamp(size.1 / 2.0, self.map.1 - size.1 / 2.0),
+        );
+    }
+
+    fn handle_keys(
+        &mut self,
+        keys_down: &HashSet<KeyCode>,
+        keys_pressed: &HashSet<KeyCode>,
+        frame_time: f32,
+        board: &Versus,
+    ) {
+        let speed = CAMERA_PAN_SPEED / self.zoom * frame_time;
+        let direction = |keys: [KeyCode; 2]| {
+            if keys.iter().any(|key| keys_down.contains(key)) {
+                speed
+            } else {
+                0.0
+            }
+        };
+        let offset = (
+            direction([KeyCode::Right, KeyCode::D]) - direction([KeyCode::Left, KeyCode::A]),
+            direction([KeyCode::Down, KeyCode::S]) - direction([KeyCode::Up, KeyCode::W]),
+        );
+        if offset != (0.0, 0.0) {
+            self.pan(offset);
+        }
+
+        for key in keys_pressed {
+            match key {
+                KeyCode::Equal | KeyCode::KpAdd => self.zoom_by(CAMERA_ZOOM_STEP),
+                KeyCode::Minus | KeyCode::KpSubtract => self.zoom_by(1.0 / CAMERA_ZOOM_STEP),
+                KeyCode::Tab => self.follow_next(board),
+                KeyCode::Home | KeyCode::Key0 => self.reset(),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Follows the local games through the replay streams they write. Newer games are picked up
+/// once they start.
+pub struct ReplayFeed {
+    dir: String,
+    /// Name and reader of the followed stream.
+    stream: Option<(String, StreamReader)>,
+    game: Option<LiveGame>,
+    /// Points of the games followed so far, the current game last.
+    points: Vec<i32>,
+    last_search: Option<f64>,
+}
+
+struct LiveGame {
+    simulation: Simulation,
+    board: Versus,
+    over: bool,
+}
+
+impl LiveGame {
+    fn board(simulation: &Simulation, alive: bool) -> Versus {
+        Versus {
+            players: vec![Player {
+                snake: simulation.snake.clone(),
+                points: simulation.points,
+                alive,
+            }],
+            target: simulation.target.clone(),
+            obstacles: simulation.obstacles.clone(),
+            width: simulation.width,
+            height: simulation.height,
+            borders: simulation.borders,
+            target_points: simulation.target_points,
+            tick: simulation.tick,
+            round_over: !alive,
+            rng: simulation.rng.clone(),
+        }
+    }
+}
+
+impl ReplayFeed {
+    /// Follows the streams written into `dir`.
+    pub fn new(dir: &str) -> Self {
+        ReplayFeed {
+            dir: dir.to_owned(),
+            stream: None,
+            game: None,
+            points: vec![],
+            last_search: None,
+        }
+    }
+
+    /// Switches to the newest stream, if it isn't followed already.
+    fn search(&mut self) -> Result<(), ReplayError> {
+        let newest = match search_for_streams(&self.dir) {
+            Ok(names) => names.into_iter().next(),
+            // No game was played yet
+            Err(ReplayError::IoError(err)) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        let Some(newest) = newest else {
+            return Ok(());
+        };
+        if self
+            .stream
+            .as_ref()
+            .is_some_and(|(name, _)| *name == newest)
+        {
+            return Ok(());
+        }
+        match StreamReader::open(&format!("{}/{newest}", self.dir)) {
+            Ok(reader) => self.stream = Some((newest, reader)),
+            // The stream may have been replaced by the next game in the meantime
+            Err(err) => warn!(?err, "Failed to open replay stream"),
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, entry: StreamEntry) {
+        match entry {
+            StreamEntry::Start { level, seed } => {
+                let simulation = Simulation::new(&level, seed);
+                self.game = Some(LiveGame {
+                    board: LiveGame::board(&simulation, true),
+                    simulation,
+                    over: false,
+                });
+                self.points.push(0);
+            }
+            StreamEntry::Tick { tick, turns } => {
+                let Some(game) = &mut self.game else {
+                    return;
+                };
+                if game.over || tick != game.simulation.tick {
+                    return;
+                }
+                let events = game.simulation.step(&turns);
+                let collided = events
+                    .iter()
+                    .any(|event| matches!(event, Event::Collision { .. }));
+                game.over = collided || events.contains(&Event::LevelComplete);
+                game.board = LiveGame::board(&game.simulation, !collided);
+                if let Some(points) = self.points.last_mut() {
+                    *points = game.simulation.points;
+                }
+            }
+        }
+    }
+}
+
+impl Feed for ReplayFeed {
+    fn update(&mut self) -> Result<(), String> {
+        let now = date::now();
+        if self
+            .last_search
+            .is_none_or(|last_search| now - last_search >= STREAM_SEARCH_INTERVAL)
+        {
+            self.last_search = Some(now);
+            self.search().map_err(|err| err.to_string())?;
+        }
+        let Some((_, reader)) = &mut self.stream else {
+            return Ok(());
+        };
+        for entry in reader.read().map_err(|err| err.to_string())? {
+            self.apply(entry);
+        }
+        Ok(())
+    }
+
+    fn board(&self) -> Option<&Versus> {
+        self.game.as_ref().map(|game| &game.board)
+    }
+
+    fn names(&self) -> Vec<String> {
+        vec![format!("Game {}", self.points.len())]
+    }
+
+    fn leaderboard(&self) -> Vec<LeaderboardEntry> {
+        let current = self.game.as_ref().map(|_| self.points.len() - 1);
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(i, points)| LeaderboardEntry {
+                name: format!("Game {}", i + 1),
+                points: *points,
+                wins: None,
+                snake: (Some(i) == current).then_some(0),
+                alive: self.game.as_ref().is_some_and(|game| !game.over),
+            })
+            .collect()
+    }
+
+    fn status(&self) -> String {
+        match &self.game {
+            Some(game) if game.over => format!("Game {} is over", self.points.len()),
+            Some(game) => format!("Game {} - Tick {}", self.points.len(), game.simulation.tick),
+            None => "Waiting for a game".to_owned(),
+        }
+    }
+}
+
+/// Shows the game of `feed` until Escape is pressed or the feed fails.
+pub async fn spectate(cx: &Context, feed: &mut dyn Feed) {
+    // The selection in the menu must not end the view right away
+    next_frame().await;
+    let mut camera: Option<Camera> = None;
+
+    loop {
+        if let Err(err) = feed.update() {
+            render_error_message(&err).await;
+            return;
+        }
+        let keys_pressed = get_keys_pressed();
+        if keys_pressed.contains(&KeyCode::Escape) {
+            return;
+        }
+
+        if let Some(board) = feed.board() {
+            let camera = match &mut camera {
+                Some(camera) if camera.fits(board) => camera,
+                _ => camera.insert(Camera::new(board)),
+            };
+            camera.handle_keys(&get_keys_down(), &keys_pressed, get_frame_time(), board);
+            let (_, wheel) = mouse_wheel();
+            if wheel != 0.0 {
+                camera.zoom_by(if wheel > 0.0 {
+                    CAMERA_ZOOM_STEP
+                } else {
+                    1.0 / CAMERA_ZOOM_STEP
+                });
+            }
+            camera.update(board);
+            render_board(board, &feed.names(), camera, cx);
+        } else {
+            clear_background(macroquad::color::BLACK);
+        }
+
+        render_scoreboard(&leaderboard_lines(feed.leaderboard()), Some(&cx.font));
+        render_text(
+            &feed.status(),
+            Point2D::new(screen_width() / 2.0, screen_height() * 0.05),
+            Some(&cx.font),
+            POINTS_TEXT_SIZE,
+            SNAKE_HEAD_COLOR,
+        );
+        render_text(
+            CONTROLS,
+            Point2D::new(screen_width() / 2.0, screen_height() * 0.97),
+            Some(&cx.font),
+            TABLE_TEXT_SIZE,
+            OBSTACLE_COLOR,
+        );
+        next_frame().await;
+    }
+}
+
+/// Renders the visible part of the board with the name of every snake next to its head.
+fn render_board(board: &Versus, names: &[String], camera: &Camera, cx: &Context) {
+    // The board is rendered as if it filled the screen, the camera shows the visible part of it
+    let scaling = (
+        screen_width() / board.width as f32,
+        screen_height() / board.height as f32,
+    );
+    let (origin, size) = camera.visible_area();
+    let camera_2d = Camera2D::from_display_rect(Rect::new(
+        origin.x * scaling.0,
+        origin.y * scaling.1,
+        size.0 * scaling.0,
+        size.1 * scaling.1,
+    ));
+    set_camera(&camera_2d);
+    render_versus_board(board);
+    set_default_camera();
+
+    for (i, (player, name)) in board.players.iter().zip(names).enumerate() {
+        if !player.alive {
+            continue;
+        }
+        let head = player.snake.position[0];
+        let position = camera_2d.world_to_screen(vec2(
+            (head.x as f32 + 0.5) * scaling.0,
+            head.y as f32 * scaling.1,
+        ));
+        render_text(
+            name,
+            Point2D::new(position.x, position.y - f32::from(TABLE_TEXT_SIZE)),
+            Some(&cx.font),
+            TABLE_TEXT_SIZE,
+            PLAYER_COLORS[i % PLAYER_COLORS.len()].0,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+    use crate::replay::ReplayStream;
+    use crate::snake::Direction;
+    use tempfile::tempdir;
+
+    fn entry(name: &str, points: i32, wins: Option<u32>, snake: Option<usize>) -> LeaderboardEntry {
+        LeaderboardEntry {
+            name: name.to_owned(),
+            points,
+            wins,
+            snake,
+            alive: false,
+        }
+    }
+
+    #[test]
+    fn test_leaderboard_lines() {
+        let lines = leaderboard_lines(vec![
+            entry("Ada", 5, Some(1), Some(0)),
+            entry("Bob", 2, Some(2), Some(1)),
+            entry("Eve", 7, Some(1), None),
+        ]);
+        let texts: Vec<&str> = lines.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(
+            vec![
+                "1. Bob: 2 - 2 wins (out)",
+                "2. Eve: 7 - 1 win",
+                "3. Ada: 5 - 1 win (out)"
+            ],
+            texts
+        );
+        assert_eq!(PLAYER_COLORS[1].0, lines[0].1);
+        assert_eq!(OBSTACLE_COLOR, lines[1].1);
+    }
+
+    fn board(width: i32, height: i32) -> Versus {
+        Versus::new(
+            &Level {
+                width,
+                height,
+                ..Level::default()
+            },
+            2,
+            1,
+        )
+    }
+
+    #[test]
+    fn test_camera_stays_on_map() {
+        let board = board(80, 40);
+        let mut camera = Camera::new(&board);
+        camera.pan((10.0, 0.0));
+        assert_eq!(Point2D::new(40.0, 20.0), camera.center);
+
+        camera.zoom_by(4.0);
+        assert_eq!(4.0, camera.zoom);
+        assert_eq!(
+            (Point2D::new(30.0, 15.0), (20.0, 10.0)),
+            camera.visible_area()
+        );
+        camera.pan((-100.0, 100.0));
+        assert_eq!(
+            (Point2D::new(0.0, 30.0), (20.0, 10.0)),
+            camera.visible_area()
+        );
+
+        camera.zoom_by(100.0);
+        assert_eq!(10.0, camera.zoom);
+        camera.zoom_by(0.01);
+        assert_eq!(1.0, camera.zoom);
+        assert_eq!(Point2D::new(40.0, 20.0), camera.center);
+    }
+
+    #[test]
+    fn test_camera_follows_snakes() {
+        let mut board = board(80, 80);
+        let mut camera = Camera::new(&board);
+        camera.zoom_by(4.0);
+        camera.follow_next(&board);
+        assert_eq!(Some(0), camera.follow);
+        camera.update(&board);
+        let head = board.players[0].snake.position[0];
+        assert_eq!(
+            Point2D::new(head.x as f32 + 0.5, head.y as f32 + 0.5),
+            camera.center
+        );
+
+        board.players[1].alive = false;
+        camera.follow_next(&board);
+        assert_eq!(None, camera.follow);
+        camera.follow_next(&board);
+        board.players[0].alive = false;
+        camera.update(&board);
+        assert_eq!(None, camera.follow);
+
+        camera.reset();
+        assert_eq!(Camera::new(&board), camera);
+    }
+
+    #[test]
+    fn test_replay_feed() {
+        let dir = tempdir().unwrap();
+        let dir_string = dir.path().to_string_lossy().into_owned();
+        let mut feed = ReplayFeed::new(&dir_string);
+        feed.update().unwrap();
+        assert!(feed.board().is_none());
+        assert_eq!("Waiting for a game", feed.status());
+
+        let level = Level::default();
+        let mut stream = ReplayStream::create(&dir_string, &level, 5).unwrap();
+        let mut simulation = Simulation::new(&level, 5);
+        for turns in [vec![Direction::Up], vec![], vec![Direction::Left]] {
+            stream.record(simulation.tick, &turns).unwrap();
+            simulation.step(&turns);
+        }
+        // The stream is only searched for once a second
+        feed.last_search = None;
+        feed.update().unwrap();
+
+        let board = feed.board().unwrap();
+        assert_eq!(simulation.snake, board.players[0].snake);
+        assert_eq!(simulation.target, board.target);
+        assert_eq!("Game 1 - Tick 3", feed.status());
+        assert_eq!(vec!["Game 1".to_owned()], feed.names());
+        assert_eq!(
+            vec![LeaderboardEntry {
+                alive: true,
+                ..entry("Game 1", simulation.points, None, Some(0))
+            }],
+            feed.leaderboard()
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Key-value storage for data which has to survive a restart of the game. Natively, every key is
+//! stored as a file in [`SAVE_PATH`]. In the browser, the browser's local storage is used via the
+//! `snake_storage.js` plugin loaded by `index.html`.
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::constants::SAVE_PATH;
+
+pub fn load(key: &str) -> Result<Option<String>, StorageError> {
+    imp::load(key)
+}
+
+pub fn save(key: &str, value: &str) -> Result<(), StorageError> {
+    imp::save(key, value)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod imp {
+    use super::{StorageError, SAVE_PATH};
+    use std::fs::{create_dir_all, read_to_string, write};
+    use std::io::ErrorKind;
+
+    pub fn load(key: &str) -> Result<Option<String>, StorageError> {
+        match read_to_string(format!("{SAVE_PATH}/{key}.json")) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(key: &str, value: &str) -> Result<(), StorageError> {
+        create_dir_all(SAVE_PATH)?;
+        write(format!("{SAVE_PATH}/{key}.json"), value)?;
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod imp {
+    use super::StorageError;
+
+    extern "C" {
+        fn snake_storage_length(key: *const u8, key_length: usize) -> i32;
+        fn snake_storage_read(key: *const u8, key_length: usize, buffer: *mut u8, length: usize);
+        fn snake_storage_write(
+            key: *const u8,
+            key_length: usize,
+            value: *const u8,
+            value_length: usize,
+        );
+    }
+
+    /// Version check performed by the miniquad plugin loader.
+    #[no_mangle]
+    pub extern "C" fn snake_storage_crate_version() -> u32 {
+        1
+    }
+
+    pub fn load(key: &str) -> Result<Option<String>, StorageError> {
+        // SAFETY: The plugin only reads `key` and writes at most `length` bytes into `buffer`.
+        unsafe {
+            let Ok(length) = usize::try_from(snake_storage_length(key.as_ptr(), key.len())) else {
+                return Ok(None);
+            };
+            let mut buffer = vec![0; length];
+            snake_storage_read(key.as_ptr(), key.len(), buffer.as_mut_ptr(), length);
+            Ok(Some(String::from_utf8(buffer)?))
+        }
+    }
+
+    pub fn save(key: &str, value: &str) -> Result<(), StorageError> {
+        // SAFETY: The plugin only reads `key` and `value`.
+        unsafe { snake_storage_write(key.as_ptr(), key.len(), value.as_ptr(), value.len()) };
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum StorageError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Utf8Error(#[from] std::string::FromUtf8Error),
+}
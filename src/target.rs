@@ -1,26 +1,26 @@
 use crate::{
     constants::{TARGET_COLOR, TARGET_WIDTH},
     graphic_utils::render_scaled_square,
+    rng::Rng,
 };
 use euclid::Point2D;
-use macroquad::rand::gen_range;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Target {
     pub position: Point2D<i32, i32>,
 }
 
 impl Target {
-    pub fn new(obstacles: &[Point2D<i32, i32>], width: i32, height: i32) -> Self {
+    pub fn new(rng: &mut Rng, obstacles: &[Point2D<i32, i32>], width: i32, height: i32) -> Self {
         loop {
-            let position = Point2D::new(gen_range(0, width), gen_range(0, height));
+            let position = Point2D::new(rng.gen_range(0, width), rng.gen_range(0, height));
             if !obstacles.contains(&position) {
                 return Target { position };
             }
         }
     }
 
-    pub fn render(&mut self, scaling: (f32, f32)) {
+    pub fn render(&self, scaling: (f32, f32)) {
         render_scaled_square(TARGET_COLOR, self.position, TARGET_WIDTH, scaling);
     }
 }
@@ -39,7 +39,7 @@ mod tests {
             Target {
                 position: expected_position
             },
-            Target::new(obstacles, width, height)
+            Target::new(&mut Rng::new(0), obstacles, width, height)
         );
     }
 
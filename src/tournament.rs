@@ -0,0 +1,404 @@
+//! Lets the autopilot strategies play many seeded games of a level and summarizes how they did,
+//! to compare the strategies and to judge the difficulty of levels.
+
+use crate::cli::{parsed_value, ArgsError};
+use crate::constants::HEADLESS_MAX_TICKS;
+use crate::controller::{autoplay, GameResult, Strategy, STRATEGIES};
+use crate::level::Level;
+use crate::simulation::{CollisionCause, Simulation, COLLISION_CAUSES};
+use serde_json::{json, Value};
+
+pub const USAGE: &str = "\
+Usage: snake-tournament [options] [levels directory]
+
+Options:
+      --games <number>       Games per level and strategy (default 100)
+      --seed <number>        Seed of the first game, the following games count up (default 0)
+      --strategy <strategy>  Strategy to play with: greedy, shortest-path or hamiltonian. Can be
+                             given multiple times (default all)
+      --max-ticks <number>   Ticks after which a game is stopped
+      --format <csv|json>    Format of the report (default csv)
+  -h, --help                 Print this help";
+
+const DEFAULT_GAMES: u32 = 100;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Command-line arguments of the `snake-tournament` binary.
+#[derive(Debug, PartialEq)]
+pub struct TournamentArgs {
+    pub levels: Option<String>,
+    pub games: u32,
+    pub seed: u64,
+    /// All strategies if none was given.
+    pub strategies: Vec<Strategy>,
+    pub max_ticks: u32,
+    pub format: Format,
+    pub help: bool,
+}
+
+impl Default for TournamentArgs {
+    fn default() -> Self {
+        TournamentArgs {
+            levels: None,
+            games: DEFAULT_GAMES,
+            seed: 0,
+            strategies: STRATEGIES.to_vec(),
+            max_ticks: HEADLESS_MAX_TICKS,
+            format: Format::Csv,
+            help: false,
+        }
+    }
+}
+
+impl TournamentArgs {
+    /// Parses the arguments without the name of the program.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = TournamentArgs::default();
+        let mut strategies = vec![];
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let args = &mut args;
+            match arg.as_str() {
+                "--games" => {
+                    parsed.games = parsed_value(args, "--games", |games| games.parse().ok())?
+                }
+                "--seed" => parsed.seed = parsed_value(args, "--seed", |seed| seed.parse().ok())?,
+                "--strategy" => {
+                    strategies.push(parsed_value(args, "--strategy", Strategy::from_name)?)
+                }
+                "--max-ticks" => {
+                    parsed.max_ticks =
+                        parsed_value(args, "--max-ticks", |ticks| ticks.parse().ok())?;
+                }
+                "--format" => parsed.format = parsed_value(args, "--format", Format::from_name)?,
+                "-h" | "--help" => parsed.help = true,
+                _ if arg.starts_with('-') => return Err(ArgsError::UnknownOption(arg)),
+                _ if parsed.levels.is_none() => parsed.levels = Some(arg),
+                _ => return Err(ArgsError::UnexpectedArgument(arg)),
+            }
+        }
+        if !strategies.is_empty() {
+            parsed.strategies = strategies;
+        }
+        Ok(parsed)
+    }
+}
+
+/// Results of all games a strategy played on a level.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Standing {
+    pub level: String,
+    pub strategy: Strategy,
+    pub results: Vec<GameResult>,
+}
+
+impl Standing {
+    /// Plays `games` games of `level` with `strategy`, using the seeds `first_seed`,
+    /// `first_seed + 1` and so on. Games are stopped after `max_ticks` ticks.
+    pub fn play(
+        id: &str,
+        level: &Level,
+        strategy: Strategy,
+        games: u32,
+        first_seed: u64,
+        max_ticks: u32,
+    ) -> Self {
+        let results = (0..u64::from(games))
+            .map(|game| {
+                let mut simulation = Simulation::new(level, first_seed.wrapping_add(game));
+                let mut controller = strategy.controller(&simulation);
+                autoplay(&mut simulation, controller.as_mut(), max_ticks)
+            })
+            .collect();
+        Standing {
+            level: id.to_owned(),
+            strategy,
+            results,
+        }
+    }
+
+    pub fn mean_points(&self) -> f64 {
+        mean(self.results.iter().map(|result| f64::from(result.points)))
+    }
+
+    pub fn median_points(&self) -> f64 {
+        let mut points: Vec<i32> = self.results.iter().map(|result| result.points).collect();
+        points.sort_unstable();
+        match points.len() {
+            0 => 0.0,
+            len if len % 2 == 0 => {
+                (f64::from(points[len / 2 - 1]) + f64::from(points[len / 2])) / 2.0
+            }
+            len => f64::from(points[len / 2]),
+        }
+    }
+
+    /// Share of the games in which the level was completed, between 0 and 1.
+    pub fn win_rate(&self) -> f64 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+        self.wins() as f64 / self.results.len() as f64
+    }
+
+    /// Average number of ticks it took to complete the level, if it was completed at all.
+    pub fn mean_ticks_to_win(&self) -> Option<f64> {
+        (self.wins() > 0).then(|| {
+            mean(
+                self.results
+                    .iter()
+                    .filter(|result| result.won)
+                    .map(|result| f64::from(result.ticks)),
+            )
+        })
+    }
+
+    /// Number of games which ended with a collision of the given cause.
+    pub fn deaths(&self, cause: CollisionCause) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.collision == Some(cause))
+            .count()
+    }
+
+    /// Number of games which neither ended by winning nor by a collision.
+    pub fn timeouts(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| !result.won && result.collision.is_none())
+            .count()
+    }
+
+    fn wins(&self) -> usize {
+        self.results.iter().filter(|result| result.won).count()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let deaths: serde_json::Map<String, Value> = COLLISION_CAUSES
+            .iter()
+            .map(|cause| (cause.name().to_owned(), json!(self.deaths(*cause))))
+            .collect();
+        json!({
+            "level": self.level,
+            "strategy": self.strategy.name(),
+            "games": self.results.len(),
+            "mean_points": self.mean_points(),
+            "median_points": self.median_points(),
+            "win_rate": self.win_rate(),
+            "mean_ticks_to_win": self.mean_ticks_to_win(),
+            "deaths": deaths,
+            "timeouts": self.timeouts(),
+        })
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / f64::from(count)
+    }
+}
+
+/// Formats the standings as CSV with a header line. Death causes get a column each.
+pub fn to_csv(standings: &[Standing]) -> String {
+    let mut header = vec![
+        "level",
+        "strategy",
+        "games",
+        "mean_points",
+        "median_points",
+        "win_rate",
+        "mean_ticks_to_win",
+    ];
+    header.extend(COLLISION_CAUSES.iter().map(|cause| cause.name()));
+    header.push("timeouts");
+
+    let mut csv = header.join(",") + "\n";
+    for standing in standings {
+        let mut row = vec![
+            csv_field(&standing.level),
+            standing.strategy.name().to_owned(),
+            standing.results.len().to_string(),
+            format!("{:.2}", standing.mean_points()),
+            format!("{:.1}", standing.median_points()),
+            format!("{:.3}", standing.win_rate()),
+            standing
+                .mean_ticks_to_win()
+                .map_or_else(String::new, |ticks| format!("{ticks:.1}")),
+        ];
+        row.extend(
+            COLLISION_CAUSES
+                .iter()
+                .map(|cause| standing.deaths(*cause).to_string()),
+        );
+        row.push(standing.timeouts().to_string());
+        csv += &(row.join(",") + "\n");
+    }
+    csv
+}
+
+/// Quotes a field if it contains characters with a meaning in CSV.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+pub fn to_json(standings: &[Standing]) -> Value {
+    Value::Array(standings.iter().map(Standing::to_json).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::borders::Borders;
+    use test_case::test_case;
+
+    fn parse(args: &str) -> Result<TournamentArgs, ArgsError> {
+        TournamentArgs::parse(args.split_whitespace().map(str::to_owned))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(TournamentArgs::default(), parse("").unwrap());
+        assert_eq!(
+            TournamentArgs {
+                levels: Some("levels".to_owned()),
+                games: 10,
+                seed: 42,
+                strategies: vec![Strategy::Greedy, Strategy::Hamiltonian],
+                max_ticks: 500,
+                format: Format::Json,
+                help: false,
+            },
+            parse(
+                "--games 10 --seed 42 --strategy greedy --strategy hamiltonian --max-ticks 500 \
+                 --format json levels"
+            )
+            .unwrap()
+        );
+        assert!(parse("-h").unwrap().help);
+    }
+
+    #[test_case("--games many" => ArgsError::InvalidValue { option: "--games", value: "many".to_owned() })]
+    #[test_case("--games -1" => ArgsError::InvalidValue { option: "--games", value: "-1".to_owned() })]
+    #[test_case("--format xml" => ArgsError::InvalidValue { option: "--format", value: "xml".to_owned() })]
+    #[test_case("--strategy random" => ArgsError::InvalidValue { option: "--strategy", value: "random".to_owned() })]
+    #[test_case("--max-ticks" => ArgsError::MissingValue("--max-ticks"))]
+    #[test_case("--fast" => ArgsError::UnknownOption("--fast".to_owned()))]
+    #[test_case("a b" => ArgsError::UnexpectedArgument("b".to_owned()))]
+    fn test_invalid_args(args: &str) -> ArgsError {
+        parse(args).unwrap_err()
+    }
+
+    fn result(points: i32, ticks: u32, won: bool, collision: Option<CollisionCause>) -> GameResult {
+        GameResult {
+            points,
+            ticks,
+            won,
+            collision,
+        }
+    }
+
+    fn standing() -> Standing {
+        Standing {
+            level: "pack/my, pack/1.json".to_owned(),
+            strategy: Strategy::Greedy,
+            results: vec![
+                result(10, 100, true, None),
+                result(3, 40, false, Some(CollisionCause::SelfBite)),
+                result(10, 140, true, None),
+                result(5, 1000, false, None),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_statistics() {
+        let standing = standing();
+        assert_eq!(7.0, standing.mean_points());
+        assert_eq!(7.5, standing.median_points());
+        assert_eq!(0.5, standing.win_rate());
+        assert_eq!(Some(120.0), standing.mean_ticks_to_win());
+        assert_eq!(1, standing.deaths(CollisionCause::SelfBite));
+        assert_eq!(0, standing.deaths(CollisionCause::Wall));
+        assert_eq!(1, standing.timeouts());
+    }
+
+    #[test_case(&[4, 1, 3] => 3.0)]
+    #[test_case(&[4, 1] => 2.5)]
+    #[test_case(&[] => 0.0)]
+    fn test_median_points(points: &[i32]) -> f64 {
+        Standing {
+            results: points
+                .iter()
+                .map(|points| result(*points, 0, false, None))
+                .collect(),
+            ..standing()
+        }
+        .median_points()
+    }
+
+    #[test]
+    fn test_to_csv() {
+        assert_eq!(
+            "level,strategy,games,mean_points,median_points,win_rate,mean_ticks_to_win,wall,\
+             obstacle,self_bite,other_snake,timeouts\n\
+             \"pack/my, pack/1.json\",greedy,4,7.00,7.5,0.500,120.0,0,0,1,0,1\n",
+            to_csv(&[standing()])
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            json!([{
+                "level": "pack/my, pack/1.json",
+                "strategy": "greedy",
+                "games": 4,
+                "mean_points": 7.0,
+                "median_points": 7.5,
+                "win_rate": 0.5,
+                "mean_ticks_to_win": 120.0,
+                "deaths": {"wall": 0, "obstacle": 0, "self_bite": 1, "other_snake": 0},
+                "timeouts": 1,
+            }]),
+            to_json(&[standing()])
+        );
+    }
+
+    #[test]
+    fn test_play_is_reproducible() {
+        let level = Level {
+            target_points: Some(5),
+            borders: Borders::SOLID,
+            ..Level::default()
+        };
+        let standing = Standing::play("test", &level, Strategy::ShortestPath, 3, 7, 1000);
+        assert_eq!(3, standing.results.len());
+        assert_eq!(1.0, standing.win_rate());
+        assert_eq!(
+            standing,
+            Standing::play("test", &level, Strategy::ShortestPath, 3, 7, 1000)
+        );
+    }
+}
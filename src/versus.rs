@@ -0,0 +1,451 @@
+//! Simulation of several snakes competing for the same targets on one map, and the rounds of a
+//! match between them.
+
+use crate::borders::Borders;
+use crate::level::Level;
+use crate::rng::Rng;
+use crate::simulation::CollisionCause;
+use crate::snake::{Direction, Snake};
+use crate::target::Target;
+use euclid::Point2D;
+
+pub const MAX_PLAYERS: usize = 8;
+
+#[derive(Debug, PartialEq)]
+pub struct Player {
+    pub snake: Snake,
+    /// Points of the current round.
+    pub points: i32,
+    /// Snakes which collided are removed from the map until the next round.
+    pub alive: bool,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum VersusEvent {
+    TargetHit {
+        player: usize,
+    },
+    /// The snake of `player` collided at `cell`. For a wall, this is the field in front of it.
+    Collision {
+        player: usize,
+        cause: CollisionCause,
+        cell: Point2D<i32, i32>,
+    },
+    /// The round ended, either because a player reached the target points or because at most one
+    /// snake is left. `winner` is `None` if all snakes collided at once.
+    RoundOver {
+        winner: Option<usize>,
+    },
+}
+
+/// A single round of a versus game. Like [`crate::simulation::Simulation`], it only advances when
+/// [`Versus::step`] is called, and all random decisions are derived from the seed.
+#[derive(Debug)]
+pub struct Versus {
+    pub players: Vec<Player>,
+    pub target: Target,
+    pub obstacles: Vec<Point2D<i32, i32>>,
+    pub width: i32,
+    pub height: i32,
+    pub borders: Borders,
+    /// Points a player needs to win the round early.
+    pub target_points: Option<i32>,
+    pub tick: u32,
+    pub round_over: bool,
+    pub rng: Rng,
+}
+
+impl Versus {
+    /// Creates a round for `players` snakes on `level`. The snakes start spread over the map
+    /// instead of at the start position of the level.
+    pub fn new(level: &Level, players: usize, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let count = players.clamp(1, MAX_PLAYERS);
+        let players = (0..count)
+            .map(|player| {
+                let (position, direction) = spawn_point(level, player, count);
+                Player {
+                    snake: Snake::new(
+                        &mut rng,
+                        Some(position),
+                        Some(direction),
+                        level.width,
+                        level.height,
+                    ),
+                    points: 0,
+                    alive: true,
+                }
+            })
+            .collect();
+        let mut versus = Versus {
+            players,
+            target: Target {
+                position: Point2D::zero(),
+            },
+            obstacles: level.obstacles.clone(),
+            width: level.width,
+            height: level.height,
+            borders: level.borders,
+            target_points: level.target_points,
+            tick: 0,
+            round_over: false,
+            rng,
+        };
+        versus.target = versus.new_target();
+        versus
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
+    /// Applies the turns of the players and moves all snakes which are still alive by one field.
+    /// Collisions are determined after all snakes moved, so snakes meeting head to head both
+    /// collide.
+    pub fn step(&mut self, turns: &[(usize, Direction)]) -> Vec<VersusEvent> {
+        if self.round_over {
+            return vec![];
+        }
+        for (player, direction) in turns {
+            if let Some(player) = self.players.get_mut(*player).filter(|player| player.alive) {
+                player.snake.set_direction(*direction);
+            }
+        }
+
+        let eater = self.players.iter().position(|player| {
+            player.alive && player.snake.position.contains(&self.target.position)
+        });
+        if eater.is_some() {
+            self.target = self.new_target();
+        }
+        for (i, player) in self.players.iter_mut().enumerate() {
+            if player.alive {
+                player
+                    .snake
+                    .move_snake(eater == Some(i), self.width, self.height, &self.borders);
+            }
+        }
+        self.tick += 1;
+
+        let mut events = vec![];
+        let collisions: Vec<(usize, CollisionCause)> = (0..self.players.len())
+            .filter_map(|i| Some((i, self.collision_cause(i)?)))
+            .collect();
+        for (player, cause) in collisions {
+            self.players[player].alive = false;
+            let snake = &self.players[player].snake;
+            let head = snake.position[0];
+            let cell = if cause == CollisionCause::Wall {
+                let (dx, dy) = snake.current_direction.offset();
+                Point2D::new(head.x - dx, head.y - dy)
+            } else {
+                head
+            };
+            events.push(VersusEvent::Collision {
+                player,
+                cause,
+                cell,
+            });
+        }
+
+        let mut winner = None;
+        if let Some(eater) = eater.filter(|eater| self.players[*eater].alive) {
+            let player = &mut self.players[eater];
+            player.points += 1;
+            events.push(VersusEvent::TargetHit { player: eater });
+            if self
+                .target_points
+                .is_some_and(|target_points| player.points >= target_points)
+            {
+                winner = Some(Some(eater));
+            }
+        }
+        let alive: Vec<usize> = (0..self.players.len())
+            .filter(|i| self.players[*i].alive)
+            .collect();
+        if winner.is_none() && (alive.is_empty() || (self.players.len() > 1 && alive.len() == 1)) {
+            winner = Some(alive.first().copied());
+        }
+        if let Some(winner) = winner {
+            self.round_over = true;
+            events.push(VersusEvent::RoundOver { winner });
+        }
+        events
+    }
+
+    /// Returns what the head of the snake of `player` collides with at the moment, if anything.
+    pub fn collision_cause(&self, player: usize) -> Option<CollisionCause> {
+        let snake = &self.players[player].snake;
+        if !self.players[player].alive {
+            return None;
+        }
+        let head = snake.position[0];
+        if snake.is_out_of_bounds(self.width, self.height) {
+            Some(CollisionCause::Wall)
+        } else if self.obstacles.contains(&head) {
+            Some(CollisionCause::Obstacle)
+        } else if snake.is_overlapping() {
+            Some(CollisionCause::SelfBite)
+        } else if self
+            .players
+            .iter()
+            .enumerate()
+            .any(|(i, other)| i != player && other.alive && other.snake.position.contains(&head))
+        {
+            Some(CollisionCause::OtherSnake)
+        } else {
+            None
+        }
+    }
+
+    /// Places a new target on a field which is neither an obstacle nor part of a snake, as long
+    /// as there is such a field.
+    fn new_target(&mut self) -> Target {
+        let mut blocked = self.obstacles.clone();
+        blocked.extend(
+            self.players
+                .iter()
+                .filter(|player| player.alive)
+                .flat_map(|player| player.snake.position.iter().copied()),
+        );
+        let free_fields = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Point2D::new(x, y)))
+            .any(|field| !blocked.contains(&field));
+        if !free_fields {
+            blocked.clone_from(&self.obstacles);
+        }
+        Target::new(&mut self.rng, &blocked, self.width, self.height)
+    }
+}
+
+/// Start of the snake of `player`. The snakes start on separate rows, alternately on the left
+/// heading right and on the right heading left. A start on an obstacle moves on to the next free
+/// field.
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+fn spawn_point(level: &Level, player: usize, players: usize) -> (Point2D<i32, i32>, Direction) {
+    let row = level.height * (player as i32 + 1) / (players as i32 + 1);
+    let (column, direction) = if player.is_multiple_of(2) {
+        (level.width / 4, Direction::Right)
+    } else {
+        (level.width - 1 - level.width / 4, Direction::Left)
+    };
+    let fields = level.width * level.height;
+    let position = (0..fields)
+        .map(|offset| {
+            let index = (row * level.width + column + offset) % fields;
+            Point2D::new(index % level.width, index / level.width)
+        })
+        .find(|field| !level.obstacles.contains(field))
+        .unwrap_or(Point2D::new(column, row));
+    (position, direction)
+}
+
+/// Round wins of the players of a match. The first player to win [`Match::rounds_to_win`] rounds
+/// wins the match.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Match {
+    pub wins: Vec<u32>,
+    pub rounds_to_win: u32,
+    /// Number of rounds played, including draws.
+    pub rounds: u32,
+}
+
+impl Match {
+    pub fn new(players: usize, rounds_to_win: u32) -> Self {
+        Match {
+            wins: vec![0; players],
+            rounds_to_win,
+            rounds: 0,
+        }
+    }
+
+    pub fn record(&mut self, winner: Option<usize>) {
+        self.rounds += 1;
+        if let Some(wins) = winner.and_then(|winner| self.wins.get_mut(winner)) {
+            *wins += 1;
+        }
+    }
+
+    pub fn winner(&self) -> Option<usize> {
+        self.wins
+            .iter()
+            .position(|wins| *wins >= self.rounds_to_win)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn versus(width: i32, height: i32, borders: Borders) -> Versus {
+        Versus::new(
+            &Level {
+                width,
+                height,
+                borders,
+                target_points: Some(3),
+                ..Level::default()
+            },
+            2,
+            0,
+        )
+    }
+
+    fn place(versus: &mut Versus, player: usize, position: &[(i32, i32)], direction: Direction) {
+        let snake = &mut versus.players[player].snake;
+        snake.position = position.iter().map(|(x, y)| Point2D::new(*x, *y)).collect();
+        snake.current_direction = direction;
+    }
+
+    #[test]
+    fn test_spawn_points() {
+        let versus = versus(20, 20, Borders::WRAP);
+        assert_eq!(vec![Point2D::new(5, 6)], versus.players[0].snake.position);
+        assert_eq!(Direction::Right, versus.players[0].snake.current_direction);
+        assert_eq!(vec![Point2D::new(14, 13)], versus.players[1].snake.position);
+        assert_eq!(Direction::Left, versus.players[1].snake.current_direction);
+    }
+
+    #[test]
+    fn test_spawn_point_avoids_obstacles() {
+        let level = Level {
+            obstacles: vec![Point2D::new(5, 6), Point2D::new(6, 6)],
+            ..Level::default()
+        };
+        let versus = Versus::new(&level, 2, 0);
+        assert_eq!(vec![Point2D::new(7, 6)], versus.players[0].snake.position);
+    }
+
+    #[test]
+    fn test_head_to_head_is_a_draw() {
+        let mut versus = versus(10, 10, Borders::WRAP);
+        place(&mut versus, 0, &[(3, 5), (2, 5)], Direction::Right);
+        place(&mut versus, 1, &[(5, 5), (6, 5)], Direction::Left);
+        versus.target.position = Point2D::new(0, 0);
+
+        let events = versus.step(&[]);
+        assert_eq!(
+            vec![
+                VersusEvent::Collision {
+                    player: 0,
+                    cause: CollisionCause::OtherSnake,
+                    cell: Point2D::new(4, 5)
+                },
+                VersusEvent::Collision {
+                    player: 1,
+                    cause: CollisionCause::OtherSnake,
+                    cell: Point2D::new(4, 5)
+                },
+                VersusEvent::RoundOver { winner: None },
+            ],
+            events
+        );
+        assert!(versus.step(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_swapping_heads_collides_both() {
+        let mut versus = versus(10, 10, Borders::WRAP);
+        place(&mut versus, 0, &[(4, 5), (3, 5)], Direction::Right);
+        place(&mut versus, 1, &[(5, 5), (6, 5)], Direction::Left);
+        versus.target.position = Point2D::new(0, 0);
+
+        let events = versus.step(&[]);
+        assert_eq!(
+            Some(&VersusEvent::RoundOver { winner: None }),
+            events.last()
+        );
+    }
+
+    #[test]
+    fn test_head_to_body_collides_attacker() {
+        let mut versus = versus(10, 10, Borders::WRAP);
+        place(&mut versus, 0, &[(4, 4)], Direction::Down);
+        place(&mut versus, 1, &[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        versus.target.position = Point2D::new(0, 0);
+
+        let events = versus.step(&[(1, Direction::Up)]);
+        assert_eq!(
+            vec![
+                VersusEvent::Collision {
+                    player: 0,
+                    cause: CollisionCause::OtherSnake,
+                    cell: Point2D::new(4, 5)
+                },
+                VersusEvent::RoundOver { winner: Some(1) },
+            ],
+            events
+        );
+        assert!(!versus.players[0].alive);
+        assert!(versus.players[1].alive);
+        assert_eq!(Point2D::new(5, 4), versus.players[1].snake.position[0]);
+    }
+
+    #[test]
+    fn test_target_points_win_round() {
+        let mut versus = versus(10, 10, Borders::WRAP);
+        place(&mut versus, 0, &[(1, 1)], Direction::Right);
+        place(&mut versus, 1, &[(8, 8)], Direction::Left);
+        versus.players[0].points = 2;
+        versus.target.position = Point2D::new(1, 1);
+
+        let events = versus.step(&[]);
+        assert_eq!(
+            vec![
+                VersusEvent::TargetHit { player: 0 },
+                VersusEvent::RoundOver { winner: Some(0) },
+            ],
+            events
+        );
+        assert_eq!(2, versus.players[0].snake.position.len());
+        assert!(!versus.players[0]
+            .snake
+            .position
+            .contains(&versus.target.position));
+    }
+
+    #[test]
+    fn test_wall_collision() {
+        let mut versus = versus(10, 10, Borders::SOLID);
+        place(&mut versus, 0, &[(9, 1)], Direction::Right);
+        versus.target.position = Point2D::new(0, 0);
+
+        let events = versus.step(&[]);
+        assert_eq!(
+            vec![
+                VersusEvent::Collision {
+                    player: 0,
+                    cause: CollisionCause::Wall,
+                    cell: Point2D::new(9, 1)
+                },
+                VersusEvent::RoundOver { winner: Some(1) },
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn test_same_seed_same_round() {
+        let level = Level::default();
+        let turns = [(0, Direction::Down), (1, Direction::Up)];
+        let mut a = Versus::new(&level, 4, 9);
+        let mut b = Versus::new(&level, 4, 9);
+        for _ in 0..30 {
+            assert_eq!(a.step(&turns), b.step(&turns));
+        }
+        assert_eq!(a.players, b.players);
+        assert_eq!(a.target, b.target);
+    }
+
+    #[test_case(&[Some(0), Some(1), None, Some(0)] => None)]
+    #[test_case(&[Some(0), Some(1), Some(1), Some(0), Some(1)] => Some(1))]
+    fn test_match_winner(winners: &[Option<usize>]) -> Option<usize> {
+        let mut versus_match = Match::new(2, 3);
+        for winner in winners {
+            versus_match.record(*winner);
+        }
+        assert_eq!(winners.len() as u32, versus_match.rounds);
+        versus_match.winner()
+    }
+}